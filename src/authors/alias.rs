@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// Another name an author is known by, such as a pen name or a variant
+// spelling like "Tolkien, J.R.R.". Names are unique across all authors.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Alias {
+    #[serde(skip_deserializing)]
+    pub id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub author_id: Option<i64>,
+    pub name: Option<String>,
+    // Free text, e.g. `pen name`.
+    pub kind: Option<String>,
+}
+
+impl Alias {
+    pub fn normalize(&mut self) {
+        self.name = self.name.as_deref().map(|n| n.trim().to_string());
+        self.kind = self.kind.as_deref().map(|k| k.trim().to_lowercase());
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.name.as_deref() {
+            None | Some("") => Err("name is required".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,8 +1,199 @@
+use super::super::books::publication_date;
+use super::super::constants::AUTHORS_TABLE;
+use super::super::resources::filter::{param, Clause};
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use super::authority::Scheme;
+use super::authors_queries;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use serde_json::Map;
+use sqlx::{types::Json, FromRow};
 
 #[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
 pub struct Author {
     pub id: Option<i64>,
     pub name: Option<String>,
+    // Year, year-month or full date, like publication dates.
+    pub birth_date: Option<String>,
+    pub death_date: Option<String>,
+    // ISO 3166-1 alpha-2 country code, e.g. `GB`.
+    pub nationality: Option<String>,
+    // Markdown, rendered by `GET /authors/{id}/biography`.
+    pub biography: Option<String>,
+    // Authority identifiers, stored as normalized by `Scheme::normalize`.
+    pub isni: Option<String>,
+    pub viaf: Option<String>,
+    pub orcid: Option<String>,
+    pub wikidata: Option<String>,
+    // Custom fields, declared through `/metadata/fields`.
+    pub metadata: Option<Json<Map<String, serde_json::Value>>>,
+}
+
+impl Author {
+    fn identifier(&mut self, scheme: Scheme) -> &mut Option<String> {
+        match scheme {
+            Scheme::Isni => &mut self.isni,
+            Scheme::Viaf => &mut self.viaf,
+            Scheme::Orcid => &mut self.orcid,
+            Scheme::Wikidata => &mut self.wikidata,
+        }
+    }
+}
+
+impl Resource for Author {
+    const PATH: &'static str = "/authors";
+    const TABLE: &'static str = AUTHORS_TABLE;
+    const FIELDS: &'static [&'static str] = &[
+        "name",
+        "birth_date",
+        "death_date",
+        "nationality",
+        "biography",
+        "isni",
+        "viaf",
+        "orcid",
+        "wikidata",
+        "metadata",
+    ];
+    const FILTERABLE: &'static [&'static str] = &["nationality"];
+    const SORTABLE: &'static [&'static str] = &["id", "name", "birth_date", "death_date"];
+    const UNIQUE: &'static [&'static str] = &["isni", "viaf", "orcid", "wikidata"];
+    const PARAMS: &'static [&'static str] = &["name", "born_after", "born_before", "alive_in"];
+    const METADATA: bool = true;
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "name" => Value::from(self.name.clone()),
+            "birth_date" => Value::from(self.birth_date.clone()),
+            "death_date" => Value::from(self.death_date.clone()),
+            "nationality" => Value::from(self.nationality.clone()),
+            "biography" => Value::from(self.biography.clone()),
+            "isni" => Value::from(self.isni.clone()),
+            "viaf" => Value::from(self.viaf.clone()),
+            "orcid" => Value::from(self.orcid.clone()),
+            "wikidata" => Value::from(self.wikidata.clone()),
+            "metadata" => Value::from(
+                self.metadata
+                    .as_ref()
+                    .and_then(|m| serde_json::to_string(&m.0).ok()),
+            ),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "name" => self.name = value.into(),
+            "birth_date" => self.birth_date = value.into(),
+            "death_date" => self.death_date = value.into(),
+            "nationality" => self.nationality = value.into(),
+            "biography" => self.biography = value.into(),
+            "isni" => self.isni = value.into(),
+            "viaf" => self.viaf = value.into(),
+            "orcid" => self.orcid = value.into(),
+            "wikidata" => self.wikidata = value.into(),
+            "metadata" => {
+                self.metadata = Option::<String>::from(value)
+                    .and_then(|m| serde_json::from_str(&m).ok())
+                    .map(Json)
+            }
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        for date in [&mut self.birth_date, &mut self.death_date] {
+            *date = date.as_deref().map(|d| d.trim().to_string());
+        }
+        self.nationality = self.nationality.as_deref().map(|n| n.trim().to_uppercase());
+        for scheme in Scheme::ALL {
+            let id = self.identifier(scheme);
+            if let Some(Ok(normalized)) = id.as_deref().map(|v| scheme.normalize(v)) {
+                *id = Some(normalized);
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("name", &self.name)?;
+        if let Some(d) = &self.birth_date {
+            publication_date::validate("birth_date", d)?;
+        }
+        if let Some(d) = &self.death_date {
+            publication_date::validate("death_date", d)?;
+        }
+        if let (Some(birth), Some(death)) = (&self.birth_date, &self.death_date) {
+            let n = birth.len().min(death.len());
+            if death[..n] < birth[..n] {
+                return Err(format!(
+                    "death_date {} is before birth_date {}",
+                    death, birth
+                ));
+            }
+        }
+        if let Some(n) = &self.nationality {
+            if n.len() != 2 || !n.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(format!(
+                    "nationality must be an ISO 3166 country code like GB (got {})",
+                    n
+                ));
+            }
+        }
+        for (scheme, id) in [
+            (Scheme::Isni, &self.isni),
+            (Scheme::Viaf, &self.viaf),
+            (Scheme::Orcid, &self.orcid),
+            (Scheme::Wikidata, &self.wikidata),
+        ] {
+            if let Some(id) = id {
+                scheme.normalize(id)?;
+            }
+        }
+        Ok(())
+    }
+
+    // `alive_in=1900` lists the authors born in or before 1900 who had not died
+    // before it; authors without a birth date are left out.
+    fn clauses(params: &[(String, String)]) -> Result<Vec<Clause>, String> {
+        let mut clauses = Vec::new();
+        // Names match aliases too, see `Alias`.
+        if let Some(name) = param(params, "name") {
+            clauses.push(authors_queries::authors_named_clause(name));
+        }
+        let year = |key: &str, v: &str| {
+            publication_date::validate(key, v).map(|_| Value::Text(v.to_string()))
+        };
+
+        for (key, op) in [("born_after", ">="), ("born_before", "<=")] {
+            if let Some(v) = param(params, key) {
+                clauses.push(Clause {
+                    sql: format!("substr(birth_date, 1, {}) {} ?", v.len(), op),
+                    binds: vec![year(key, v)?],
+                });
+            }
+        }
+        if let Some(v) = param(params, "alive_in") {
+            let bound = year("alive_in", v)?;
+            clauses.push(Clause {
+                sql: format!(
+                    "substr(birth_date, 1, {n}) <= ? AND \
+                     (death_date IS NULL OR substr(death_date, 1, {n}) >= ?)",
+                    n = v.len()
+                ),
+                binds: vec![bound.clone(), bound],
+            });
+        }
+
+        Ok(clauses)
+    }
 }
@@ -0,0 +1,142 @@
+// Authority identifiers tie an author to the records of other catalogues.
+// Each scheme is stored in its canonical form so that lookups can compare
+// them as plain text.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Isni,
+    Viaf,
+    Orcid,
+    Wikidata,
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 4] = [Scheme::Isni, Scheme::Viaf, Scheme::Orcid, Scheme::Wikidata];
+
+    pub fn parse(scheme: &str) -> Result<Scheme, String> {
+        Scheme::ALL
+            .into_iter()
+            .find(|s| s.as_str() == scheme)
+            .ok_or_else(|| {
+                format!(
+                    "scheme must be one of isni, viaf, orcid, wikidata (got {})",
+                    scheme
+                )
+            })
+    }
+
+    // Also the name of the column the identifier is kept in.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Isni => "isni",
+            Scheme::Viaf => "viaf",
+            Scheme::Orcid => "orcid",
+            Scheme::Wikidata => "wikidata",
+        }
+    }
+
+    // Checks an identifier and returns its canonical form: ISNIs as 16 plain
+    // characters, ORCIDs hyphenated in groups of four, VIAF ids as digits and
+    // Wikidata ids as `Q` followed by digits.
+    pub fn normalize(&self, value: &str) -> Result<String, String> {
+        let err =
+            |expected: &str| format!("{} must be {} (got {})", self.as_str(), expected, value);
+        let compact: String = value
+            .chars()
+            .filter(|c| *c != '-' && *c != ' ')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        match self {
+            Scheme::Isni | Scheme::Orcid => {
+                if !is_mod11_2(&compact) {
+                    return Err(err(
+                        "16 digits, the last one may be X, with a valid check digit",
+                    ));
+                }
+                if *self == Scheme::Isni {
+                    return Ok(compact);
+                }
+                let groups: Vec<&str> = (0..4).map(|i| &compact[i * 4..i * 4 + 4]).collect();
+                Ok(groups.join("-"))
+            }
+            // VIAF ids carry no check digit, only their format can be checked.
+            Scheme::Viaf => {
+                if compact.is_empty()
+                    || compact.len() > 22
+                    || !compact.chars().all(|c| c.is_ascii_digit())
+                {
+                    return Err(err("up to 22 digits"));
+                }
+                match compact.trim_start_matches('0') {
+                    "" => Err(err("up to 22 digits")),
+                    id => Ok(id.to_string()),
+                }
+            }
+            // Neither do Wikidata ids.
+            Scheme::Wikidata => match compact.strip_prefix('Q') {
+                Some(n)
+                    if !n.is_empty()
+                        && !n.starts_with('0')
+                        && n.chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    Ok(compact)
+                }
+                _ => Err(err("Q followed by a number, like Q42")),
+            },
+        }
+    }
+}
+
+// ISO 7064 MOD 11-2, as used by ISNI and ORCID.
+fn is_mod11_2(id: &str) -> bool {
+    if id.len() != 16 || !id.is_ascii() || !id[..15].chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let total = id[..15]
+        .chars()
+        .fold(0, |t, c| (t + c.to_digit(10).unwrap()) * 2);
+    let check = match (12 - total % 11) % 11 {
+        10 => 'X',
+        d => char::from_digit(d, 10).unwrap(),
+    };
+    id.ends_with(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            Scheme::Isni.normalize("0000 0001 2281 955x").unwrap(),
+            "000000012281955X"
+        );
+        assert_eq!(
+            Scheme::Orcid.normalize("0000000218250097").unwrap(),
+            "0000-0002-1825-0097"
+        );
+        assert_eq!(Scheme::Viaf.normalize("0113230702").unwrap(), "113230702");
+        assert_eq!(Scheme::Wikidata.normalize("q42").unwrap(), "Q42");
+    }
+
+    #[test]
+    fn test_normalize_rejects_bad_identifiers() {
+        assert!(Scheme::Isni.normalize("0000 0001 2281 9551").is_err());
+        assert!(Scheme::Isni.normalize("0000 0001 2281").is_err());
+        assert!(Scheme::Isni.normalize("00000000000000é").is_err());
+        assert!(Scheme::Orcid.normalize("0000-0000-0000-00é").is_err());
+        assert!(Scheme::Orcid.normalize("0000-0002-1825-0098").is_err());
+        assert!(Scheme::Viaf.normalize("12a").is_err());
+        assert!(Scheme::Viaf.normalize("").is_err());
+        assert!(Scheme::Wikidata.normalize("Q").is_err());
+        assert!(Scheme::Wikidata.normalize("Q042").is_err());
+        assert!(Scheme::Wikidata.normalize("P31").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Scheme::parse("orcid").unwrap(), Scheme::Orcid);
+        assert!(Scheme::parse("lccn").is_err());
+    }
+}
@@ -1,23 +1,66 @@
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use serde::Deserialize;
 use sqlx::{Pool, Sqlite};
 
-use super::super::responses::CreateResponse;
-use super::super::responses::CustomError;
+use super::super::metadata::metadata;
+use super::super::resources::filter::Filters;
+use super::super::resources::resource::Resource;
+use super::super::resources::resources;
+use super::super::resources::resources_repository::Repository;
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::alias::Alias;
 use super::author::Author;
-use super::authors_db;
-use super::filter::Filters;
+use super::authority::Scheme;
+use super::authors_db::{self, Change, Merge};
+use super::markdown;
 
 pub fn config_authors(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_authors)
-        .service(get_author)
-        .service(create_author)
-        .service(update_author)
-        .service(delete_author);
+    cfg.service(get_author_by_identifier)
+        .service(get_author_biography)
+        .service(get_aliases)
+        .service(create_alias)
+        .service(delete_alias)
+        .service(merge_author)
+        .service(
+            web::resource(Author::PATH)
+                .route(web::get().to(metadata::get_all::<Author>))
+                .route(web::post().to(metadata::create::<Author>)),
+        )
+        .service(
+            resources::item::<Author>()
+                .route(web::get().to(get_author))
+                .route(web::put().to(metadata::update::<Author>))
+                .route(web::delete().to(resources::delete::<Author>)),
+        );
+}
+
+#[derive(Deserialize)]
+pub struct MergeBody {
+    pub target_id: i64,
 }
 
-#[get("/authors")]
-async fn get_authors(filter: web::Query<Filters>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = authors_db::get_authors(filter, pool).await;
+// Ids of authors merged into another one redirect to it.
+async fn get_author(
+    id: web::Path<i64>,
+    authors: web::Data<dyn Repository<Author>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let r = match authors.get_one(id).await {
+        Err(sqlx::Error::RowNotFound) => match authors_db::get_redirect(pool.get_ref(), id).await {
+            Ok(Some(target)) => {
+                return HttpResponse::MovedPermanently()
+                    .insert_header(("Location", format!("/authors/{}", target)))
+                    .json(CustomError::message(format!(
+                        "author {} was merged into author {}",
+                        id, target
+                    )))
+            }
+            Ok(None) => Err(sqlx::Error::RowNotFound),
+            Err(e) => Err(e),
+        },
+        r => r,
+    };
 
     match r {
         Ok(v) => HttpResponse::Ok().json(v),
@@ -25,9 +68,9 @@ async fn get_authors(filter: web::Query<Filters>, pool: web::Data<Pool<Sqlite>>)
     }
 }
 
-#[get("/authors/{id}")]
-async fn get_author(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = authors_db::get_author(pool, req.match_info().query("id").parse().unwrap()).await;
+#[get("/authors/{id}/aliases")]
+async fn get_aliases(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = authors_db::get_aliases(pool.get_ref(), id.into_inner()).await;
 
     match r {
         Ok(v) => HttpResponse::Ok().json(v),
@@ -35,43 +78,98 @@ async fn get_author(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Res
     }
 }
 
-#[post("/authors")]
-async fn create_author(json: web::Json<Author>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = authors_db::create_author(pool, json.clone()).await;
+#[post("/authors/{id}/aliases")]
+async fn create_alias(
+    id: web::Path<i64>,
+    json: web::Json<Alias>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let mut alias = json.into_inner();
+    alias.normalize();
+    if let Err(e) = alias.validate() {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = authors_db::create_alias(pool.get_ref(), id.into_inner(), alias).await;
 
     match r {
-        Ok(r) => HttpResponse::Created().json(CreateResponse {
-            id: r.last_insert_rowid(),
+        Ok(Change::Done(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(Change::Duplicate(owner)) => HttpResponse::Conflict().json(ConflictResponse {
+            message: "an author already has this alias".to_string(),
+            id: owner,
+            location: format!("/authors/{}", owner),
         }),
         Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
     }
 }
 
-#[put("/authors/{id}")]
-async fn update_author(
-    req: HttpRequest,
-    json: web::Json<Author>,
+#[delete("/authors/{author_id}/aliases/{id}")]
+async fn delete_alias(
+    path: web::Path<(i64, i64)>,
     pool: web::Data<Pool<Sqlite>>,
 ) -> impl Responder {
-    let r = authors_db::update_author(
-        json.clone(),
-        pool,
-        req.match_info().query("id").parse().unwrap(),
-    )
-    .await;
+    let (author_id, id) = path.into_inner();
+    let r = authors_db::delete_alias(pool.get_ref(), author_id, id).await;
 
     match r {
-        Ok(_) => HttpResponse::Ok().json("Updated"),
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
         Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
     }
 }
 
-#[delete("/authors/{id}")]
-async fn delete_author(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = authors_db::delete_author(pool, req.match_info().query("id").parse().unwrap()).await;
+// Folds the author into `target_id`, see `authors_db::merge_author`.
+#[post("/authors/{id}/merge")]
+async fn merge_author(
+    id: web::Path<i64>,
+    json: web::Json<MergeBody>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let r = authors_db::merge_author(pool.get_ref(), id.into_inner(), json.target_id).await;
 
     match r {
-        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Ok(Merge::Done(v)) => HttpResponse::Ok().json(v),
+        Ok(Merge::Refused(message)) => HttpResponse::Conflict().json(CustomError::message(message)),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The identifier may be given in any form its scheme accepts, e.g. an ISNI
+// with or without spaces.
+#[get("/authors/by-identifier/{scheme}/{value}")]
+async fn get_author_by_identifier(
+    path: web::Path<(String, String)>,
+    authors: web::Data<dyn Repository<Author>>,
+) -> impl Responder {
+    let (scheme, value) = path.into_inner();
+    let (scheme, value) =
+        match Scheme::parse(&scheme).and_then(|s| s.normalize(&value).map(|v| (s, v))) {
+            Ok(r) => r,
+            Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+        };
+    let r = authors
+        .get_all(&Filters::default().by(scheme.as_str(), value).limit(1))
+        .await
+        .and_then(|v| v.into_iter().next().ok_or(sqlx::Error::RowNotFound));
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The biography rendered from Markdown to HTML, safe to embed in a page.
+#[get("/authors/{id}/biography")]
+async fn get_author_biography(
+    id: web::Path<i64>,
+    authors: web::Data<dyn Repository<Author>>,
+) -> impl Responder {
+    let r = authors.get_one(id.into_inner()).await;
+
+    match r {
+        Ok(a) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(markdown::to_html(
+                a.biography.as_deref().unwrap_or_default(),
+            )),
         Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
     }
 }
@@ -80,144 +178,142 @@ async fn delete_author(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl
 mod tests {
     use super::super::super::*;
     use crate::authors::author::Author;
+    use crate::resources::resources_db::SqliteRepository;
+    use crate::resources::resources_repository::Repository;
+    use crate::test_utils::{self, author, book, test_pool};
     use actix_web::{
         http::{self},
         test,
     };
-    use sqlx::{
-        sqlite::{SqliteConnectOptions, SqlitePoolOptions},
-        Error, Pool, Sqlite,
-    };
-    use std::str::FromStr;
-    const DATABASE_TEST_URL: &str = "sqlite://db_test.sqlite";
-
-    pub async fn establish_connection() -> Result<Pool<Sqlite>, Error> {
-        let connection_options =
-            SqliteConnectOptions::from_str(DATABASE_TEST_URL)?.create_if_missing(true);
-
-        let sqlite_pool = SqlitePoolOptions::new()
-            .max_connections(constants::POOL_MAX_CONNECTIONS)
-            .connect_with(connection_options)
-            .await?;
-
-        sqlx::query(&format!(
-            "
-        CREATE TABLE IF NOT EXISTS {} (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name text
-          );
-        ",
-            constants::AUTHORS_TABLE
-        ))
-        .execute(&sqlite_pool.clone())
-        .await?;
-
-        Ok(sqlite_pool)
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(contributors::contributors::config_contributors)
+                    .configure(super::config_authors),
+            )
+            .await
+        }};
+    }
+
+    fn names(body: &Value) -> Vec<&str> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap())
+            .collect()
     }
 
     #[actix_web::test]
     async fn test_get_authors() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_authors)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        author().name("Austen").create(&conn_pool).await;
+        author().name("Herbert").create(&conn_pool).await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get().uri("/authors").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(names(&body), vec!["Austen", "Herbert"]);
     }
 
     #[actix_web::test]
     async fn test_get_authors_limit() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_authors)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        for name in ["Austen", "Herbert", "Tolkien"] {
+            author().name(name).create(&conn_pool).await;
+        }
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get()
             .uri("/authors?limit=2")
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(names(&body), vec!["Austen", "Herbert"]);
     }
 
     #[actix_web::test]
-    async fn test_get_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_author)
-                .service(super::create_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
-        let req = test::TestRequest::post()
-            .set_json(Author {
-                id: Some(1),
-                name: Some("test1".to_string()),
-            })
-            .uri("/authors")
+    async fn test_get_authors_sort() {
+        let conn_pool = test_pool().await;
+        for name in ["Herbert", "Tolkien", "Austen"] {
+            author().name(name).create(&conn_pool).await;
+        }
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri("/authors?sort=-name")
             .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(names(&body), vec!["Tolkien", "Herbert", "Austen"]);
+    }
 
-        test::call_service(&app, req).await;
+    #[actix_web::test]
+    async fn test_get_author() {
+        let conn_pool = test_pool().await;
+        let a = author().name("test1").create(&conn_pool).await;
+        let app = app!(conn_pool);
 
-        let req = test::TestRequest::get().uri("/authors/1").to_request();
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}", a.id.unwrap()))
+            .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body,
+            json!({"id": a.id, "name": "test1", "birth_date": null, "death_date": null,
+                "nationality": null, "biography": null, "isni": null, "viaf": null,
+                "orcid": null, "wikidata": null, "metadata": null})
+        );
     }
 
     #[actix_web::test]
     async fn test_get_author_internal_server_error() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get().uri("/authors/0").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains("no rows"));
     }
 
     #[actix_rt::test]
     async fn test_create_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::create_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::post()
-            .set_json(Author {
-                id: Some(1),
-                name: Some("test1".to_string()),
-            })
+            .set_json(author().name("test1").build())
             .uri("/authors")
             .to_request();
 
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+        let id = body["id"].as_i64().unwrap();
+
+        let created = SqliteRepository::<Author>::new(conn_pool)
+            .get_one(id)
+            .await
+            .unwrap();
+        assert_eq!(created.name.as_deref(), Some("test1"));
     }
 
     #[actix_rt::test]
     async fn test_create_author_bad_request() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::create_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::post()
             .set_json("")
@@ -230,34 +326,30 @@ mod tests {
 
     #[actix_web::test]
     async fn test_update_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::update_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let a = author().name("Austen").create(&conn_pool).await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::put()
-            .uri("/authors/1")
-            .set_json(Author {
-                id: Some(1),
-                name: Some("test1".to_string()),
-            })
+            .uri(&format!("/authors/{}", a.id.unwrap()))
+            .set_json(author().name("Jane Austen").build())
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, "Updated");
+
+        let updated = SqliteRepository::<Author>::new(conn_pool)
+            .get_one(a.id.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(updated.name.as_deref(), Some("Jane Austen"));
     }
 
     #[actix_web::test]
     async fn test_update_author_bad_request() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::update_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::put()
             .uri("/authors/1")
@@ -267,18 +359,404 @@ mod tests {
         assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
     }
 
+    #[actix_web::test]
+    async fn test_create_author_biographical_data() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .uri("/authors")
+            .set_json(json!({
+                "name": "Tolkien",
+                "birth_date": "1892-01-03",
+                "death_date": "1973-09",
+                "nationality": " gb ",
+                "isni": "0000 0001 2281 955x",
+                "orcid": "0000000218250097",
+                "viaf": "095218067",
+                "wikidata": "q892"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}", body["id"]))
+            .to_request();
+        let a: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(a["nationality"], "GB");
+        assert_eq!(a["isni"], "000000012281955X");
+        assert_eq!(a["orcid"], "0000-0002-1825-0097");
+        assert_eq!(a["viaf"], "95218067");
+        assert_eq!(a["wikidata"], "Q892");
+    }
+
+    #[actix_web::test]
+    async fn test_create_author_invalid_biographical_data() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for (field, value) in [
+            ("birth_date", "1892-13"),
+            ("death_date", "1800"),
+            ("nationality", "GBR"),
+            ("isni", "0000 0001 2281 9551"),
+            ("orcid", "0000-0002-1825-0098"),
+            ("viaf", "VIAF1"),
+            ("wikidata", "P31"),
+        ] {
+            let mut a = json!({"name": "Tolkien", "birth_date": "1892"});
+            a[field] = json!(value);
+            let req = test::TestRequest::post()
+                .uri("/authors")
+                .set_json(a)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", field);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_create_author_duplicate_identifier() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let mut ids = Vec::new();
+        for wikidata in ["Q892", "q892"] {
+            let req = test::TestRequest::post()
+                .uri("/authors")
+                .set_json(json!({"name": "Tolkien", "wikidata": wikidata}))
+                .to_request();
+            ids.push(test::call_service(&app, req).await);
+        }
+        assert_eq!(ids[0].status(), http::StatusCode::CREATED);
+        assert_eq!(ids[1].status(), http::StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_get_author_by_identifier() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let req = test::TestRequest::post()
+            .uri("/authors")
+            .set_json(json!({"name": "Tolkien", "isni": "000000012281955X"}))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/authors/by-identifier/isni/0000%200001%202281%20955X")
+            .to_request();
+        let a: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(a["id"], created["id"]);
+
+        let req = test::TestRequest::get()
+            .uri("/authors/by-identifier/wikidata/Q42")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        for uri in [
+            "/authors/by-identifier/lccn/n79021164",
+            "/authors/by-identifier/isni/123",
+            "/authors/by-identifier/isni/00000000000000%C3%A9",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_author_biography() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let req = test::TestRequest::post()
+            .uri("/authors")
+            .set_json(json!({"name": "Tolkien", "biography": "**Philologist**<script>x</script>"}))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}/biography", created["id"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(
+            body,
+            "<p><strong>Philologist</strong>&lt;script&gt;x&lt;/script&gt;</p>\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_get_authors_by_era_and_nationality() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        for (name, birth, death, nationality) in [
+            ("Austen", "1775-12-16", Some("1817-07-18"), "GB"),
+            ("Tolkien", "1892", Some("1973"), "GB"),
+            ("Pratchett", "1948-04", Some("2015"), "GB"),
+            ("Atwood", "1939", None, "CA"),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/authors")
+                .set_json(
+                    json!({"name": name, "birth_date": birth, "death_date": death,
+                    "nationality": nationality}),
+                )
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        for (uri, expected) in [
+            (
+                "/authors?alive_in=1950&nationality=GB",
+                vec!["Tolkien", "Pratchett"],
+            ),
+            ("/authors?alive_in=2020", vec!["Atwood"]),
+            ("/authors?born_after=1900", vec!["Pratchett", "Atwood"]),
+            ("/authors?born_before=1892", vec!["Austen", "Tolkien"]),
+            ("/authors?born_after=1939&born_before=1939", vec!["Atwood"]),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(names(&body), expected, "{}", uri);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/authors?alive_in=19")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    macro_rules! post_json {
+        ($app:expr, $uri:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&$uri)
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_author_aliases() {
+        let conn_pool = test_pool().await;
+        let tolkien = author()
+            .name("J. R. R. Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let other = author().name("Lewis").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/aliases", tolkien),
+            json!({"name": " Tolkien, J.R.R. ", "kind": "Variant"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let alias: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}/aliases", tolkien))
+            .to_request();
+        let aliases: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            aliases,
+            json!([{"id": alias["id"], "author_id": tolkien, "name": "Tolkien, J.R.R.", "kind": "variant"}])
+        );
+
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/aliases", other),
+            json!({"name": "TOLKIEN, J.R.R."})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["location"], format!("/authors/{}", tolkien));
+
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/aliases", other),
+            json!({"name": " "})
+        );
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get()
+            .uri("/authors?name=tolkien,%20j.r.r.")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), vec!["J. R. R. Tolkien"]);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/authors/{}/aliases/{}", tolkien, alias["id"]))
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/authors?name=Tolkien,%20J.R.R.")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), Vec::<&str>::new());
+    }
+
+    #[actix_web::test]
+    async fn test_merge_author() {
+        let conn_pool = test_pool().await;
+        let hobbit = book()
+            .title("The Hobbit")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let silmarillion = book()
+            .title("The Silmarillion")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let target = author()
+            .name("J. R. R. Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let app = app!(conn_pool);
+        let resp = post_json!(
+            app,
+            "/authors",
+            json!({"name": "Tolkien, J.R.R.", "birth_date": "1892", "wikidata": "Q892"})
+        );
+        let duplicate: Value = test::read_body_json(resp).await;
+        let duplicate = duplicate["id"].as_i64().unwrap();
+        post_json!(
+            app,
+            format!("/authors/{}/aliases", duplicate),
+            json!({"name": "JRRT"})
+        );
+        for (b, contributors) in [
+            (hobbit, json!([{"author_id": duplicate, "role": "author"}])),
+            (
+                silmarillion,
+                json!([{"author_id": target, "role": "author"}, {"author_id": duplicate, "role": "author"},
+                    {"author_id": duplicate, "role": "editor"}]),
+            ),
+        ] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}/contributors", b))
+                .set_json(contributors)
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/merge", duplicate),
+            json!({ "target_id": target })
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let merged: Value = test::read_body_json(resp).await;
+        assert_eq!(merged["id"], target);
+        assert_eq!(merged["name"], "J. R. R. Tolkien");
+        assert_eq!(merged["birth_date"], "1892");
+        assert_eq!(merged["wikidata"], "Q892");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}/works", target))
+            .to_request();
+        let works: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(works["author"].as_array().unwrap().len(), 2);
+        assert_eq!(works["editor"][0]["book_id"], silmarillion);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}/aliases", target))
+            .to_request();
+        let aliases: Value = test::call_and_read_body_json(&app, req).await;
+        let aliases: Vec<&str> = aliases
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(aliases, vec!["JRRT", "Tolkien, J.R.R."]);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}", duplicate))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            format!("/authors/{}", target)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_merge_author_chains_redirects() {
+        let conn_pool = test_pool().await;
+        let first = author().name("A").create(&conn_pool).await.id.unwrap();
+        let second = author().name("B").create(&conn_pool).await.id.unwrap();
+        let third = author().name("C").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        post_json!(
+            app,
+            format!("/authors/{}/merge", first),
+            json!({ "target_id": second })
+        );
+        post_json!(
+            app,
+            format!("/authors/{}/merge", second),
+            json!({ "target_id": third })
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}", first))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("location").unwrap().to_str().unwrap(),
+            format!("/authors/{}", third)
+        );
+
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/merge", third),
+            json!({ "target_id": third })
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let resp = post_json!(
+            app,
+            format!("/authors/{}/merge", third),
+            json!({ "target_id": first })
+        );
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     #[actix_web::test]
     async fn test_delete_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::delete_author)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let a = author().create(&conn_pool).await;
+        let app = app!(conn_pool);
 
-        let req = test::TestRequest::delete().uri("/authors/2").to_request();
+        let req = test::TestRequest::delete()
+            .uri(&format!("/authors/{}", a.id.unwrap()))
+            .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, "Deleted");
+
+        let remaining = SqliteRepository::<Author>::new(conn_pool)
+            .get_one(a.id.unwrap())
+            .await;
+        assert!(remaining.is_err());
     }
 }
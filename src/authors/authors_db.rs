@@ -1,67 +1,142 @@
+use super::alias::Alias;
 use super::author::Author;
 use super::authors_queries;
-use super::filter::Filters;
-use actix_web::web;
-use sqlx::{sqlite::SqliteQueryResult, Error, Pool, Sqlite};
+use sqlx::{Error, Pool, Sqlite, Transaction};
 
-pub async fn get_authors(
-    filter: web::Query<Filters>,
-    pool: web::Data<Pool<Sqlite>>,
-) -> Result<Vec<Author>, Error> {
-    let query = authors_queries::get_authors_query(filter);
-    let r = sqlx::query_as::<_, Author>(&query)
-        .fetch_all(pool.get_ref())
-        .await;
-
-    return r;
+pub enum Change {
+    Done(i64),
+    // The name already belongs to the given author.
+    Duplicate(i64),
 }
 
-pub async fn get_author(pool: web::Data<Pool<Sqlite>>, author_id: i64) -> Result<Author, Error> {
-    let query = authors_queries::get_author_query();
-    let r = sqlx::query_as::<_, Author>(&query)
-        .bind(author_id)
-        .fetch_one(pool.get_ref())
-        .await;
+pub enum Merge {
+    Done(Box<Author>),
+    // The merge is not allowed, with the reason.
+    Refused(String),
+}
 
-    return r;
+async fn fetch_author(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<Author, Error> {
+    sqlx::query_as::<_, Author>(&authors_queries::get_author_query())
+        .bind(id)
+        .fetch_one(tx)
+        .await
 }
 
-pub async fn create_author(
-    pool: web::Data<Pool<Sqlite>>,
-    author: Author,
-) -> Result<SqliteQueryResult, Error> {
-    let query = authors_queries::create_author_query();
-    let r = sqlx::query(&query)
-        .bind(author.name)
-        .execute(pool.get_ref())
-        .await;
+pub async fn get_aliases(pool: &Pool<Sqlite>, author_id: i64) -> Result<Vec<Alias>, Error> {
+    let mut tx = pool.begin().await?;
+    fetch_author(&mut tx, author_id).await?;
 
-    return r;
+    sqlx::query_as::<_, Alias>(&authors_queries::get_aliases_query())
+        .bind(author_id)
+        .fetch_all(&mut tx)
+        .await
 }
 
-pub async fn update_author(
-    author: Author,
-    pool: web::Data<Pool<Sqlite>>,
+pub async fn create_alias(
+    pool: &Pool<Sqlite>,
     author_id: i64,
-) -> Result<SqliteQueryResult, Error> {
-    let query = authors_queries::update_author_query(author);
-    let r = sqlx::query(&query)
+    alias: Alias,
+) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    fetch_author(&mut tx, author_id).await?;
+    let owner: Option<i64> = sqlx::query_scalar(&authors_queries::get_alias_owner_query())
+        .bind(&alias.name)
+        .fetch_optional(&mut tx)
+        .await?;
+    if let Some(owner) = owner {
+        return Ok(Change::Duplicate(owner));
+    }
+
+    let r = sqlx::query(&authors_queries::create_alias_query())
         .bind(author_id)
-        .execute(pool.get_ref())
-        .await;
+        .bind(alias.name)
+        .bind(alias.kind)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
 
-    return r;
+    Ok(Change::Done(r.last_insert_rowid()))
 }
 
-pub async fn delete_author(
-    pool: web::Data<Pool<Sqlite>>,
-    author_id: i64,
-) -> Result<SqliteQueryResult, Error> {
-    let query = authors_queries::delete_author_query();
-    let r = sqlx::query(&query)
+pub async fn delete_alias(pool: &Pool<Sqlite>, author_id: i64, id: i64) -> Result<(), Error> {
+    sqlx::query(&authors_queries::delete_alias_query())
         .bind(author_id)
-        .execute(pool.get_ref())
-        .await;
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// The author a merged-away id now points to, if any.
+pub async fn get_redirect(pool: &Pool<Sqlite>, id: i64) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar(&authors_queries::get_redirect_query())
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Folds the author `id` into `target_id` in one transaction: book links and
+// aliases move over, the duplicate's name becomes an alias, details the
+// target lacks are copied, and `id` keeps resolving to the target. Reviews
+// belong to books, so they follow along without changes.
+pub async fn merge_author(pool: &Pool<Sqlite>, id: i64, target_id: i64) -> Result<Merge, Error> {
+    if id == target_id {
+        return Ok(Merge::Refused(format!(
+            "author {} cannot be merged into itself",
+            id
+        )));
+    }
+    let mut tx = pool.begin().await?;
+    let duplicate = fetch_author(&mut tx, id).await?;
+    let target = fetch_author(&mut tx, target_id).await?;
+
+    sqlx::query(&authors_queries::move_contributions_query())
+        .bind(target_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query(&authors_queries::move_aliases_query())
+        .bind(target_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    if duplicate.name != target.name {
+        sqlx::query(&authors_queries::create_alias_if_missing_query())
+            .bind(target_id)
+            .bind(&duplicate.name)
+            .execute(&mut tx)
+            .await?;
+    }
+    sqlx::query(&authors_queries::move_redirects_query())
+        .bind(target_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query(&authors_queries::create_redirect_query())
+        .bind(id)
+        .bind(target_id)
+        .execute(&mut tx)
+        .await?;
+    // The duplicate goes first so that its identifiers are free to move.
+    sqlx::query(&authors_queries::delete_author_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query(&authors_queries::fill_author_query())
+        .bind(duplicate.birth_date)
+        .bind(duplicate.death_date)
+        .bind(duplicate.nationality)
+        .bind(duplicate.biography)
+        .bind(duplicate.isni)
+        .bind(duplicate.viaf)
+        .bind(duplicate.orcid)
+        .bind(duplicate.wikidata)
+        .bind(target_id)
+        .execute(&mut tx)
+        .await?;
+    let target = fetch_author(&mut tx, target_id).await?;
+    tx.commit().await?;
 
-    return r;
+    Ok(Merge::Done(Box::new(target)))
 }
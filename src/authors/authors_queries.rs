@@ -1,51 +1,116 @@
-use super::super::constants::AUTHORS_TABLE;
-use super::author::Author;
-use super::filter::Filters;
-use actix_web::web;
-
-pub fn get_authors_query(filter: web::Query<Filters>) -> String {
-    let mut query = format!("Select * From {}", AUTHORS_TABLE);
-    if !!!filter.limit.is_none() {
-        query = format!("{} limit {}", query, filter.limit.unwrap());
-    }
-    return query;
-}
+use super::super::constants::{
+    AUTHORS_TABLE, AUTHOR_ALIASES_TABLE, AUTHOR_REDIRECTS_TABLE, BOOK_CONTRIBUTORS_TABLE,
+};
+use super::super::resources::filter::Clause;
+use super::super::resources::value::Value;
 
 pub fn get_author_query() -> String {
-    return format!("Select * From {} where id=?", AUTHORS_TABLE);
+    format!("Select * From {} where id=?", AUTHORS_TABLE)
 }
 
-pub fn create_author_query() -> String {
-    return format!(
-        "INSERT INTO {} (name) values (?) RETURNING id",
-        AUTHORS_TABLE
-    );
+pub fn get_aliases_query() -> String {
+    format!(
+        "Select * From {} where author_id=? order by name",
+        AUTHOR_ALIASES_TABLE
+    )
 }
 
-fn get_update_str(author: Author) -> String {
-    let mut update_str = String::from("");
+pub fn get_all_aliases_query() -> String {
+    format!(
+        "Select * From {} order by author_id, name",
+        AUTHOR_ALIASES_TABLE
+    )
+}
 
-    if !!!author.name.is_none() {
-        update_str.push_str("name='");
-        update_str.push_str(author.name.unwrap().as_str());
-        update_str.push_str("'");
-    }
-    update_str
+pub fn get_alias_owner_query() -> String {
+    format!(
+        "Select author_id From {} where name=?",
+        AUTHOR_ALIASES_TABLE
+    )
 }
 
-pub fn update_author_query(author: Author) -> String {
-    let fields = get_update_str(author);
-    if fields.is_empty() {
-        return fields;
-    }
-    return format!(
-        "{}{}{}",
-        format!("UPDATE {} SET ", AUTHORS_TABLE),
-        fields,
-        " where id=?"
-    );
+pub fn create_alias_query() -> String {
+    format!(
+        "INSERT INTO {} (author_id, name, kind) values (?, ?, ?)",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn delete_alias_query() -> String {
+    format!(
+        "DELETE From {} where author_id=? and id=?",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn get_redirect_query() -> String {
+    format!(
+        "Select target_id From {} where author_id=?",
+        AUTHOR_REDIRECTS_TABLE
+    )
+}
+
+// Links of the duplicate that the target already has in the same role are
+// dropped with the duplicate.
+pub fn move_contributions_query() -> String {
+    format!(
+        "UPDATE {bc} SET author_id=?1 where author_id=?2 and NOT EXISTS \
+         (SELECT 1 FROM {bc} t WHERE t.book_id = {bc}.book_id AND t.role = {bc}.role \
+           AND t.author_id=?1)",
+        bc = BOOK_CONTRIBUTORS_TABLE
+    )
+}
+
+pub fn move_aliases_query() -> String {
+    format!(
+        "UPDATE {} SET author_id=? where author_id=?",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn create_alias_if_missing_query() -> String {
+    format!(
+        "INSERT OR IGNORE INTO {} (author_id, name, kind) values (?, ?, 'merged')",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn move_redirects_query() -> String {
+    format!(
+        "UPDATE {} SET target_id=? where target_id=?",
+        AUTHOR_REDIRECTS_TABLE
+    )
+}
+
+pub fn create_redirect_query() -> String {
+    format!(
+        "INSERT INTO {} (author_id, target_id) values (?, ?)",
+        AUTHOR_REDIRECTS_TABLE
+    )
 }
 
 pub fn delete_author_query() -> String {
-    return format!("DELETE From {} where id=?", AUTHORS_TABLE);
+    format!("DELETE From {} where id=?", AUTHORS_TABLE)
+}
+
+// Fills in whatever the target does not know yet from the duplicate.
+pub fn fill_author_query() -> String {
+    format!(
+        "UPDATE {} SET birth_date=COALESCE(birth_date, ?), death_date=COALESCE(death_date, ?), \
+         nationality=COALESCE(nationality, ?), biography=COALESCE(biography, ?), \
+         isni=COALESCE(isni, ?), viaf=COALESCE(viaf, ?), orcid=COALESCE(orcid, ?), \
+         wikidata=COALESCE(wikidata, ?) where id=?",
+        AUTHORS_TABLE
+    )
+}
+
+// Authors whose name, or one of whose aliases, is `name`.
+pub fn authors_named_clause(name: &str) -> Clause {
+    Clause {
+        sql: format!(
+            "name = ? OR id IN (SELECT author_id FROM {} WHERE name = ?)",
+            AUTHOR_ALIASES_TABLE
+        ),
+        binds: vec![Value::Text(name.to_string()), Value::Text(name.to_string())],
+    }
 }
@@ -1,6 +0,0 @@
-use serde::Deserialize;
-
-#[derive(Deserialize)]
-pub struct Filters {
-    pub limit: Option<u32>,
-}
@@ -0,0 +1,245 @@
+// A small Markdown renderer for author biographies. It covers paragraphs,
+// headings, lists, block quotes, fenced code, emphasis, inline code and
+// links. Any HTML in the source is escaped rather than passed through, and
+// links are limited to http, https, mailto and local targets, so the output
+// is safe to embed as is.
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    ["http://", "https://", "mailto:", "/", "#"]
+        .iter()
+        .any(|p| lower.starts_with(p))
+        && !lower.starts_with("//")
+}
+
+// Renders inline markup. `text` is raw Markdown, escaping happens here.
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if let Some(next) = rest[1..]
+                .chars()
+                .next()
+                .filter(|n| n.is_ascii_punctuation())
+            {
+                out.push_str(&escape(&next.to_string()));
+                rest = &rest[1 + next.len_utf8()..];
+                continue;
+            }
+        }
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push_str(&format!("<code>{}</code>", escape(&rest[1..1 + end])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if rest.starts_with("**") {
+            if let Some(end) = rest[2..].find("**").filter(|e| *e > 0) {
+                out.push_str(&format!("<strong>{}</strong>", inline(&rest[2..2 + end])));
+                rest = &rest[end + 4..];
+                continue;
+            }
+        }
+        if c == '*' || c == '_' {
+            if let Some(end) = rest[1..].find(c).filter(|e| *e > 0) {
+                out.push_str(&format!("<em>{}</em>", inline(&rest[1..1 + end])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if c == '[' {
+            if let Some(mid) = rest.find("](") {
+                if let Some(end) = rest[mid + 2..].find(')') {
+                    let label = inline(&rest[1..mid]);
+                    let url = rest[mid + 2..mid + 2 + end].trim();
+                    if is_safe_url(url) {
+                        out.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), label));
+                    } else {
+                        out.push_str(&label);
+                    }
+                    rest = &rest[mid + 3 + end..];
+                    continue;
+                }
+            }
+        }
+        out.push_str(&escape(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        Some((level, line[level..].trim()))
+    } else {
+        None
+    }
+}
+
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some(("ul", item));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some(("ol", &line[digits + 2..]));
+    }
+    None
+}
+
+pub fn to_html(markdown: &str) -> String {
+    blocks(markdown, 0)
+}
+
+// Quotes nest by recursion, so deeper ones are kept as plain text rather than
+// letting a biography of `>`s exhaust the stack.
+const MAX_QUOTE_DEPTH: usize = 16;
+
+fn blocks(markdown: &str, depth: usize) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let lines: Vec<&str> = markdown.lines().collect();
+
+    let flush = |html: &mut String, paragraph: &mut Vec<&str>, list: &mut Option<&str>| {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join("\n"))));
+            paragraph.clear();
+        }
+        if let Some(tag) = list.take() {
+            html.push_str(&format!("</{}>\n", tag));
+        }
+    };
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let trimmed = line.trim_start();
+        i += 1;
+
+        if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph, &mut list);
+        } else if trimmed.starts_with("```") {
+            flush(&mut html, &mut paragraph, &mut list);
+            let mut code = Vec::new();
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1;
+            html.push_str(&format!(
+                "<pre><code>{}</code></pre>\n",
+                escape(&code.join("\n"))
+            ));
+        } else if let Some((level, text)) = heading(trimmed) {
+            flush(&mut html, &mut paragraph, &mut list);
+            html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+        } else if let Some(first) = trimmed
+            .strip_prefix('>')
+            .filter(|_| depth < MAX_QUOTE_DEPTH)
+        {
+            flush(&mut html, &mut paragraph, &mut list);
+            let mut quote = vec![first.trim_start()];
+            while let Some(next) = lines.get(i).and_then(|l| l.trim_start().strip_prefix('>')) {
+                quote.push(next.trim_start());
+                i += 1;
+            }
+            html.push_str(&format!(
+                "<blockquote>\n{}</blockquote>\n",
+                blocks(&quote.join("\n"), depth + 1)
+            ));
+        } else if let Some((tag, item)) = list_item(trimmed) {
+            if list != Some(tag) {
+                flush(&mut html, &mut paragraph, &mut list);
+                html.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            html.push_str(&format!("<li>{}</li>\n", inline(item.trim())));
+        } else {
+            if list.is_some() {
+                flush(&mut html, &mut paragraph, &mut list);
+            }
+            paragraph.push(trimmed);
+        }
+    }
+    flush(&mut html, &mut paragraph, &mut list);
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let md = "# Life\n\nBorn in *Bloemfontein*,\nraised in **Birmingham**.\n\n\
+                  - The Hobbit\n- The Lord of the Rings\n\n1. one\n2. two\n\n> Not all those\n> who wander\n\n\
+                  ```\nlet x = 1 < 2;\n```";
+        assert_eq!(
+            to_html(md),
+            "<h1>Life</h1>\n\
+             <p>Born in <em>Bloemfontein</em>,\nraised in <strong>Birmingham</strong>.</p>\n\
+             <ul>\n<li>The Hobbit</li>\n<li>The Lord of the Rings</li>\n</ul>\n\
+             <ol>\n<li>one</li>\n<li>two</li>\n</ol>\n\
+             <blockquote>\n<p>Not all those\nwho wander</p>\n</blockquote>\n\
+             <pre><code>let x = 1 &lt; 2;</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_deep_quotes() {
+        let html = to_html(&">".repeat(20_000));
+        assert_eq!(html.matches("<blockquote>").count(), MAX_QUOTE_DEPTH);
+        assert!(html.contains(&format!(
+            "<p>{}</p>",
+            "&gt;".repeat(20_000 - MAX_QUOTE_DEPTH)
+        )));
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            to_html("See [Wikipedia](https://en.wikipedia.org/?a=1&b=2), `a*b*c` and \\*stars\\*."),
+            "<p>See <a href=\"https://en.wikipedia.org/?a=1&amp;b=2\">Wikipedia</a>, \
+             <code>a*b*c</code> and *stars*.</p>\n"
+        );
+        assert_eq!(to_html("2 * 3 = 6"), "<p>2 * 3 = 6</p>\n");
+    }
+
+    #[test]
+    fn test_sanitizes() {
+        assert_eq!(
+            to_html("<script>alert(1)</script> <b onclick=\"x\">bold</b>"),
+            "<p>&lt;script&gt;alert(1)&lt;/script&gt; &lt;b onclick=&quot;x&quot;&gt;bold&lt;/b&gt;</p>\n"
+        );
+        assert_eq!(
+            to_html("[click](javascript:alert(1)) [x](//evil.example)"),
+            "<p>click) x</p>\n"
+        );
+        assert_eq!(
+            to_html("[x](https://a.example/\"onmouseover=\"alert(1))"),
+            "<p><a href=\"https://a.example/&quot;onmouseover=&quot;alert(1\">x</a>)</p>\n"
+        );
+    }
+}
@@ -1,5 +1,8 @@
-mod author;
+pub mod alias;
+pub mod author;
+pub mod authority;
+#[allow(clippy::module_inception)]
 pub mod authors;
 mod authors_db;
-mod authors_queries;
-mod filter;
+pub mod authors_queries;
+mod markdown;
@@ -0,0 +1,277 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Error, Pool, Sqlite};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::RwLock;
+
+use super::super::responses::CustomError;
+use super::autocomplete_db;
+use super::index::{Index, Kind, Suggestion, MAX_LIMIT};
+
+pub const DEFAULT_LIMIT: usize = 10;
+
+// The suggestion index, loaded on first use and kept in step with the
+// database through the changes its triggers record.
+#[derive(Default)]
+pub struct Autocomplete {
+    index: RwLock<Option<Index>>,
+    // The last change applied to the index.
+    last_change: AtomicI64,
+}
+
+impl Autocomplete {
+    async fn refresh(&self, pool: &Pool<Sqlite>) -> Result<(), Error> {
+        if self.index.read().unwrap().is_none() {
+            // Anything changed while loading is replayed on the next query.
+            let last = autocomplete_db::get_last_change(pool).await?;
+            let index = Index::build(autocomplete_db::get_documents(pool).await?);
+            {
+                let mut guard = self.index.write().unwrap();
+                if guard.is_some() {
+                    return Ok(());
+                }
+                *guard = Some(index);
+                self.last_change.store(last, Ordering::SeqCst);
+            }
+            return autocomplete_db::delete_changes(pool, last).await;
+        }
+
+        let changes =
+            autocomplete_db::get_changes(pool, self.last_change.load(Ordering::SeqCst)).await?;
+        if changes.documents.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut guard = self.index.write().unwrap();
+            // Another query may have applied these, or later ones, meanwhile.
+            if self.last_change.load(Ordering::SeqCst) >= changes.last {
+                return Ok(());
+            }
+            let index = guard.as_mut().unwrap();
+            for (kind, id, document) in changes.documents {
+                match document {
+                    Some(document) => index.insert(document),
+                    None => index.remove(kind, id),
+                }
+            }
+            self.last_change.store(changes.last, Ordering::SeqCst);
+        }
+        autocomplete_db::delete_changes(pool, changes.last).await
+    }
+
+    pub async fn search(
+        &self,
+        pool: &Pool<Sqlite>,
+        query: &str,
+        kinds: &[Kind],
+        limit: usize,
+    ) -> Result<Vec<Suggestion>, Error> {
+        self.refresh(pool).await?;
+        let guard = self.index.read().unwrap();
+        Ok(guard.as_ref().unwrap().search(query, kinds, limit))
+    }
+}
+
+pub fn config_autocomplete(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_autocomplete);
+}
+
+#[derive(Deserialize)]
+pub struct AutocompleteParams {
+    pub q: Option<String>,
+    pub types: Option<String>,
+    pub limit: Option<usize>,
+}
+
+// Suggests books by title and authors by name or alias for what has been
+// typed so far, best matches first and, among equal ones, the most popular.
+#[get("/autocomplete")]
+async fn get_autocomplete(
+    params: web::Query<AutocompleteParams>,
+    autocomplete: web::Data<Autocomplete>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let q = match &params.q {
+        Some(q) => q,
+        None => return HttpResponse::BadRequest().json(CustomError::message("q is required")),
+    };
+    let kinds = match &params.types {
+        None => Kind::ALL.to_vec(),
+        Some(types) => match types.split(',').map(|t| Kind::parse(t.trim())).collect() {
+            Ok(kinds) => kinds,
+            Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+        },
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "limit must be between 1 and {} (got {})",
+            MAX_LIMIT, limit
+        )));
+    }
+
+    match autocomplete.search(pool.get_ref(), q, &kinds, limit).await {
+        Ok(suggestions) => HttpResponse::Ok().json(suggestions),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, author, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(super::Autocomplete::default()))
+                    .configure(authors::authors::config_authors)
+                    .configure(books::books::config_books)
+                    .configure(super::config_autocomplete),
+            )
+            .await
+        }};
+    }
+
+    fn labels(suggestions: &Value) -> Vec<&str> {
+        suggestions
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["label"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_autocomplete() {
+        let conn_pool = test_pool().await;
+        book()
+            .title("The Lord of the Rings")
+            .author("Tolkien")
+            .create(&conn_pool)
+            .await;
+        let war = book()
+            .title("War and Peace")
+            .author("Tolstoy")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        book()
+            .title("Tolkien: A Biography")
+            .create(&conn_pool)
+            .await;
+        author().name("Lev Tolstoï").create(&conn_pool).await;
+        sqlx::query("UPDATE books SET review_count = 5 WHERE id = ?")
+            .bind(war)
+            .execute(&conn_pool)
+            .await
+            .unwrap();
+        let app = app!(conn_pool);
+
+        // Titles starting with the query come first, then word matches by
+        // popularity; accents are ignored.
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=Tol")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            labels(&suggestions),
+            ["Tolkien: A Biography", "Lev Tolstoï"]
+        );
+        assert_eq!(suggestions[1]["kind"], "author");
+
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=lord%20of%20the%20r&types=books")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels(&suggestions), ["The Lord of the Rings"]);
+        assert_eq!(suggestions[0]["detail"], "Tolkien");
+
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=pea&types=books&limit=1")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels(&suggestions), ["War and Peace"]);
+        assert_eq!(suggestions[0]["popularity"], 5);
+    }
+
+    #[actix_web::test]
+    async fn test_autocomplete_follows_changes() {
+        let conn_pool = test_pool().await;
+        let tolstoy = author()
+            .name("Leo Tolstoy")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=lev&types=authors")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels(&suggestions), Vec::<&str>::new());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/authors/{}/aliases", tolstoy))
+            .set_json(json!({"name": "Lev Nikolayevich Tolstoy"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let req = test::TestRequest::post()
+            .uri("/books")
+            .set_json(json!({"title": "Levels of Life"}))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=lev")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels(&suggestions), ["Leo Tolstoy", "Levels of Life"]);
+        assert_eq!(suggestions[0]["matched"], "Lev Nikolayevich Tolstoy");
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/authors/{}", tolstoy))
+            .to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::get()
+            .uri("/autocomplete?q=lev&types=authors")
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels(&suggestions), Vec::<&str>::new());
+    }
+
+    #[actix_web::test]
+    async fn test_autocomplete_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for (uri, message) in [
+            ("/autocomplete", "q is required"),
+            (
+                "/autocomplete?q=a&types=books,members",
+                "types must be books or authors (got members)",
+            ),
+            (
+                "/autocomplete?q=a&limit=0",
+                "limit must be between 1 and 50 (got 0)",
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::autocomplete_queries;
+use super::index::{Document, Kind};
+use sqlx::{Error, FromRow, Pool, Sqlite};
+use std::collections::HashMap;
+
+#[derive(FromRow)]
+struct BookRow {
+    id: i64,
+    title: Option<String>,
+    author: Option<String>,
+    popularity: i64,
+}
+
+#[derive(FromRow)]
+struct AuthorRow {
+    id: i64,
+    name: Option<String>,
+    popularity: i64,
+}
+
+impl From<BookRow> for Document {
+    fn from(row: BookRow) -> Self {
+        Document {
+            kind: Kind::Book,
+            id: row.id,
+            label: row.title.unwrap_or_default(),
+            detail: row.author,
+            aliases: Vec::new(),
+            popularity: row.popularity,
+        }
+    }
+}
+
+fn author(row: AuthorRow, aliases: Vec<String>) -> Document {
+    Document {
+        kind: Kind::Author,
+        id: row.id,
+        label: row.name.unwrap_or_default(),
+        detail: None,
+        aliases,
+        popularity: row.popularity,
+    }
+}
+
+pub async fn get_last_change(pool: &Pool<Sqlite>) -> Result<i64, Error> {
+    sqlx::query_scalar(&autocomplete_queries::get_last_change_query())
+        .fetch_one(pool)
+        .await
+}
+
+// Every book and author, aliases included.
+pub async fn get_documents(pool: &Pool<Sqlite>) -> Result<Vec<Document>, Error> {
+    let books: Vec<BookRow> = sqlx::query_as(&autocomplete_queries::get_books_query())
+        .fetch_all(pool)
+        .await?;
+    let authors: Vec<AuthorRow> = sqlx::query_as(&autocomplete_queries::get_authors_query())
+        .fetch_all(pool)
+        .await?;
+    let rows: Vec<(i64, String)> = sqlx::query_as(&autocomplete_queries::get_aliases_query())
+        .fetch_all(pool)
+        .await?;
+    let mut aliases: HashMap<i64, Vec<String>> = HashMap::new();
+    for (author_id, name) in rows {
+        aliases.entry(author_id).or_default().push(name);
+    }
+
+    let mut documents: Vec<Document> = books.into_iter().map(Document::from).collect();
+    documents.extend(authors.into_iter().map(|row| {
+        let names = aliases.remove(&row.id).unwrap_or_default();
+        author(row, names)
+    }));
+    Ok(documents)
+}
+
+pub struct Changes {
+    pub last: i64,
+    // Every changed record, with `None` for those that were deleted.
+    pub documents: Vec<(Kind, i64, Option<Document>)>,
+}
+
+// The records changed since `after`, as they are now.
+pub async fn get_changes(pool: &Pool<Sqlite>, after: i64) -> Result<Changes, Error> {
+    let rows: Vec<(i64, String, i64)> = sqlx::query_as(&autocomplete_queries::get_changes_query())
+        .bind(after)
+        .fetch_all(pool)
+        .await?;
+    let last = rows.last().map_or(after, |(id, _, _)| *id);
+    let mut changed: Vec<(Kind, i64)> = rows
+        .into_iter()
+        .map(|(_, kind, id)| {
+            let kind = if kind == "book" {
+                Kind::Book
+            } else {
+                Kind::Author
+            };
+            (kind, id)
+        })
+        .collect();
+    changed.sort_unstable();
+    changed.dedup();
+
+    let mut documents = Vec::new();
+    for (kind, id) in changed {
+        let document = match kind {
+            Kind::Book => sqlx::query_as::<_, BookRow>(&autocomplete_queries::get_book_query())
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .map(Document::from),
+            Kind::Author => {
+                let row = sqlx::query_as::<_, AuthorRow>(&autocomplete_queries::get_author_query())
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await?;
+                match row {
+                    Some(row) => {
+                        let aliases: Vec<String> =
+                            sqlx::query_scalar(&autocomplete_queries::get_author_aliases_query())
+                                .bind(id)
+                                .fetch_all(pool)
+                                .await?;
+                        Some(author(row, aliases))
+                    }
+                    None => None,
+                }
+            }
+        };
+        documents.push((kind, id, document));
+    }
+    Ok(Changes { last, documents })
+}
+
+// Forgets applied changes; the index of this process is their only reader.
+pub async fn delete_changes(pool: &Pool<Sqlite>, upto: i64) -> Result<(), Error> {
+    sqlx::query(&autocomplete_queries::delete_changes_query())
+        .bind(upto)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use super::super::constants::{
+    AUTHORS_TABLE, AUTHOR_ALIASES_TABLE, AUTOCOMPLETE_CHANGES_TABLE, BOOKS_TABLE,
+    BOOK_CONTRIBUTORS_TABLE, LOANS_TABLE,
+};
+
+// Books are as popular as they are borrowed and reviewed.
+fn books_query() -> String {
+    format!(
+        "Select b.id, b.title, b.author, b.review_count + \
+         (Select COUNT(*) From {} l where l.book_id = b.id) AS popularity From {} b",
+        LOANS_TABLE, BOOKS_TABLE
+    )
+}
+
+// Authors are as popular as the number of books they contributed to.
+fn authors_query() -> String {
+    format!(
+        "Select a.id, a.name, \
+         (Select COUNT(DISTINCT c.book_id) From {} c where c.author_id = a.id) AS popularity \
+         From {} a",
+        BOOK_CONTRIBUTORS_TABLE, AUTHORS_TABLE
+    )
+}
+
+pub fn get_books_query() -> String {
+    books_query()
+}
+
+pub fn get_book_query() -> String {
+    format!("{} where b.id=?", books_query())
+}
+
+pub fn get_authors_query() -> String {
+    authors_query()
+}
+
+pub fn get_author_query() -> String {
+    format!("{} where a.id=?", authors_query())
+}
+
+pub fn get_aliases_query() -> String {
+    format!(
+        "Select author_id, name From {} order by id",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn get_author_aliases_query() -> String {
+    format!(
+        "Select name From {} where author_id=? order by id",
+        AUTHOR_ALIASES_TABLE
+    )
+}
+
+pub fn get_last_change_query() -> String {
+    format!(
+        "Select COALESCE(MAX(id), 0) From {}",
+        AUTOCOMPLETE_CHANGES_TABLE
+    )
+}
+
+pub fn get_changes_query() -> String {
+    format!(
+        "Select id, kind, ref_id From {} where id > ? order by id",
+        AUTOCOMPLETE_CHANGES_TABLE
+    )
+}
+
+pub fn delete_changes_query() -> String {
+    format!("DELETE From {} where id <= ?", AUTOCOMPLETE_CHANGES_TABLE)
+}
@@ -0,0 +1,620 @@
+use super::super::similarity::normalize;
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+pub const MAX_LIMIT: usize = 50;
+// Queries matching more words than this are too common to rank on every
+// keystroke, so their best entries are kept ranked and updated in place.
+const HOT_QUERY: usize = 20_000;
+const CACHE_SIZE: usize = 2 * MAX_LIMIT;
+// Words of entries added since the last merge are scanned one by one, until
+// there are this many.
+const PENDING: usize = 4096;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Book,
+    Author,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 2] = [Kind::Book, Kind::Author];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Book => "book",
+            Kind::Author => "author",
+        }
+    }
+
+    // Parses the plural used in `?types=`.
+    pub fn parse(kind: &str) -> Result<Kind, String> {
+        match kind {
+            "books" => Ok(Kind::Book),
+            "authors" => Ok(Kind::Author),
+            _ => Err(format!("types must be books or authors (got {})", kind)),
+        }
+    }
+}
+
+// Something that can be suggested: a book by its title or an author by their
+// name or any of their aliases.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub kind: Kind,
+    pub id: i64,
+    pub label: String,
+    pub detail: Option<String>,
+    pub aliases: Vec<String>,
+    pub popularity: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub kind: Kind,
+    pub id: i64,
+    pub label: String,
+    pub detail: Option<String>,
+    // The alias the query matched, when it was not the label.
+    pub matched: Option<String>,
+    pub popularity: i64,
+}
+
+struct Entry {
+    doc: Document,
+    // The normalized label followed by the normalized aliases.
+    texts: Vec<String>,
+    // Removed entries stay until their words are merged away.
+    removed: bool,
+}
+
+// Match quality, best first: the whole text, a prefix of it, or a prefix of
+// any later word.
+fn tier(text: &str, query: &str) -> Option<u8> {
+    if text == query {
+        Some(0)
+    } else if text.starts_with(query) {
+        Some(1)
+    } else if text
+        .match_indices(query)
+        .any(|(i, _)| text.as_bytes()[i - 1] == b' ')
+    {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn word_starts(text: &str) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(0).chain(text.match_indices(' ').map(|(i, _)| i + 1))
+}
+
+// The first bytes of a text, in an order that agrees with the text's.
+fn key(bytes: &[u8]) -> u64 {
+    let mut key = [0; 8];
+    let n = bytes.len().min(8);
+    key[..n].copy_from_slice(&bytes[..n]);
+    u64::from_be_bytes(key)
+}
+
+// A text of an entry from the start of one of its words.
+#[derive(Clone, Copy)]
+struct Suffix {
+    key: u64,
+    slot: u32,
+    text: u32,
+    offset: u32,
+    len: u32,
+}
+
+impl Suffix {
+    fn tier(&self, query_len: usize) -> u8 {
+        match (self.offset, self.len as usize == query_len) {
+            (0, true) => 0,
+            (0, false) => 1,
+            _ => 2,
+        }
+    }
+}
+
+// Tier, then popularity, then shorter labels.
+type Rank = (u8, Reverse<i64>, usize, Kind, i64);
+
+struct Ranked {
+    entries: Vec<(Rank, u32)>,
+    // Whether entries beyond `CACHE_SIZE` were left out.
+    truncated: bool,
+}
+
+// Keeps the `n` best of `ranked`, best first and each entry once even when
+// it matched at several words.
+fn best(mut ranked: Vec<(Rank, u32)>, n: usize) -> Vec<(Rank, u32)> {
+    let mut k = n;
+    while k < ranked.len() {
+        ranked.select_nth_unstable(k);
+        let distinct: HashSet<u32> = ranked[..k].iter().map(|(_, s)| *s).collect();
+        if distinct.len() >= n {
+            ranked.truncate(k);
+            break;
+        }
+        k *= 2;
+    }
+    ranked.sort_unstable();
+    let mut seen = HashSet::new();
+    ranked.retain(|(_, s)| seen.insert(*s));
+    ranked.truncate(n);
+    ranked
+}
+
+// Every query matching the entry, with the best tier it matches at.
+fn queries(entry: &Entry) -> HashMap<&str, u8> {
+    let mut queries: HashMap<&str, u8> = HashMap::new();
+    for text in &entry.texts {
+        for offset in word_starts(text) {
+            let suffix = &text[offset..];
+            for (end, _) in suffix.char_indices().skip(1).chain([(suffix.len(), ' ')]) {
+                let query = &suffix[..end];
+                if query.ends_with(' ') {
+                    continue;
+                }
+                let tier = match (offset, end == suffix.len()) {
+                    (0, true) => 0,
+                    (0, false) => 1,
+                    _ => 2,
+                };
+                let best = queries.entry(query).or_insert(tier);
+                *best = tier.min(*best);
+            }
+        }
+    }
+    queries
+}
+
+// A prefix index over the words of every text: queries match the texts
+// having a word that starts with them, the words after it included.
+pub struct Index {
+    entries: Vec<Option<Entry>>,
+    slots: HashMap<(Kind, i64), u32>,
+    free: Vec<u32>,
+    removed: Vec<u32>,
+    // Word starts sorted by what follows them, and those added since.
+    sorted: Vec<Suffix>,
+    pending: Vec<Suffix>,
+    cache: Mutex<HashMap<Kind, HashMap<String, Ranked>>>,
+    hot: usize,
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Index {
+            entries: Vec::new(),
+            slots: HashMap::new(),
+            free: Vec::new(),
+            removed: Vec::new(),
+            sorted: Vec::new(),
+            pending: Vec::new(),
+            cache: Mutex::new(HashMap::new()),
+            hot: HOT_QUERY,
+        }
+    }
+}
+
+impl Index {
+    pub fn build(documents: impl IntoIterator<Item = Document>) -> Index {
+        let mut index = Index::default();
+        for document in documents {
+            index.add(document);
+        }
+        index.merge();
+        index.warm();
+        index
+    }
+
+    fn entry(&self, slot: u32) -> &Entry {
+        self.entries[slot as usize].as_ref().unwrap()
+    }
+
+    fn bytes(&self, suffix: &Suffix) -> &[u8] {
+        &self.entry(suffix.slot).texts[suffix.text as usize].as_bytes()[suffix.offset as usize..]
+    }
+
+    fn cmp(&self, a: &Suffix, b: &Suffix) -> Ordering {
+        a.key
+            .cmp(&b.key)
+            .then_with(|| self.bytes(a).cmp(self.bytes(b)))
+    }
+
+    // How the start of a suffix compares to a query, `Equal` when the
+    // suffix starts with it.
+    fn cmp_prefix(&self, suffix: &Suffix, query: &[u8], query_key: u64) -> Ordering {
+        if query.len() <= 8 {
+            let mask = u64::MAX << (8 * (8 - query.len()));
+            return (suffix.key & mask).cmp(&query_key);
+        }
+        suffix.key.cmp(&query_key).then_with(|| {
+            let bytes = self.bytes(suffix);
+            bytes[..bytes.len().min(query.len())].cmp(query)
+        })
+    }
+
+    fn matching(&self, query: &str) -> Vec<&Suffix> {
+        let query = query.as_bytes();
+        let query_key = key(query);
+        let start = self
+            .sorted
+            .partition_point(|s| self.cmp_prefix(s, query, query_key) == Ordering::Less);
+        let len = self.sorted[start..]
+            .partition_point(|s| self.cmp_prefix(s, query, query_key) == Ordering::Equal);
+        self.sorted[start..start + len]
+            .iter()
+            .chain(
+                self.pending
+                    .iter()
+                    .filter(|s| self.bytes(s).starts_with(query)),
+            )
+            .collect()
+    }
+
+    fn rank_all(&self, matching: &[&Suffix], query_len: usize, kind: Kind) -> Vec<(Rank, u32)> {
+        matching
+            .iter()
+            .filter_map(|s| {
+                let entry = self.entry(s.slot);
+                if entry.doc.kind != kind || entry.removed {
+                    return None;
+                }
+                let rank = (
+                    s.tier(query_len),
+                    Reverse(entry.doc.popularity),
+                    entry.doc.label.len(),
+                    kind,
+                    entry.doc.id,
+                );
+                Some((rank, s.slot))
+            })
+            .collect()
+    }
+
+    fn ranked(&self, matching: &[&Suffix], query_len: usize, kind: Kind) -> Ranked {
+        let entries = self.rank_all(matching, query_len, kind);
+        Ranked {
+            truncated: entries.len() > CACHE_SIZE,
+            entries: best(entries, CACHE_SIZE),
+        }
+    }
+
+    // Ranks every hot query ahead of time, so that the first keystrokes
+    // after a restart are as fast as the later ones. Suffixes sharing their
+    // first `depth` bytes are split on the next one for as long as there are
+    // too many of them.
+    fn warm(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        let mut ranges = vec![(0, self.sorted.len(), 0)];
+        while let Some((start, end, depth)) = ranges.pop() {
+            if end - start <= self.hot {
+                continue;
+            }
+            let prefix = &self.bytes(&self.sorted[start])[..depth];
+            if let Ok(query) = std::str::from_utf8(prefix) {
+                if !query.is_empty() && !query.ends_with(' ') {
+                    let matching: Vec<&Suffix> = self.sorted[start..end].iter().collect();
+                    for kind in Kind::ALL {
+                        let ranked = self.ranked(&matching, query.len(), kind);
+                        cache
+                            .entry(kind)
+                            .or_default()
+                            .insert(query.to_string(), ranked);
+                    }
+                }
+            }
+            let next = |s: &Suffix| self.bytes(s).get(depth).copied();
+            let mut from = start;
+            while from < end {
+                let byte = next(&self.sorted[from]);
+                let to = from + self.sorted[from..end].partition_point(|s| next(s) <= byte);
+                if byte.is_some() {
+                    ranges.push((from, to, depth + 1));
+                }
+                from = to;
+            }
+        }
+    }
+
+    // Sorts the pending suffixes in, and drops those of removed entries.
+    fn merge(&mut self) {
+        let mut removed = vec![false; self.entries.len()];
+        for slot in &self.removed {
+            removed[*slot as usize] = true;
+        }
+        let live = |s: &Suffix| !removed[s.slot as usize];
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.retain(live);
+        pending.sort_unstable_by(|a, b| self.cmp(a, b));
+        let mut pending = pending.into_iter().peekable();
+        let mut merged = Vec::with_capacity(self.sorted.len() + pending.len());
+        for suffix in self.sorted.iter().filter(|s| live(s)) {
+            while let Some(p) = pending.next_if(|p| self.cmp(p, suffix) == Ordering::Less) {
+                merged.push(p);
+            }
+            merged.push(*suffix);
+        }
+        merged.extend(pending);
+        self.sorted = merged;
+        for slot in self.removed.drain(..) {
+            self.entries[slot as usize] = None;
+            self.free.push(slot);
+        }
+    }
+
+    fn add(&mut self, mut doc: Document) {
+        self.remove(doc.kind, doc.id);
+        let label = normalize(&doc.label);
+        if label.is_empty() {
+            return;
+        }
+        let (aliases, texts): (Vec<String>, Vec<String>) = std::mem::take(&mut doc.aliases)
+            .into_iter()
+            .map(|a| {
+                let text = normalize(&a);
+                (a, text)
+            })
+            .filter(|(_, text)| !text.is_empty())
+            .unzip();
+        doc.aliases = aliases;
+        let (kind, id) = (doc.kind, doc.id);
+        let entry = Entry {
+            doc,
+            texts: std::iter::once(label).chain(texts).collect(),
+            removed: false,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.entries.push(None);
+                (self.entries.len() - 1) as u32
+            }
+        };
+
+        for (i, text) in entry.texts.iter().enumerate() {
+            for offset in word_starts(text) {
+                self.pending.push(Suffix {
+                    key: key(&text.as_bytes()[offset..]),
+                    slot,
+                    text: i as u32,
+                    offset: offset as u32,
+                    len: (text.len() - offset) as u32,
+                });
+            }
+        }
+        if let Some(cache) = self.cache.get_mut().unwrap().get_mut(&kind) {
+            let doc = &entry.doc;
+            for (query, tier) in queries(&entry) {
+                if let Some(ranked) = cache.get_mut(query) {
+                    let rank = (tier, Reverse(doc.popularity), doc.label.len(), kind, id);
+                    let at = ranked.entries.partition_point(|(r, _)| *r < rank);
+                    ranked.entries.insert(at, (rank, slot));
+                    if ranked.entries.len() > CACHE_SIZE {
+                        ranked.entries.pop();
+                        ranked.truncated = true;
+                    }
+                }
+            }
+        }
+        self.entries[slot as usize] = Some(entry);
+        self.slots.insert((kind, id), slot);
+    }
+
+    pub fn insert(&mut self, doc: Document) {
+        self.add(doc);
+        if self.pending.len() > PENDING {
+            self.merge();
+        }
+    }
+
+    pub fn remove(&mut self, kind: Kind, id: i64) {
+        let slot = match self.slots.remove(&(kind, id)) {
+            Some(slot) => slot,
+            None => return,
+        };
+        self.removed.push(slot);
+        let entry = self.entries[slot as usize].as_mut().unwrap();
+        entry.removed = true;
+
+        let entry = self.entries[slot as usize].as_ref().unwrap();
+        if let Some(cache) = self.cache.get_mut().unwrap().get_mut(&kind) {
+            for query in queries(entry).into_keys() {
+                if let Some(ranked) = cache.get_mut(query) {
+                    ranked.entries.retain(|(_, s)| *s != slot);
+                    // Too few left to answer every limit, rank it again when asked.
+                    if ranked.truncated && ranked.entries.len() < MAX_LIMIT {
+                        cache.remove(query);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn search(&self, query: &str, kinds: &[Kind], limit: usize) -> Vec<Suggestion> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let limit = limit.min(MAX_LIMIT);
+        let mut kinds = kinds.to_vec();
+        kinds.sort_unstable();
+        kinds.dedup();
+
+        let mut ranked: Vec<(Rank, u32)> = Vec::new();
+        let mut cache = self.cache.lock().unwrap();
+        let mut matching = None;
+        for kind in kinds {
+            if let Some(cached) = cache.get(&kind).and_then(|c| c.get(&query)) {
+                ranked.extend(cached.entries.iter().take(limit));
+                continue;
+            }
+            let matching = matching.get_or_insert_with(|| self.matching(&query));
+            if matching.len() <= self.hot {
+                ranked.extend(best(self.rank_all(matching, query.len(), kind), limit));
+                continue;
+            }
+            let cached = self.ranked(matching, query.len(), kind);
+            ranked.extend(cached.entries.iter().take(limit));
+            cache.entry(kind).or_default().insert(query.clone(), cached);
+        }
+        drop(cache);
+
+        best(ranked, limit)
+            .into_iter()
+            .map(|(_, slot)| {
+                let entry = self.entry(slot);
+                let text = (0..entry.texts.len())
+                    .min_by_key(|i| tier(&entry.texts[*i], &query).unwrap_or(u8::MAX))
+                    .unwrap();
+                Suggestion {
+                    kind: entry.doc.kind,
+                    id: entry.doc.id,
+                    label: entry.doc.label.clone(),
+                    detail: entry.doc.detail.clone(),
+                    matched: text.checked_sub(1).map(|i| entry.doc.aliases[i].clone()),
+                    popularity: entry.doc.popularity,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(id: i64, title: &str, popularity: i64) -> Document {
+        Document {
+            kind: Kind::Book,
+            id,
+            label: title.to_string(),
+            detail: None,
+            aliases: Vec::new(),
+            popularity,
+        }
+    }
+
+    fn author(id: i64, name: &str, aliases: &[&str]) -> Document {
+        Document {
+            kind: Kind::Author,
+            id,
+            label: name.to_string(),
+            detail: None,
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            popularity: 0,
+        }
+    }
+
+    fn ids(suggestions: Vec<Suggestion>) -> Vec<i64> {
+        suggestions.iter().map(|s| s.id).collect()
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let mut index = Index::default();
+        index.insert(book(1, "Anna Karenina", 1));
+        index.insert(book(2, "The Annals", 9));
+        index.insert(book(3, "Anna", 0));
+        index.insert(book(4, "Annapurna", 5));
+        index.insert(book(5, "Karenina, Anna", 2));
+
+        // Exact, then prefix of the title, then word prefix by popularity.
+        assert_eq!(ids(index.search("anna", &Kind::ALL, 10)), [3, 4, 1, 2, 5]);
+        assert_eq!(ids(index.search("ann", &Kind::ALL, 10)), [4, 1, 3, 2, 5]);
+        assert_eq!(ids(index.search("ÄNN", &Kind::ALL, 2)), [4, 1]);
+        assert_eq!(ids(index.search("anna k", &Kind::ALL, 10)), [1]);
+        assert_eq!(ids(index.search("karenina an", &Kind::ALL, 10)), [5]);
+        assert_eq!(ids(index.search("the anna", &Kind::ALL, 10)), [2]);
+        assert_eq!(
+            ids(index.search("the k", &Kind::ALL, 10)),
+            Vec::<i64>::new()
+        );
+        assert_eq!(ids(index.search(" ", &Kind::ALL, 10)), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_search_aliases_and_kinds() {
+        let mut index = Index::default();
+        index.insert(author(1, "Mark Twain", &["Samuel Clemens"]));
+        index.insert(book(1, "Samuel Beckett: A Life", 0));
+
+        let suggestions = index.search("sam", &[Kind::Author], 10);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].label, "Mark Twain");
+        assert_eq!(suggestions[0].matched.as_deref(), Some("Samuel Clemens"));
+        assert_eq!(index.search("twa", &[Kind::Author], 10)[0].matched, None);
+        assert_eq!(index.search("sam", &Kind::ALL, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_cached_prefixes_follow_changes() {
+        let mut index = Index {
+            hot: 0,
+            ..Index::default()
+        };
+        for id in 0..(CACHE_SIZE as i64 + 10) {
+            index.insert(book(id, &format!("Book {}", id), id));
+        }
+        index.merge();
+        index.warm();
+        assert_eq!(ids(index.search("bo", &Kind::ALL, 1)), [109]);
+
+        index.insert(book(500, "Bo", 0));
+        assert_eq!(ids(index.search("bo", &Kind::ALL, 1)), [500]);
+        index.insert(book(500, "Atom Bomb", 1000));
+        assert_eq!(ids(index.search("bo", &Kind::ALL, 2)), [109, 108]);
+        assert_eq!(ids(index.search("bom", &Kind::ALL, 2)), [500]);
+
+        // Dropping below what a search may ask for ranks the prefix again.
+        for id in 50..(CACHE_SIZE as i64 + 10) {
+            index.remove(Kind::Book, id);
+        }
+        assert_eq!(ids(index.search("bo", &Kind::ALL, 3)), [49, 48, 47]);
+        assert_eq!(index.search("bo", &Kind::ALL, MAX_LIMIT).len(), MAX_LIMIT);
+    }
+
+    #[test]
+    fn test_search_across_merges() {
+        let words = ["red", "rose", "river", "road", "rain"];
+        let title = |id: i64| {
+            (0..3)
+                .map(|i| words[((id * 7 + i * 3) % 5) as usize])
+                .collect::<Vec<&str>>()
+                .join(" ")
+        };
+        let mut index = Index::default();
+        let mut titles = HashMap::new();
+        for id in 0..(PENDING as i64) {
+            index.insert(book(id, &title(id), id % 13));
+            titles.insert(id, title(id));
+        }
+        for id in (0..(PENDING as i64)).step_by(3) {
+            index.remove(Kind::Book, id);
+            titles.remove(&id);
+        }
+        for id in (1..(PENDING as i64)).step_by(3) {
+            index.insert(book(id, &title(id + 1), id % 7));
+            titles.insert(id, title(id + 1));
+        }
+
+        for query in ["r", "ro", "rose r", "red river rain", "rain ro"] {
+            let mut expected: Vec<i64> = titles
+                .iter()
+                .filter(|(_, t)| tier(t, query).is_some())
+                .map(|(id, _)| *id)
+                .collect();
+            expected.sort_unstable();
+            let mut found = ids(index.search(query, &Kind::ALL, MAX_LIMIT));
+            found.sort_unstable();
+            assert_eq!(found.len(), expected.len().min(MAX_LIMIT), "{}", query);
+            assert!(found.iter().all(|id| expected.contains(id)), "{}", query);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod autocomplete;
+mod autocomplete_db;
+mod autocomplete_queries;
+pub mod index;
@@ -1,9 +1,324 @@
+use super::super::constants::BOOKS_TABLE;
+use super::super::contributors::contributor::{Contributor, Role};
+use super::super::contributors::contributors_queries;
+use super::super::resources::filter::{param, Clause};
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use super::super::series::book_series::SeriesLink;
+use super::super::subjects::subjects_queries;
+use super::super::tags::tag::normalize_name;
+use super::super::tags::tags_queries;
+use super::isbn;
+use super::publication_date;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use serde_json::Map;
+use sqlx::{types::Json, FromRow};
 
 #[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
 pub struct Book {
     pub id: Option<i64>,
     pub title: Option<String>,
     pub author: Option<String>,
+    pub publisher_id: Option<i64>,
+    // Stored as ISBN-13, see `isbn::to_isbn13`.
+    pub isbn: Option<String>,
+    // A year, year-month or full date, see `publication_date`.
+    pub publication_date: Option<String>,
+    // ISO 639 code, e.g. `en` or `fra`.
+    pub language: Option<String>,
+    pub page_count: Option<i64>,
+    pub edition: Option<String>,
+    pub description: Option<String>,
+    // Custom fields, declared through `/metadata/fields`.
+    pub metadata: Option<Json<Map<String, serde_json::Value>>>,
+    // Maintained by the reviews module whenever a review is written.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub average_rating: Option<f64>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub review_count: Option<i64>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub copy_count: Option<i64>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub available_copies: Option<i64>,
+    // Set through `PUT /books/{id}/contributors`, in position order.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub contributors: Option<Json<Vec<Contributor>>>,
+    // Set through `PUT /series/{id}/volumes/{book_id}`.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub series: Option<Json<Vec<SeriesLink>>>,
+}
+
+impl Resource for Book {
+    const PATH: &'static str = "/books";
+    const TABLE: &'static str = BOOKS_TABLE;
+    const FIELDS: &'static [&'static str] = &[
+        "title",
+        "author",
+        "publisher_id",
+        "isbn",
+        "publication_date",
+        "language",
+        "page_count",
+        "edition",
+        "description",
+        "metadata",
+    ];
+    const FILTERABLE: &'static [&'static str] = &["title", "author", "publisher_id", "language"];
+    const SORTABLE: &'static [&'static str] = &[
+        "id",
+        "title",
+        "author",
+        "average_rating",
+        "review_count",
+        "available_copies",
+        "publication_date",
+        "page_count",
+    ];
+    const SELECT: &'static str = "*, \
+        (SELECT COUNT(*) FROM copies WHERE copies.book_id = books.id) AS copy_count, \
+        (SELECT COUNT(*) FROM copies WHERE copies.book_id = books.id \
+          AND copies.status = 'available' AND copies.id NOT IN \
+          (SELECT copy_id FROM holds WHERE status = 'ready' AND copy_id IS NOT NULL)) \
+          AS available_copies, \
+        (SELECT json_group_array(json_object('author_id', c.author_id, 'name', c.name, \
+          'role', c.role, 'position', c.position)) FROM \
+          (SELECT bc.author_id, a.name, bc.role, bc.position FROM book_contributors bc \
+          JOIN authors a ON a.id = bc.author_id WHERE bc.book_id = books.id \
+          ORDER BY bc.position) c) AS contributors, \
+        (SELECT json_group_array(json_object('series_id', v.series_id, 'name', v.name, \
+          'position', v.position, 'alternate', json(CASE WHEN v.alternate THEN 'true' ELSE 'false' END), \
+          'previous_book_id', (SELECT p.book_id FROM series_books p WHERE p.series_id = v.series_id \
+            AND p.alternate = 0 AND p.position < v.position ORDER BY p.position DESC LIMIT 1), \
+          'next_book_id', (SELECT n.book_id FROM series_books n WHERE n.series_id = v.series_id \
+            AND n.alternate = 0 AND n.position > v.position ORDER BY n.position LIMIT 1))) FROM \
+          (SELECT sb.series_id, s.name, sb.position, sb.alternate FROM series_books sb \
+          JOIN series s ON s.id = sb.series_id WHERE sb.book_id = books.id \
+          ORDER BY s.name, s.id) v) AS series";
+    const UNIQUE: &'static [&'static str] = &["isbn"];
+    const METADATA: bool = true;
+    const PARAMS: &'static [&'static str] = &[
+        "tags",
+        "tag_mode",
+        "min_rating",
+        "max_rating",
+        "available",
+        "contributor_id",
+        "role",
+        "subject",
+        "include_descendants",
+        "published_after",
+        "published_before",
+        "min_pages",
+        "max_pages",
+        "facets",
+        "facet_limit",
+        "envelope",
+    ];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "title" => Value::from(self.title.clone()),
+            "author" => Value::from(self.author.clone()),
+            "publisher_id" => Value::from(self.publisher_id),
+            "isbn" => Value::from(self.isbn.clone()),
+            "publication_date" => Value::from(self.publication_date.clone()),
+            "language" => Value::from(self.language.clone()),
+            "page_count" => Value::from(self.page_count),
+            "edition" => Value::from(self.edition.clone()),
+            "description" => Value::from(self.description.clone()),
+            "metadata" => Value::from(
+                self.metadata
+                    .as_ref()
+                    .and_then(|m| serde_json::to_string(&m.0).ok()),
+            ),
+            "average_rating" => Value::from(self.average_rating),
+            "review_count" => Value::from(self.review_count),
+            "available_copies" => Value::from(self.available_copies),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "title" => self.title = value.into(),
+            "author" => self.author = value.into(),
+            "publisher_id" => self.publisher_id = value.into(),
+            "isbn" => self.isbn = value.into(),
+            "publication_date" => self.publication_date = value.into(),
+            "language" => self.language = value.into(),
+            "page_count" => self.page_count = value.into(),
+            "edition" => self.edition = value.into(),
+            "description" => self.description = value.into(),
+            "metadata" => {
+                self.metadata = Option::<String>::from(value)
+                    .and_then(|m| serde_json::from_str(&m).ok())
+                    .map(Json)
+            }
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        if let Some(Ok(isbn)) = self.isbn.as_deref().map(isbn::to_isbn13) {
+            self.isbn = Some(isbn);
+        }
+        self.publication_date = self
+            .publication_date
+            .as_deref()
+            .map(|d| d.trim().to_string());
+        self.language = self.language.as_deref().map(|l| l.trim().to_lowercase());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("title", &self.title)?;
+        validate_not_blank("author", &self.author)?;
+        validate_not_blank("edition", &self.edition)?;
+        if let Some(i) = &self.isbn {
+            isbn::to_isbn13(i)?;
+        }
+        if let Some(d) = &self.publication_date {
+            publication_date::validate("publication_date", d)?;
+        }
+        if let Some(l) = &self.language {
+            validate_language(l)?;
+        }
+        match self.page_count {
+            Some(p) if p < 1 => Err(format!("page_count must be at least 1 (got {})", p)),
+            _ => Ok(()),
+        }
+    }
+
+    fn clauses(params: &[(String, String)]) -> Result<Vec<Clause>, String> {
+        let mut clauses = Vec::new();
+
+        let all = match param(params, "tag_mode") {
+            None | Some("all") => true,
+            Some("any") => false,
+            Some(m) => return Err(format!("tag_mode must be all or any (got {})", m)),
+        };
+        if let Some(tags) = param(params, "tags") {
+            let names: Vec<String> = tags
+                .split(',')
+                .map(normalize_name)
+                .filter(|n| !n.is_empty())
+                .collect();
+            if !names.is_empty() {
+                clauses.push(tags_queries::books_with_tags_clause(names, all));
+            }
+        }
+
+        for (key, op) in [("min_rating", ">="), ("max_rating", "<=")] {
+            if let Some(v) = param(params, key) {
+                let rating: f64 = v.parse().map_err(|_| format!("{} must be a number", key))?;
+                clauses.push(Clause {
+                    sql: format!("average_rating {} ?", op),
+                    binds: vec![Value::Real(rating)],
+                });
+            }
+        }
+
+        let available = match param(params, "available") {
+            None => None,
+            Some("true") => Some("IN"),
+            Some("false") => Some("NOT IN"),
+            Some(v) => return Err(format!("available must be true or false (got {})", v)),
+        };
+        if let Some(op) = available {
+            clauses.push(Clause {
+                sql: format!(
+                    "id {} (SELECT book_id FROM copies WHERE status = 'available' \
+                     AND id NOT IN (SELECT copy_id FROM holds \
+                     WHERE status = 'ready' AND copy_id IS NOT NULL))",
+                    op
+                ),
+                binds: Vec::new(),
+            });
+        }
+
+        let contributor_id = match param(params, "contributor_id") {
+            Some(v) => Some(
+                v.parse::<i64>()
+                    .map_err(|_| "contributor_id must be an integer".to_string())?,
+            ),
+            None => None,
+        };
+        let role = param(params, "role").map(Role::parse).transpose()?;
+        if contributor_id.is_some() || role.is_some() {
+            clauses.push(contributors_queries::books_with_contributor_clause(
+                contributor_id,
+                role.map(|r| r.as_str()),
+            ));
+        }
+
+        let descendants = match param(params, "include_descendants") {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(v) => {
+                return Err(format!(
+                    "include_descendants must be true or false (got {})",
+                    v
+                ))
+            }
+        };
+        if let Some(code) = param(params, "subject") {
+            clauses.push(subjects_queries::books_with_subject_clause(
+                code.trim(),
+                descendants,
+            ));
+        }
+
+        // Bounds are compared at their own precision, so `published_before=1990`
+        // also matches books from May 1990.
+        for (key, op) in [("published_after", ">="), ("published_before", "<=")] {
+            if let Some(v) = param(params, key) {
+                publication_date::validate(key, v)?;
+                clauses.push(Clause {
+                    sql: format!("substr(publication_date, 1, {}) {} ?", v.len(), op),
+                    binds: vec![Value::Text(v.to_string())],
+                });
+            }
+        }
+
+        for (key, op) in [("min_pages", ">="), ("max_pages", "<=")] {
+            if let Some(v) = param(params, key) {
+                let pages: i64 = v
+                    .parse()
+                    .map_err(|_| format!("{} must be an integer", key))?;
+                clauses.push(Clause {
+                    sql: format!("page_count {} ?", op),
+                    binds: vec![Value::Integer(pages)],
+                });
+            }
+        }
+
+        Ok(clauses)
+    }
+}
+
+// Two-letter ISO 639-1 or three-letter ISO 639-2/3 codes.
+fn validate_language(language: &str) -> Result<(), String> {
+    if (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "language must be an ISO 639 code like en or fra (got {})",
+            language
+        ))
+    }
 }
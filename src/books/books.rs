@@ -1,78 +1,169 @@
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
 use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
 
-use super::super::responses::CreateResponse;
+use super::super::metadata::metadata;
+use super::super::resources::filter::{param, Filters};
+use super::super::resources::resources;
+use super::super::resources::resources_repository::Repository;
 use super::super::responses::CustomError;
+use super::super::search::search::{did_you_mean, Hits, Search};
 use super::book::Book;
-use super::books_db;
-use super::filter::Filters;
+use super::books_db::{self, Delete};
+use super::facet::{self, Facet, FacetCount};
+use super::isbn;
 
 pub fn config_books(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_books)
-        .service(get_book)
-        .service(create_book)
-        .service(update_book)
-        .service(delete_book);
+    cfg.service(get_book_by_isbn)
+        .service(convert_isbn)
+        .service(
+            web::resource("/books")
+                .route(web::get().to(get_books))
+                .route(web::post().to(metadata::create::<Book>)),
+        )
+        .service(
+            resources::item::<Book>()
+                .route(web::get().to(resources::get_one::<Book>))
+                .route(web::put().to(metadata::update::<Book>))
+                .route(web::delete().to(delete_book)),
+        );
 }
 
-#[get("/books")]
-async fn get_books(filter: web::Query<Filters>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = books_db::get_books(filter, pool).await;
+// Books that were ever lent are kept for the loan history.
+async fn delete_book(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> HttpResponse {
+    let id = id.into_inner();
+    let r = books_db::delete_book(pool.get_ref(), id).await;
 
     match r {
-        Ok(v) => HttpResponse::Ok().json(v),
+        Ok(Delete::Done) => HttpResponse::Ok().json("Deleted"),
+        Ok(Delete::Lent) => HttpResponse::Conflict().json(CustomError::message(format!(
+            "book {} has been lent before and is kept for the loan history",
+            id
+        ))),
         Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
     }
 }
 
-#[get("/books/{id}")]
-async fn get_book(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = books_db::get_book(pool, req.match_info().query("id").parse().unwrap()).await;
-
-    match r {
-        Ok(v) => HttpResponse::Ok().json(v),
-        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+// Counts for each of `facets`, every value being counted under all the
+// active filters except those on the facet itself.
+async fn get_facets(
+    pool: &Pool<Sqlite>,
+    params: &[(String, String)],
+    facets: Vec<Facet>,
+    limit: u32,
+) -> Result<BTreeMap<&'static str, Vec<FacetCount>>, HttpResponse> {
+    let mut counts = BTreeMap::new();
+    for facet in facets {
+        let filter = metadata::filters::<Book>(pool, &facet.other_params(params)).await?;
+        let r = books_db::get_facet_counts(pool, facet, &filter, limit)
+            .await
+            .map_err(|e| HttpResponse::InternalServerError().json(CustomError::new(e)))?;
+        counts.insert(facet.as_str(), r);
     }
-}
 
-#[post("/books")]
-async fn create_book(json: web::Json<Book>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = books_db::create_book(pool, json.clone()).await;
+    Ok(counts)
+}
 
-    match r {
-        Ok(r) => HttpResponse::Created().json(CreateResponse {
-            id: r.last_insert_rowid(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+// The generic listing. With `envelope=true` the books come as the hits of a
+// `search::Hits`, along with the `facets` asked for and, when a `title`
+// matches nothing, a `did_you_mean` correction.
+async fn get_books(
+    params: web::Query<Vec<(String, String)>>,
+    books: web::Data<dyn Repository<Book>>,
+    pool: web::Data<Pool<Sqlite>>,
+    search: Option<web::Data<Search>>,
+) -> HttpResponse {
+    let envelope = match param(&params, "envelope") {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(v) => {
+            return HttpResponse::BadRequest().json(CustomError::message(format!(
+                "envelope must be true or false (got {})",
+                v
+            )))
+        }
+    };
+    let (facets, facet_limit) = match facet::parse_params(&params) {
+        Ok(f) => f,
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    if !envelope && !facets.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(CustomError::message("facets requires envelope=true"));
+    }
+    let filter = match metadata::filters::<Book>(pool.get_ref(), &params).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let hits = match books.get_all(&filter).await {
+        Ok(hits) => hits,
+        Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+    };
+    if !envelope {
+        return HttpResponse::Ok().json(hits);
     }
+
+    let facets = match get_facets(pool.get_ref(), &params, facets, facet_limit).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let did_you_mean = match (param(&params, "title"), &search) {
+        (Some(title), Some(search)) if hits.is_empty() => {
+            let r = did_you_mean(search, pool.get_ref(), "/books", &params, "title", title);
+            match r.await {
+                Ok(d) => d,
+                Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+            }
+        }
+        _ => None,
+    };
+    HttpResponse::Ok().json(Hits {
+        hits,
+        facets,
+        did_you_mean,
+    })
 }
 
-#[put("/books/{id}")]
-async fn update_book(
-    req: HttpRequest,
-    json: web::Json<Book>,
-    pool: web::Data<Pool<Sqlite>>,
+// Either form of the ISBN is accepted, hyphenated or not.
+#[get("/books/isbn/{isbn}")]
+async fn get_book_by_isbn(
+    isbn: web::Path<String>,
+    books: web::Data<dyn Repository<Book>>,
 ) -> impl Responder {
-    let r = books_db::update_book(
-        json.clone(),
-        pool,
-        req.match_info().query("id").parse().unwrap(),
-    )
-    .await;
+    let isbn = match isbn::to_isbn13(&isbn) {
+        Ok(i) => i,
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    let r = books
+        .get_all(&Filters::default().by("isbn", isbn).limit(1))
+        .await
+        .and_then(|v| v.into_iter().next().ok_or(sqlx::Error::RowNotFound));
 
     match r {
-        Ok(_) => HttpResponse::Ok().json("Updated"),
+        Ok(v) => HttpResponse::Ok().json(v),
         Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
     }
 }
 
-#[delete("/books/{id}")]
-async fn delete_book(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
-    let r = books_db::delete_book(pool, req.match_info().query("id").parse().unwrap()).await;
+#[derive(Serialize)]
+pub struct IsbnForms {
+    pub isbn13: String,
+    pub isbn10: Option<String>,
+}
+
+#[get("/isbn/{isbn}")]
+async fn convert_isbn(isbn: web::Path<String>) -> impl Responder {
+    let r = isbn::to_isbn13(&isbn).and_then(|isbn13| {
+        Ok(IsbnForms {
+            isbn10: isbn::to_isbn10(&isbn13)?,
+            isbn13,
+        })
+    });
 
     match r {
-        Ok(_) => HttpResponse::Ok().json("Deleted"),
-        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::BadRequest().json(CustomError::message(e)),
     }
 }
 
@@ -80,180 +171,184 @@ async fn delete_book(req: HttpRequest, pool: web::Data<Pool<Sqlite>>) -> impl Re
 mod tests {
     use super::super::super::*;
     use crate::books::book::Book;
+    use crate::resources::resources_db::SqliteRepository;
+    use crate::resources::resources_repository::Repository;
+    use crate::test_utils::{self, book, member, test_pool};
     use actix_web::{
         http::{self},
         test,
     };
-    use sqlx::{
-        sqlite::{SqliteConnectOptions, SqlitePoolOptions},
-        Error, Pool, Sqlite,
-    };
-    use std::str::FromStr;
-    const DATABASE_TEST_URL: &str = "sqlite://db_test.sqlite";
-
-    pub async fn establish_connection() -> Result<Pool<Sqlite>, Error> {
-        let connection_options =
-            SqliteConnectOptions::from_str(DATABASE_TEST_URL)?.create_if_missing(true);
-
-        let sqlite_pool = SqlitePoolOptions::new()
-            .max_connections(constants::POOL_MAX_CONNECTIONS)
-            .connect_with(connection_options)
-            .await?;
-
-        sqlx::query(&format!(
-            "
-        CREATE TABLE IF NOT EXISTS {} (
-          id INTEGER PRIMARY KEY AUTOINCREMENT,
-          title text,
-          author text
-        );
-        ",
-            constants::BOOKS_TABLE
-        ))
-        .execute(&sqlite_pool.clone())
-        .await?;
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(super::config_books),
+            )
+            .await
+        }};
+    }
 
-        Ok(sqlite_pool)
+    fn titles(body: &Value) -> Vec<&str> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["title"].as_str().unwrap())
+            .collect()
     }
 
     #[actix_web::test]
     async fn test_get_books() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_books)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        book().title("Dune").create(&conn_pool).await;
+        book().title("Emma").create(&conn_pool).await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get().uri("/books").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Dune", "Emma"]);
     }
 
     #[actix_web::test]
     async fn test_get_books_limit() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_books)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        for title in ["Dune", "Emma", "Persuasion"] {
+            book().title(title).create(&conn_pool).await;
+        }
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get().uri("/books?limit=2").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Dune", "Emma"]);
     }
 
     #[actix_web::test]
     async fn test_get_books_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_books)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        book()
+            .title("Dune")
+            .author("herbert")
+            .create(&conn_pool)
+            .await;
+        book().title("Rust").author("saeb").create(&conn_pool).await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get()
             .uri("/books?author=saeb")
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Rust"]);
     }
 
     #[actix_web::test]
     async fn test_get_books_limit_author() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_books)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        book()
+            .title("Dune")
+            .author("herbert")
+            .create(&conn_pool)
+            .await;
+        book().title("Rust").author("saeb").create(&conn_pool).await;
+        book()
+            .title("Actix")
+            .author("saeb")
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get()
             .uri("/books?author=saeb&limit=1")
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Rust"]);
     }
 
     #[actix_web::test]
-    async fn test_get_book() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_book)
-                .service(super::create_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+    async fn test_get_books_unknown_filter() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
-        let req = test::TestRequest::post()
-            .set_json(Book {
-                id: Some(1),
-                title: Some("test1".to_string()),
-                author: Some("test1".to_string()),
-            })
-            .uri("/books")
+        let req = test::TestRequest::get()
+            .uri("/books?colour=red")
             .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "unknown filter colour");
+    }
 
-        test::call_service(&app, req).await;
+    #[actix_web::test]
+    async fn test_get_book() {
+        let conn_pool = test_pool().await;
+        let b = book()
+            .title("test1")
+            .author("test1")
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
 
-        let req = test::TestRequest::get().uri("/books/1").to_request();
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}", b.id.unwrap()))
+            .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body,
+            json!({"id": b.id, "title": "test1", "author": "test1", "publisher_id": null, "isbn": null, "publication_date": null, "language": null, "page_count": null, "edition": null, "description": null, "average_rating": null, "review_count": 0, "copy_count": 0, "available_copies": 0, "contributors": [], "series": [], "metadata": null})
+        );
     }
 
     #[actix_web::test]
     async fn test_get_book_internal_server_error() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::get_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::get().uri("/books/0").to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains("no rows"));
     }
 
     #[actix_rt::test]
     async fn test_create_book() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::create_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::post()
-            .set_json(Book {
-                id: Some(1),
-                title: Some("test1".to_string()),
-                author: Some("test1".to_string()),
-            })
+            .set_json(book().title("test1").author("test1").build())
             .uri("/books")
             .to_request();
 
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+        let id = body["id"].as_i64().unwrap();
+
+        let created = SqliteRepository::<Book>::new(conn_pool)
+            .get_one(id)
+            .await
+            .unwrap();
+        assert_eq!(created.title.as_deref(), Some("test1"));
+        assert_eq!(created.author.as_deref(), Some("test1"));
     }
 
     #[actix_rt::test]
     async fn test_create_book_bad_request() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::create_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::post()
             .set_json("")
@@ -264,37 +359,53 @@ mod tests {
         assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
     }
 
+    #[actix_rt::test]
+    async fn test_create_book_blank_title() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(book().title(" ").build())
+            .uri("/books")
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "title must not be blank");
+    }
+
     #[actix_web::test]
     async fn test_update_book() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::update_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let b = book()
+            .title("Dune")
+            .author("herbert")
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::put()
-            .uri("/books/1")
-            .set_json(Book {
-                id: Some(1),
-                title: Some("test1".to_string()),
-                author: Some("test1".to_string()),
-            })
+            .uri(&format!("/books/{}", b.id.unwrap()))
+            .set_json(json!({"title": "Dune Messiah"}))
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, "Updated");
+
+        let updated = SqliteRepository::<Book>::new(conn_pool)
+            .get_one(b.id.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(updated.title.as_deref(), Some("Dune Messiah"));
+        assert_eq!(updated.author.as_deref(), Some("herbert"));
     }
 
     #[actix_web::test]
     async fn test_update_book_bad_request() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::update_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
 
         let req = test::TestRequest::put()
             .uri("/books/1")
@@ -304,18 +415,380 @@ mod tests {
         assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
     }
 
+    #[actix_rt::test]
+    async fn test_create_book_normalizes_isbn() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(book().isbn("0-306-40615-2").build())
+            .uri("/books")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+
+        let created = SqliteRepository::<Book>::new(conn_pool)
+            .get_one(body["id"].as_i64().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(created.isbn.as_deref(), Some("9780306406157"));
+    }
+
+    #[actix_rt::test]
+    async fn test_create_book_invalid_isbn() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(book().isbn("978-0-306-40615-8").build())
+            .uri("/books")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            "978-0-306-40615-8 is not a valid ISBN-10 or ISBN-13"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_create_book_duplicate_isbn() {
+        let conn_pool = test_pool().await;
+        let b = book().isbn("9780306406157").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(book().isbn("0306406152").build())
+            .uri("/books")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], b.id.unwrap());
+        assert_eq!(body["location"], format!("/books/{}", b.id.unwrap()));
+    }
+
+    #[actix_web::test]
+    async fn test_get_book_by_isbn() {
+        let conn_pool = test_pool().await;
+        book().title("Dune").create(&conn_pool).await;
+        let b = book()
+            .title("Emma")
+            .isbn("9780306406157")
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
+
+        for isbn in ["978-0-306-40615-7", "0-306-40615-2"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/books/isbn/{}", isbn))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["id"], b.id.unwrap());
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/books/isbn/9780804429573")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        for isbn in ["123", "12345678%C3%A9"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/books/isbn/{}", isbn))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_convert_isbn() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri("/isbn/0-306-40615-2")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body,
+            json!({"isbn13": "9780306406157", "isbn10": "0306406152"})
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/isbn/979-10-90636-07-1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, json!({"isbn13": "9791090636071", "isbn10": null}));
+    }
+
+    #[actix_web::test]
+    async fn test_create_book_bibliographic_fields() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .uri("/books")
+            .set_json(json!({
+                "title": "Dune",
+                "author": "Herbert",
+                "publication_date": " 1965-08 ",
+                "language": "EN",
+                "page_count": 412,
+                "edition": "First edition",
+                "description": "Spice."
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}", body["id"]))
+            .to_request();
+        let b: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(b["publication_date"], "1965-08");
+        assert_eq!(b["language"], "en");
+        assert_eq!(b["page_count"], 412);
+        assert_eq!(b["edition"], "First edition");
+        assert_eq!(b["description"], "Spice.");
+    }
+
+    #[actix_web::test]
+    async fn test_create_book_invalid_bibliographic_fields() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for (field, value) in [
+            ("publication_date", json!("1965-13")),
+            ("publication_date", json!("65")),
+            ("language", json!("english")),
+            ("page_count", json!(0)),
+            ("edition", json!(" ")),
+        ] {
+            let mut b = json!({"title": "Dune", "author": "Herbert"});
+            b[field] = value;
+            let req = test::TestRequest::post()
+                .uri("/books")
+                .set_json(b)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", field);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_by_publication_and_language() {
+        let conn_pool = test_pool().await;
+        book()
+            .title("Dune")
+            .published("1965-08-01")
+            .language("en")
+            .page_count(412)
+            .create(&conn_pool)
+            .await;
+        book()
+            .title("Jane Eyre")
+            .published("1990-05")
+            .language("en")
+            .page_count(500)
+            .create(&conn_pool)
+            .await;
+        book()
+            .title("Neuromancien")
+            .published("1992")
+            .language("fr")
+            .page_count(320)
+            .create(&conn_pool)
+            .await;
+        book().title("Undated").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        for (uri, expected) in [
+            ("/books?published_after=1990&language=en", vec!["Jane Eyre"]),
+            ("/books?published_after=1990-06", vec!["Neuromancien"]),
+            ("/books?published_before=1990", vec!["Dune", "Jane Eyre"]),
+            ("/books?min_pages=400&max_pages=450", vec!["Dune"]),
+            (
+                "/books?sort=-publication_date&published_after=1900",
+                vec!["Neuromancien", "Jane Eyre", "Dune"],
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(titles(&body), expected, "{}", uri);
+        }
+
+        for uri in ["/books?published_after=90", "/books?min_pages=many"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_facets() {
+        let conn_pool = test_pool().await;
+        for (title, author, published, language) in [
+            ("Dune", "Herbert", "1965-08-01", "en"),
+            ("Children of Dune", "Herbert", "1976", "en"),
+            ("Emma", "Austen", "1815", "en"),
+            ("Neuromancien", "Gibson", "1985", "fr"),
+            ("Neuromancer", "Gibson", "1984", "en"),
+        ] {
+            book()
+                .title(title)
+                .author(author)
+                .published(published)
+                .language(language)
+                .create(&conn_pool)
+                .await;
+        }
+        book().title("Anonymous").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri(
+                "/books?language=en&published_after=1900&facets=author,language,decade&limit=1\
+                 &envelope=true",
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body["hits"]), vec!["Dune"]);
+        // The language counts ignore `language=en`, the decade counts
+        // `published_after`, and none of them the limit.
+        assert_eq!(
+            body["facets"],
+            json!({
+                "author": [{"value": "Herbert", "count": 2}, {"value": "Gibson", "count": 1}],
+                "language": [{"value": "en", "count": 3}, {"value": "fr", "count": 1}],
+                "decade": [
+                    {"value": "1810s", "count": 1},
+                    {"value": "1960s", "count": 1},
+                    {"value": "1970s", "count": 1},
+                    {"value": "1980s", "count": 1}
+                ]
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/books?facets=author&facet_limit=1&envelope=true")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            body["facets"],
+            json!({"author": [{"value": "Gibson", "count": 2}]})
+        );
+
+        for (uri, message) in [
+            ("/books?facets=author", "facets requires envelope=true"),
+            (
+                "/books?facets=publisher",
+                "facets must be one of author, tag, language, decade (got publisher)",
+            ),
+            (
+                "/books?facets=author&facet_limit=0",
+                "facet_limit must be between 1 and 100 (got 0)",
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", uri);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_envelope() {
+        let conn_pool = test_pool().await;
+        book().title("Dune").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri("/books?envelope=true")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(titles(&body["hits"]), vec!["Dune"]);
+        assert_eq!(body["facets"], json!({}));
+        assert_eq!(body["did_you_mean"], Value::Null);
+        let req = test::TestRequest::get()
+            .uri("/books?envelope=false")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(titles(&body), vec!["Dune"]);
+
+        let req = test::TestRequest::get()
+            .uri("/books?envelope=yes")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "envelope must be true or false (got yes)");
+    }
+
     #[actix_web::test]
     async fn test_delete_book() {
-        let conn_pool = establish_connection().await.unwrap();
-        let app = test::init_service(
-            App::new()
-                .service(super::delete_book)
-                .app_data(web::Data::new(conn_pool.clone())),
-        )
-        .await;
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await;
+        let app = app!(conn_pool);
 
-        let req = test::TestRequest::delete().uri("/books/2").to_request();
+        let req = test::TestRequest::delete()
+            .uri(&format!("/books/{}", b.id.unwrap()))
+            .to_request();
         let resp = test::call_service(&app, req).await;
         assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, "Deleted");
+
+        let remaining = SqliteRepository::<Book>::new(conn_pool)
+            .get_one(b.id.unwrap())
+            .await;
+        assert!(remaining.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_lent_book() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        sqlx::query(
+            "INSERT INTO loans (book_id, member_id, loaned_at, due_at, returned_at) \
+             values (?, ?, '2024-01-01', '2024-01-15', '2024-01-10')",
+        )
+        .bind(b)
+        .bind(m)
+        .execute(&conn_pool)
+        .await
+        .unwrap();
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/books/{}", b))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!(
+                "book {} has been lent before and is kept for the loan history",
+                b
+            )
+        );
+        assert!(SqliteRepository::<Book>::new(conn_pool)
+            .get_one(b)
+            .await
+            .is_ok());
     }
 }
@@ -1,69 +1,44 @@
-use super::book::Book;
+use super::super::resources::filter::Filters;
+use super::super::resources::resources_db::bind_filters;
 use super::books_queries;
-use super::filter::Filters;
-use actix_web::web;
-use sqlx::{sqlite::SqliteQueryResult, Error, Pool, Sqlite};
+use super::facet::{Facet, FacetCount};
+use sqlx::{Error, Pool, Sqlite};
 
-pub async fn get_books(
-    filter: web::Query<Filters>,
-    pool: web::Data<Pool<Sqlite>>,
-) -> Result<Vec<Book>, Error> {
-    let query = books_queries::get_books_query(filter);
-
-    let r = sqlx::query_as::<_, Book>(&query)
-        .fetch_all(pool.get_ref())
-        .await;
-
-    return r;
-}
-
-pub async fn get_book(pool: web::Data<Pool<Sqlite>>, book_id: i64) -> Result<Book, Error> {
-    let query = books_queries::get_book_query();
-    let r = sqlx::query_as::<_, Book>(&query)
-        .bind(book_id)
-        .fetch_one(pool.get_ref())
-        .await;
-
-    return r;
+pub enum Delete {
+    Done,
+    // The book has been lent before and is kept for the loan history.
+    Lent,
 }
 
-pub async fn create_book(
-    pool: web::Data<Pool<Sqlite>>,
-    book: Book,
-) -> Result<SqliteQueryResult, Error> {
-    let query = books_queries::create_book_query();
-    let r = sqlx::query(&query)
-        .bind(book.title)
-        .bind(book.author)
-        .execute(pool.get_ref())
-        .await;
-
-    return r;
+pub async fn get_facet_counts(
+    pool: &Pool<Sqlite>,
+    facet: Facet,
+    filter: &Filters,
+    limit: u32,
+) -> Result<Vec<FacetCount>, Error> {
+    let query = books_queries::get_facet_counts_query(facet, filter);
+
+    bind_filters(sqlx::query_as::<_, FacetCount>(&query), filter)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
 }
 
-pub async fn update_book(
-    book: Book,
-    pool: web::Data<Pool<Sqlite>>,
-    book_id: i64,
-) -> Result<SqliteQueryResult, Error> {
-    let query = books_queries::update_book_query(book);
-    let r = sqlx::query(&query)
-        .bind(book_id)
-        .execute(pool.get_ref())
-        .await;
-
-    return r;
-}
-
-pub async fn delete_book(
-    pool: web::Data<Pool<Sqlite>>,
-    book_id: i64,
-) -> Result<SqliteQueryResult, Error> {
-    let query = books_queries::delete_book_query();
-    let r = sqlx::query(&query)
-        .bind(book_id)
-        .execute(pool.get_ref())
-        .await;
-
-    return r;
+pub async fn delete_book(pool: &Pool<Sqlite>, id: i64) -> Result<Delete, Error> {
+    let mut tx = pool.begin().await?;
+    let loans: i64 = sqlx::query_scalar(&books_queries::count_loans_query())
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+    if loans > 0 {
+        return Ok(Delete::Lent);
+    }
+
+    sqlx::query(&books_queries::delete_book_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Delete::Done)
 }
@@ -1,66 +1,85 @@
-use super::super::constants::BOOKS_TABLE;
-use super::book::Book;
-use super::filter::Filters;
-use actix_web::web;
+use super::super::constants::{BOOKS_TABLE, BOOK_TAGS_TABLE, LOANS_TABLE, TAGS_TABLE};
+use super::super::resources::filter::{Clause, Filters};
+use super::super::resources::resources_queries;
+use super::super::resources::value::Value;
+use super::facet::Facet;
 
-pub fn get_books_query(filter: web::Query<Filters>) -> String {
-    let mut query = format!("Select * From {}", BOOKS_TABLE);
-    if !!!filter.author.is_none() {
-        query = format!(
-            "{} where author='{}'",
-            query,
-            filter.author.as_ref().unwrap()
-        );
-    }
-    if !!!filter.limit.is_none() {
-        query = format!("{} limit {}", query, filter.limit.unwrap());
-    }
-    return query;
-}
-
-pub fn get_book_query() -> String {
-    return format!("Select * From {} where id=?", BOOKS_TABLE);
-}
+// Counts the books matching `filter` for each value of `facet`, most
+// frequent first. The limit is bound after the filter's values.
+pub fn get_facet_counts_query(facet: Facet, filter: &Filters) -> String {
+    let mut conditions = resources_queries::conditions(filter);
+    let (value, from) = match facet {
+        Facet::Author => ("author", BOOKS_TABLE.to_string()),
+        Facet::Language => ("language", BOOKS_TABLE.to_string()),
+        Facet::Decade => (
+            "substr(publication_date, 1, 3) || '0s'",
+            BOOKS_TABLE.to_string(),
+        ),
+        Facet::Tag => {
+            let books = if conditions.is_empty() {
+                format!("Select id From {}", BOOKS_TABLE)
+            } else {
+                format!(
+                    "Select id From {} where {}",
+                    BOOKS_TABLE,
+                    conditions.join(" and ")
+                )
+            };
+            conditions = vec![format!("bt.book_id IN ({})", books)];
+            (
+                "t.name",
+                format!(
+                    "{} bt JOIN {} t ON t.id = bt.tag_id",
+                    BOOK_TAGS_TABLE, TAGS_TABLE
+                ),
+            )
+        }
+    };
+    conditions.push(format!("{} IS NOT NULL", value));
 
-pub fn create_book_query() -> String {
-    return format!(
-        "INSERT INTO {} (title, author) values (?, ?) RETURNING id",
-        BOOKS_TABLE
-    );
+    format!(
+        "Select {} AS value, COUNT(*) AS count From {} where {} \
+         group by value order by count desc, value limit ?",
+        value,
+        from,
+        conditions.join(" and ")
+    )
 }
 
-fn get_update_str(book: Book) -> String {
-    let mut update_str = String::from("");
-
-    if !!!book.title.is_none() {
-        update_str.push_str("title='");
-        update_str.push_str(book.title.unwrap().as_str());
-        update_str.push_str("'");
+// Restricts `books` to `ids`, passed as one JSON array so that there is no
+// limit on how many.
+pub fn books_with_ids_clause(ids: &[i64]) -> Clause {
+    Clause {
+        sql: "id IN (SELECT value FROM json_each(?))".to_string(),
+        binds: vec![Value::Text(serde_json::to_string(ids).unwrap())],
     }
-    if !!!book.author.is_none() {
-        if !update_str.is_empty() {
-            update_str.push_str(", ");
-        }
-        update_str.push_str("author='");
-        update_str.push_str(book.author.unwrap().as_str());
-        update_str.push_str("'");
-    }
-    update_str
 }
 
-pub fn update_book_query(book: Book) -> String {
-    let fields = get_update_str(book);
-    if fields.is_empty() {
-        return fields;
-    }
-    return format!(
-        "{}{}{}",
-        format!("UPDATE {} SET ", BOOKS_TABLE),
-        fields,
-        " where id=?"
-    );
+pub fn count_loans_query() -> String {
+    format!("Select count(*) From {} where book_id=?", LOANS_TABLE)
 }
 
 pub fn delete_book_query() -> String {
-    return format!("DELETE From {} where id=?", BOOKS_TABLE);
+    format!("DELETE From {} where id=?", BOOKS_TABLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_facet_counts_query() {
+        let filter = Filters::default().by("language", "en");
+        assert_eq!(
+            get_facet_counts_query(Facet::Author, &filter),
+            "Select author AS value, COUNT(*) AS count From books where language=? \
+             and author IS NOT NULL group by value order by count desc, value limit ?"
+        );
+        assert_eq!(
+            get_facet_counts_query(Facet::Tag, &filter),
+            "Select t.name AS value, COUNT(*) AS count From book_tags bt JOIN tags t \
+             ON t.id = bt.tag_id where bt.book_id IN (Select id From books where language=?) \
+             and t.name IS NOT NULL group by value order by count desc, value limit ?"
+        );
+    }
 }
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+use super::super::resources::filter::param;
+
+pub const DEFAULT_FACET_LIMIT: u32 = 20;
+pub const MAX_FACET_LIMIT: u32 = 100;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Facet {
+    Author,
+    Tag,
+    Language,
+    Decade,
+}
+
+impl Facet {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Facet::Author => "author",
+            Facet::Tag => "tag",
+            Facet::Language => "language",
+            Facet::Decade => "decade",
+        }
+    }
+
+    pub fn parse(facet: &str) -> Result<Facet, String> {
+        match facet {
+            "author" => Ok(Facet::Author),
+            "tag" => Ok(Facet::Tag),
+            "language" => Ok(Facet::Language),
+            "decade" => Ok(Facet::Decade),
+            _ => Err(format!(
+                "facets must be one of author, tag, language, decade (got {})",
+                facet
+            )),
+        }
+    }
+
+    // The query parameters that filter on the facet. Its counts leave them
+    // out, so that the other values stay visible once one is picked.
+    pub fn params(&self) -> &'static [&'static str] {
+        match self {
+            Facet::Author => &["author"],
+            Facet::Tag => &["tags", "tag_mode"],
+            Facet::Language => &["language"],
+            Facet::Decade => &["published_after", "published_before"],
+        }
+    }
+
+    // `params` without those filtering on the facet, nor those that only
+    // page or sort the books.
+    pub fn other_params(&self, params: &[(String, String)]) -> Vec<(String, String)> {
+        params
+            .iter()
+            .filter(|(k, _)| {
+                !self.params().contains(&k.as_str())
+                    && !["sort", "limit", "offset"].contains(&k.as_str())
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// Parses `facets=author,tag`, in the order given and without repeats.
+pub fn parse_facets(facets: &str) -> Result<Vec<Facet>, String> {
+    let mut parsed: Vec<Facet> = Vec::new();
+    for facet in facets.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let facet = Facet::parse(facet)?;
+        if !parsed.contains(&facet) {
+            parsed.push(facet);
+        }
+    }
+    Ok(parsed)
+}
+
+// The `facets` and `facet_limit` parameters, shared by the book list and
+// search.
+pub fn parse_params(params: &[(String, String)]) -> Result<(Vec<Facet>, u32), String> {
+    let facets = match param(params, "facets") {
+        Some(f) => parse_facets(f)?,
+        None => Vec::new(),
+    };
+    let limit = match param(params, "facet_limit") {
+        None => DEFAULT_FACET_LIMIT,
+        Some(l) => match l.parse::<u32>() {
+            Ok(l) if (1..=MAX_FACET_LIMIT).contains(&l) => l,
+            _ => {
+                return Err(format!(
+                    "facet_limit must be between 1 and {} (got {})",
+                    MAX_FACET_LIMIT, l
+                ))
+            }
+        },
+    };
+    Ok((facets, limit))
+}
+
+// How many books have `value`; decades are given as `1990s`.
+#[derive(Serialize, Debug, FromRow, Clone, PartialEq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_facets() {
+        assert_eq!(
+            parse_facets("tag, author,tag,").unwrap(),
+            vec![Facet::Tag, Facet::Author]
+        );
+        assert!(parse_facets("author,publisher").is_err());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = |p: &[(&str, &str)]| -> Vec<(String, String)> {
+            p.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(parse_params(&[]).unwrap(), (vec![], DEFAULT_FACET_LIMIT));
+        assert_eq!(
+            parse_params(&params(&[("facets", "decade"), ("facet_limit", "5")])).unwrap(),
+            (vec![Facet::Decade], 5)
+        );
+        assert_eq!(
+            parse_params(&params(&[("facet_limit", "101")])),
+            Err("facet_limit must be between 1 and 100 (got 101)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_other_params() {
+        let params: Vec<(String, String)> = [
+            ("language", "en"),
+            ("tags", "sf"),
+            ("author", "Le Guin"),
+            ("limit", "5"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(
+            Facet::Tag.other_params(&params),
+            vec![
+                ("language".to_string(), "en".to_string()),
+                ("author".to_string(), "Le Guin".to_string())
+            ]
+        );
+    }
+}
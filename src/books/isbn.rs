@@ -0,0 +1,108 @@
+// ISBNs are accepted in either form, with or without hyphens and spaces, and
+// stored as plain ISBN-13.
+
+fn digits(isbn: &str) -> String {
+    isbn.chars()
+        .filter(|c| *c != '-' && *c != ' ')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn isbn10_check_digit(first9: &str) -> char {
+    let sum: u32 = first9
+        .chars()
+        .zip((2..=10).rev())
+        .map(|(c, w)| c.to_digit(10).unwrap() * w)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        d => char::from_digit(d, 10).unwrap(),
+    }
+}
+
+fn isbn13_check_digit(first12: &str) -> char {
+    let sum: u32 = first12
+        .chars()
+        .enumerate()
+        .map(|(i, c)| c.to_digit(10).unwrap() * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+// `d` is checked to be ASCII before it is sliced, as other characters would
+// not fall on byte boundaries.
+fn is_isbn10(d: &str) -> bool {
+    d.len() == 10
+        && d.is_ascii()
+        && d[..9].chars().all(|c| c.is_ascii_digit())
+        && d.ends_with(isbn10_check_digit(&d[..9]))
+}
+
+fn is_isbn13(d: &str) -> bool {
+    d.len() == 13
+        && d.chars().all(|c| c.is_ascii_digit())
+        && (d.starts_with("978") || d.starts_with("979"))
+        && d.ends_with(isbn13_check_digit(&d[..12]))
+}
+
+pub fn to_isbn13(isbn: &str) -> Result<String, String> {
+    let d = digits(isbn);
+    if is_isbn13(&d) {
+        return Ok(d);
+    }
+    if is_isbn10(&d) {
+        let first12 = format!("978{}", &d[..9]);
+        let check = isbn13_check_digit(&first12);
+        return Ok(format!("{}{}", first12, check));
+    }
+    Err(format!("{} is not a valid ISBN-10 or ISBN-13", isbn))
+}
+
+// Only 978-prefixed ISBN-13s have an ISBN-10 equivalent.
+pub fn to_isbn10(isbn: &str) -> Result<Option<String>, String> {
+    let d = to_isbn13(isbn)?;
+    if !d.starts_with("978") {
+        return Ok(None);
+    }
+    let first9 = &d[3..12];
+    Ok(Some(format!("{}{}", first9, isbn10_check_digit(first9))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_isbn13() {
+        assert_eq!(to_isbn13("0-306-40615-2").unwrap(), "9780306406157");
+        assert_eq!(to_isbn13("978-0-306-40615-7").unwrap(), "9780306406157");
+        assert_eq!(to_isbn13("9780306406157").unwrap(), "9780306406157");
+        assert_eq!(to_isbn13("0-8044-2957-x").unwrap(), "9780804429573");
+        assert_eq!(to_isbn13("979-10-90636-07-1").unwrap(), "9791090636071");
+    }
+
+    #[test]
+    fn test_to_isbn13_rejects_bad_checksums() {
+        assert!(to_isbn13("0-306-40615-3").is_err());
+        assert!(to_isbn13("978-0-306-40615-8").is_err());
+        assert!(to_isbn13("123-0-306-40615-7").is_err());
+        assert!(to_isbn13("03064061").is_err());
+        assert!(to_isbn13("").is_err());
+        assert!(to_isbn13("12345678é").is_err());
+        assert!(to_isbn10("97803064061é").is_err());
+    }
+
+    #[test]
+    fn test_to_isbn10() {
+        assert_eq!(
+            to_isbn10("978-0-306-40615-7").unwrap(),
+            Some("0306406152".to_string())
+        );
+        assert_eq!(
+            to_isbn10("9780804429573").unwrap(),
+            Some("080442957X".to_string())
+        );
+        assert_eq!(to_isbn10("979-10-90636-07-1").unwrap(), None);
+        assert!(to_isbn10("9780306406158").is_err());
+    }
+}
@@ -1,5 +1,8 @@
-mod book;
+pub mod book;
+#[allow(clippy::module_inception)]
 pub mod books;
-mod books_db;
-mod books_queries;
-mod filter;
+pub mod books_db;
+pub mod books_queries;
+pub mod facet;
+pub mod isbn;
+pub mod publication_date;
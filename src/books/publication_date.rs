@@ -0,0 +1,43 @@
+use super::super::dates;
+
+// Publication dates are often only known to the year or the month, so any of
+// the forms `dates::parse` reads is accepted.
+pub fn validate(field: &str, date: &str) -> Result<(), String> {
+    let err = || {
+        format!(
+            "{} must be a year, year-month or date like 1990, 1990-05 or 1990-05-17 (got {})",
+            field, date
+        )
+    };
+    dates::parse(date).map(|_| ()).ok_or_else(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        for date in ["1990", "1990-05", "1990-05-17", "2000-02-29"] {
+            assert!(validate("date", date).is_ok(), "{}", date);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_dates() {
+        for date in [
+            "",
+            "90",
+            "1990-5",
+            "1990-13",
+            "1990-00",
+            "1990-04-31",
+            "1900-02-29",
+            "1990-05-17-01",
+            "199O",
+            "1990/05",
+        ] {
+            assert!(validate("date", date).is_err(), "{}", date);
+        }
+    }
+}
@@ -3,3 +3,62 @@ pub const POOL_MAX_CONNECTIONS: u32 = 5;
 pub const DATABASE_URL: &str = "sqlite://db.sqlite";
 pub const BOOKS_TABLE: &str = "books";
 pub const AUTHORS_TABLE: &str = "authors";
+pub const PUBLISHERS_TABLE: &str = "publishers";
+pub const TAGS_TABLE: &str = "tags";
+pub const BOOK_TAGS_TABLE: &str = "book_tags";
+pub const BOOK_CONTRIBUTORS_TABLE: &str = "book_contributors";
+pub const SERIES_TABLE: &str = "series";
+pub const SERIES_BOOKS_TABLE: &str = "series_books";
+pub const SUBJECTS_TABLE: &str = "subjects";
+pub const BOOK_SUBJECTS_TABLE: &str = "book_subjects";
+pub const AUTHOR_ALIASES_TABLE: &str = "author_aliases";
+pub const AUTHOR_REDIRECTS_TABLE: &str = "author_redirects";
+pub const AUTOCOMPLETE_CHANGES_TABLE: &str = "autocomplete_changes";
+pub const SEARCH_CHANGES_TABLE: &str = "search_changes";
+pub const SEARCH_DOCUMENTS_TABLE: &str = "search_documents";
+pub const SIMILAR_CHANGES_TABLE: &str = "similar_changes";
+pub const SIMILAR_BOOKS_TABLE: &str = "similar_books";
+pub const METADATA_FIELDS_TABLE: &str = "metadata_fields";
+pub const REVIEWS_TABLE: &str = "reviews";
+pub const MEMBERS_TABLE: &str = "members";
+pub const LOANS_TABLE: &str = "loans";
+pub const COPIES_TABLE: &str = "copies";
+pub const HOLDS_TABLE: &str = "holds";
+pub const FINE_POLICIES_TABLE: &str = "fine_policies";
+pub const HOLIDAYS_TABLE: &str = "holidays";
+pub const FINES_TABLE: &str = "fines";
+pub const FINE_PAYMENTS_TABLE: &str = "fine_payments";
+pub const DEFAULT_MEMBER_TYPE: &str = "standard";
+pub const STORAGE: &str = "storage";
+pub const STORAGE_SQLITE: &str = "sqlite";
+pub const STORAGE_MEMORY: &str = "memory";
+pub const LOAN_DAYS: &str = "loan_days";
+pub const MAX_RENEWALS: &str = "max_renewals";
+pub const HOLD_DAYS: &str = "hold_days";
+pub const DEFAULT_LOAN_DAYS: i64 = 14;
+pub const DEFAULT_MAX_RENEWALS: i64 = 2;
+pub const DEFAULT_HOLD_DAYS: i64 = 3;
+pub const SEARCH: &str = "search";
+pub const SEARCH_SQLITE: &str = "sqlite";
+pub const SEARCH_TANTIVY: &str = "tantivy";
+// Kept next to `db.sqlite`.
+pub const SEARCH_INDEX_DIR: &str = "search_index";
+pub const TITLE_BOOST: &str = "title_boost";
+pub const NAMES_BOOST: &str = "names_boost";
+pub const BODY_BOOST: &str = "body_boost";
+pub const DEFAULT_TITLE_BOOST: f32 = 3.0;
+pub const DEFAULT_NAMES_BOOST: f32 = 2.0;
+pub const DEFAULT_BODY_BOOST: f32 = 1.0;
+pub const CONTRIBUTOR_WEIGHT: &str = "contributor_weight";
+pub const TAG_WEIGHT: &str = "tag_weight";
+pub const SUBJECT_WEIGHT: &str = "subject_weight";
+pub const SERIES_WEIGHT: &str = "series_weight";
+pub const TEXT_WEIGHT: &str = "text_weight";
+pub const DEFAULT_CONTRIBUTOR_WEIGHT: f32 = 3.0;
+pub const DEFAULT_TAG_WEIGHT: f32 = 1.0;
+pub const DEFAULT_SUBJECT_WEIGHT: f32 = 1.5;
+pub const DEFAULT_SERIES_WEIGHT: f32 = 4.0;
+pub const DEFAULT_TEXT_WEIGHT: f32 = 2.0;
+pub const STATS_TTL: &str = "stats_ttl";
+// Seconds a statistics report is served from the cache.
+pub const DEFAULT_STATS_TTL: i64 = 300;
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Role {
+    Author,
+    Editor,
+    Translator,
+    Illustrator,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Author => "author",
+            Role::Editor => "editor",
+            Role::Translator => "translator",
+            Role::Illustrator => "illustrator",
+        }
+    }
+
+    pub fn parse(role: &str) -> Result<Role, String> {
+        match role {
+            "author" => Ok(Role::Author),
+            "editor" => Ok(Role::Editor),
+            "translator" => Ok(Role::Translator),
+            "illustrator" => Ok(Role::Illustrator),
+            _ => Err(format!(
+                "role must be one of author, editor, translator, illustrator (got {})",
+                role
+            )),
+        }
+    }
+}
+
+// One author's part in a book. `position` orders the contributors of a
+// book, starting at 1. `name` and `position` are ignored when contributors
+// are set, the order of the list decides the position.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Contributor {
+    pub author_id: i64,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub name: Option<String>,
+    pub role: Role,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub position: i64,
+}
+
+// A book as listed among an author's works.
+#[derive(Serialize, Debug, FromRow, Clone)]
+pub struct Work {
+    pub book_id: i64,
+    pub title: Option<String>,
+    pub role: Role,
+    pub position: i64,
+}
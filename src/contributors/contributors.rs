@@ -0,0 +1,294 @@
+use actix_web::{get, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
+
+use super::super::responses::CustomError;
+use super::contributor::{Contributor, Role, Work};
+use super::contributors_db;
+
+pub fn config_contributors(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_book_contributors)
+        .service(set_book_contributors)
+        .service(get_author_works);
+}
+
+#[derive(Deserialize)]
+pub struct WorksParams {
+    pub role: Option<String>,
+}
+
+#[get("/books/{id}/contributors")]
+async fn get_book_contributors(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let r = contributors_db::get_book_contributors(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The body lists `{author_id, role}` pairs in the order they should appear.
+#[put("/books/{id}/contributors")]
+async fn set_book_contributors(
+    id: web::Path<i64>,
+    json: web::Json<Vec<Contributor>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let contributors = json.into_inner();
+    for (i, c) in contributors.iter().enumerate() {
+        if contributors[..i]
+            .iter()
+            .any(|o| o.author_id == c.author_id && o.role == c.role)
+        {
+            return HttpResponse::BadRequest().json(CustomError::message(format!(
+                "author {} is listed twice as {}",
+                c.author_id,
+                c.role.as_str()
+            )));
+        }
+    }
+    let r = contributors_db::set_book_contributors(pool.get_ref(), id.into_inner(), &contributors)
+        .await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Works are grouped by role, e.g. `{"author": [...], "translator": [...]}`.
+#[get("/authors/{id}/works")]
+async fn get_author_works(
+    id: web::Path<i64>,
+    params: web::Query<WorksParams>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let role = match params.role.as_deref().map(Role::parse).transpose() {
+        Ok(r) => r,
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    let r = contributors_db::get_author_works(pool.get_ref(), id.into_inner(), role).await;
+
+    match r {
+        Ok(works) => {
+            let mut by_role: BTreeMap<&str, Vec<Work>> = BTreeMap::new();
+            for w in works {
+                by_role.entry(w.role.as_str()).or_default().push(w);
+            }
+            HttpResponse::Ok().json(by_role)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, author, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_contributors),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! set_contributors {
+        ($app:expr, $book_id:expr, $body:expr) => {{
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}/contributors", $book_id))
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_set_book_contributors() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let tolkien = author()
+            .name("Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let lee = author().name("Lee").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = set_contributors!(
+            app,
+            b,
+            json!([
+                {"author_id": tolkien, "role": "author"},
+                {"author_id": lee, "role": "illustrator"},
+                {"author_id": tolkien, "role": "illustrator"}
+            ])
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body[1],
+            json!({"author_id": lee, "name": "Lee", "role": "illustrator", "position": 2})
+        );
+
+        let book = get_json!(app, format!("/books/{}", b));
+        assert_eq!(book["contributors"], body);
+
+        let resp = set_contributors!(app, b, json!([{"author_id": lee, "role": "editor"}]));
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, get_json!(app, format!("/books/{}/contributors", b)));
+        assert_eq!(body.as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_set_book_contributors_bad_request() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let a = author().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = set_contributors!(
+            app,
+            b,
+            json!([{"author_id": a, "role": "author"}, {"author_id": a, "role": "author"}])
+        );
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("author {} is listed twice as author", a)
+        );
+
+        let resp = set_contributors!(app, b, json!([{"author_id": a, "role": "narrator"}]));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let resp = set_contributors!(app, b, json!([{"author_id": 0, "role": "author"}]));
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(get_json!(app, format!("/books/{}/contributors", b))
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_get_author_works() {
+        let conn_pool = test_pool().await;
+        let hobbit = book()
+            .title("The Hobbit")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let beowulf = book().title("Beowulf").create(&conn_pool).await.id.unwrap();
+        let tolkien = author()
+            .name("Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let app = app!(conn_pool);
+
+        set_contributors!(
+            app,
+            hobbit,
+            json!([{"author_id": tolkien, "role": "author"}])
+        );
+        set_contributors!(
+            app,
+            beowulf,
+            json!([{"author_id": tolkien, "role": "translator"}])
+        );
+
+        let works = get_json!(app, format!("/authors/{}/works", tolkien));
+        assert_eq!(works["author"][0]["title"], "The Hobbit");
+        assert_eq!(works["translator"][0]["book_id"], beowulf);
+
+        let works = get_json!(app, format!("/authors/{}/works?role=translator", tolkien));
+        assert!(works.get("author").is_none());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/authors/{}/works?role=narrator", tolkien))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_by_contributor() {
+        let conn_pool = test_pool().await;
+        let hobbit = book()
+            .title("The Hobbit")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let beowulf = book().title("Beowulf").create(&conn_pool).await.id.unwrap();
+        book().title("Dune").create(&conn_pool).await;
+        let tolkien = author().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        set_contributors!(
+            app,
+            hobbit,
+            json!([{"author_id": tolkien, "role": "author"}])
+        );
+        set_contributors!(
+            app,
+            beowulf,
+            json!([{"author_id": tolkien, "role": "translator"}])
+        );
+
+        let titles = |body: Value| -> Vec<String> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let books = get_json!(app, format!("/books?contributor_id={}", tolkien));
+        assert_eq!(titles(books), vec!["The Hobbit", "Beowulf"]);
+        let books = get_json!(
+            app,
+            format!("/books?contributor_id={}&role=translator", tolkien)
+        );
+        assert_eq!(titles(books), vec!["Beowulf"]);
+
+        let req = test::TestRequest::get()
+            .uri("/books?role=author")
+            .to_request();
+        let books: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(titles(books), vec!["The Hobbit"]);
+
+        let req = test::TestRequest::get()
+            .uri("/books?contributor_id=x")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,74 @@
+use super::contributor::{Contributor, Role, Work};
+use super::contributors_queries;
+use sqlx::{Error, Pool, Sqlite};
+
+pub async fn get_book_contributors(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+) -> Result<Vec<Contributor>, Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&contributors_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+    sqlx::query_as::<_, Contributor>(&contributors_queries::get_book_contributors_query())
+        .bind(book_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+// Replaces all contributors of a book in one transaction; their order in
+// `contributors` becomes their position.
+pub async fn set_book_contributors(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    contributors: &[Contributor],
+) -> Result<Vec<Contributor>, Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&contributors_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query(&contributors_queries::delete_book_contributors_query())
+        .bind(book_id)
+        .execute(&mut tx)
+        .await?;
+    for (position, c) in contributors.iter().enumerate() {
+        sqlx::query(&contributors_queries::get_author_id_query())
+            .bind(c.author_id)
+            .fetch_one(&mut tx)
+            .await?;
+        sqlx::query(&contributors_queries::create_book_contributor_query())
+            .bind(book_id)
+            .bind(c.author_id)
+            .bind(c.role)
+            .bind(position as i64 + 1)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    get_book_contributors(pool, book_id).await
+}
+
+pub async fn get_author_works(
+    pool: &Pool<Sqlite>,
+    author_id: i64,
+    role: Option<Role>,
+) -> Result<Vec<Work>, Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&contributors_queries::get_author_id_query())
+        .bind(author_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+    let query = contributors_queries::get_author_works_query(role.is_some());
+    let mut q = sqlx::query_as::<_, Work>(&query).bind(author_id);
+    if let Some(role) = role {
+        q = q.bind(role);
+    }
+    q.fetch_all(&mut tx).await
+}
@@ -0,0 +1,61 @@
+use super::super::constants::{AUTHORS_TABLE, BOOKS_TABLE, BOOK_CONTRIBUTORS_TABLE};
+use super::super::resources::filter::Clause;
+use super::super::resources::value::Value;
+
+pub fn get_book_contributors_query() -> String {
+    format!(
+        "Select bc.author_id, a.name, bc.role, bc.position From {} bc \
+         JOIN {} a ON a.id = bc.author_id where bc.book_id=? order by bc.position",
+        BOOK_CONTRIBUTORS_TABLE, AUTHORS_TABLE
+    )
+}
+
+pub fn get_author_works_query(role: bool) -> String {
+    format!(
+        "Select bc.book_id, b.title, bc.role, bc.position From {} bc \
+         JOIN {} b ON b.id = bc.book_id where bc.author_id=?{} order by bc.role, b.title, b.id",
+        BOOK_CONTRIBUTORS_TABLE,
+        BOOKS_TABLE,
+        if role { " and bc.role=?" } else { "" }
+    )
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn get_author_id_query() -> String {
+    format!("Select id From {} where id=?", AUTHORS_TABLE)
+}
+
+pub fn delete_book_contributors_query() -> String {
+    format!("DELETE From {} where book_id=?", BOOK_CONTRIBUTORS_TABLE)
+}
+
+pub fn create_book_contributor_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, author_id, role, position) values (?, ?, ?, ?)",
+        BOOK_CONTRIBUTORS_TABLE
+    )
+}
+
+// Books an author contributed to, in any role unless one is given; or books
+// with any contributor in the given role.
+pub fn books_with_contributor_clause(author_id: Option<i64>, role: Option<&str>) -> Clause {
+    let mut sql = format!(
+        "id IN (SELECT book_id FROM {} WHERE 1",
+        BOOK_CONTRIBUTORS_TABLE
+    );
+    let mut binds = Vec::new();
+    if let Some(id) = author_id {
+        sql.push_str(" AND author_id = ?");
+        binds.push(Value::Integer(id));
+    }
+    if let Some(role) = role {
+        sql.push_str(" AND role = ?");
+        binds.push(Value::Text(role.to_string()));
+    }
+    sql.push(')');
+
+    Clause { sql, binds }
+}
@@ -0,0 +1,5 @@
+pub mod contributor;
+#[allow(clippy::module_inception)]
+pub mod contributors;
+mod contributors_db;
+pub mod contributors_queries;
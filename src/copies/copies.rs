@@ -0,0 +1,429 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::loans::loan::LoanPolicy;
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::copies_db::{self, Change};
+use super::copy::BookCopy;
+
+pub fn config_copies(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_copies)
+        .service(get_copy)
+        .service(create_copy)
+        .service(update_copy)
+        .service(delete_copy);
+}
+
+fn conflict(book_id: i64, r: Change) -> HttpResponse {
+    match r {
+        Change::Duplicate(id) => HttpResponse::Conflict().json(ConflictResponse {
+            message: "a copy with this barcode already exists".to_string(),
+            id,
+            location: format!("/books/{}/copies/{}", book_id, id),
+        }),
+        Change::Refused(message) => HttpResponse::Conflict().json(CustomError::message(message)),
+        Change::Done(id) => HttpResponse::Ok().json(CreateResponse { id }),
+    }
+}
+
+#[get("/books/{book_id}/copies")]
+async fn get_copies(book_id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = copies_db::get_copies(pool.get_ref(), book_id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/books/{book_id}/copies/{id}")]
+async fn get_copy(path: web::Path<(i64, i64)>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let r = copies_db::get_copy(pool.get_ref(), book_id, id).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[post("/books/{book_id}/copies")]
+async fn create_copy(
+    book_id: web::Path<i64>,
+    json: web::Json<BookCopy>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let book_id = book_id.into_inner();
+    let mut copy = json.into_inner();
+    copy.normalize();
+    if let Err(e) = copy.validate(true) {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = copies_db::create_copy(pool.get_ref(), book_id, copy, policy.get_ref()).await;
+
+    match r {
+        Ok(Change::Done(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(c) => conflict(book_id, c),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Only the status transitions allowed by `Status::can_be_set_to` are accepted.
+#[put("/books/{book_id}/copies/{id}")]
+async fn update_copy(
+    path: web::Path<(i64, i64)>,
+    json: web::Json<BookCopy>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let mut copy = json.into_inner();
+    copy.normalize();
+    if let Err(e) = copy.validate(false) {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = copies_db::update_copy(pool.get_ref(), book_id, id, copy, policy.get_ref()).await;
+
+    match r {
+        Ok(Change::Done(_)) => HttpResponse::Ok().json("Updated"),
+        Ok(c) => conflict(book_id, c),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[delete("/books/{book_id}/copies/{id}")]
+async fn delete_copy(path: web::Path<(i64, i64)>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let r = copies_db::delete_copy(pool.get_ref(), book_id, id).await;
+
+    match r {
+        Ok(Change::Done(_)) => HttpResponse::Ok().json("Deleted"),
+        Ok(c) => conflict(book_id, c),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::loans::loan::LoanPolicy;
+    use crate::test_utils::{self, book, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test, web,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(LoanPolicy {
+                        loan_days: 14,
+                        max_renewals: 1,
+                        hold_days: 3,
+                    }))
+                    .configure(books::books::config_books)
+                    .configure(loans::loans::config_loans)
+                    .configure(super::config_copies),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $req:expr, $uri:expr, $body:expr) => {{
+            let req = $req.uri(&$uri).set_json($body).to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    macro_rules! add_copy {
+        ($app:expr, $book_id:expr, $barcode:expr) => {{
+            let resp = send!(
+                $app,
+                test::TestRequest::post(),
+                format!("/books/{}/copies", $book_id),
+                json!({"barcode": $barcode, "branch": "main"})
+            );
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap()
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_create_copies() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let first = add_copy!(app, b, "B-1");
+        add_copy!(app, b, "B-2");
+
+        let copies = get_json!(app, format!("/books/{}/copies", b));
+        assert_eq!(copies.as_array().unwrap().len(), 2);
+        let copy = get_json!(app, format!("/books/{}/copies/{}", b, first));
+        assert_eq!(
+            copy,
+            json!({"id": first, "book_id": b, "barcode": "B-1", "branch": "main", "condition": null, "status": "available"})
+        );
+
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            format!("/books/{}/copies", b),
+            json!({"barcode": " B-1 "})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["location"], format!("/books/{}/copies/{}", b, first));
+    }
+
+    #[actix_web::test]
+    async fn test_create_copy_bad_request() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        for body in [
+            json!({"branch": "main"}),
+            json!({"barcode": "B-1", "status": "on_loan"}),
+            json!({"barcode": "B-1", "status": "borrowed"}),
+        ] {
+            let resp = send!(
+                app,
+                test::TestRequest::post(),
+                format!("/books/{}/copies", b),
+                body
+            );
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_update_copy_status() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let c = add_copy!(app, b, "B-1");
+        let uri = format!("/books/{}/copies/{}", b, c);
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            uri,
+            json!({"status": "in_repair", "condition": "torn cover"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let copy = get_json!(app, uri);
+        assert_eq!(copy["status"], "in_repair");
+        assert_eq!(copy["condition"], "torn cover");
+        assert_eq!(copy["branch"], "main");
+
+        for (status, message) in [
+            ("on_loan", "copies are lent and returned through /loans"),
+            ("lost", ""),
+            ("in_repair", "a copy cannot go from lost to in_repair"),
+        ] {
+            let resp = send!(
+                app,
+                test::TestRequest::put(),
+                uri,
+                json!({ "status": status })
+            );
+            if message.is_empty() {
+                assert_eq!(resp.status(), http::StatusCode::OK);
+            } else {
+                assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+                let body: Value = test::read_body_json(resp).await;
+                assert_eq!(body["message"], message);
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_loans_follow_copies() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let first = add_copy!(app, b, "B-1");
+        let second = add_copy!(app, b, "B-2");
+
+        let book = get_json!(app, format!("/books/{}", b));
+        assert_eq!(book["copy_count"], 2);
+        assert_eq!(book["available_copies"], 2);
+
+        let mut loans = Vec::new();
+        for copy in [first, second] {
+            let resp = send!(
+                app,
+                test::TestRequest::post(),
+                "/loans",
+                json!({"book_id": b, "member_id": m})
+            );
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            let loan = get_json!(app, format!("/loans/{}", body["id"]));
+            assert_eq!(loan["copy_id"], copy);
+            loans.push(body["id"].clone());
+        }
+
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            "/loans",
+            json!({"book_id": b, "member_id": m})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("no copies of book {} are available", b)
+        );
+        let copy = get_json!(app, format!("/books/{}/copies/{}", b, first));
+        assert_eq!(copy["status"], "on_loan");
+
+        send!(
+            app,
+            test::TestRequest::post(),
+            format!("/loans/{}/return", loans[0]),
+            json!({})
+        );
+        let copy = get_json!(app, format!("/books/{}/copies/{}", b, first));
+        assert_eq!(copy["status"], "available");
+        let book = get_json!(app, format!("/books/{}", b));
+        assert_eq!(book["available_copies"], 1);
+
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            "/loans",
+            json!({"copy_id": second, "member_id": m})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], loans[1]);
+
+        let resp = send!(
+            app,
+            test::TestRequest::delete(),
+            format!("/books/{}/copies/{}", b, first),
+            json!({})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_lost_copy_on_loan() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let c = add_copy!(app, b, "B-1");
+        let uri = format!("/books/{}/copies/{}", b, c);
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            "/loans",
+            json!({"copy_id": c, "member_id": m})
+        );
+        let loan: Value = test::read_body_json(resp).await;
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            uri,
+            json!({"status": "lost"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            uri,
+            json!({"status": "available"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!(
+                "copy {} is still on loan {}, return it through /loans",
+                c, loan["id"]
+            )
+        );
+
+        // Returning the loan brings the copy back.
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            format!("/loans/{}/return", loan["id"]),
+            json!({})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let copy = get_json!(app, uri);
+        assert_eq!(copy["status"], "available");
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            "/loans",
+            json!({"copy_id": c, "member_id": m})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_available() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await.id.unwrap();
+        let emma = book().title("Emma").create(&conn_pool).await.id.unwrap();
+        book().title("Rust").create(&conn_pool).await;
+        let app = app!(conn_pool);
+        add_copy!(app, dune, "B-1");
+        let c = add_copy!(app, emma, "B-2");
+        send!(
+            app,
+            test::TestRequest::put(),
+            format!("/books/{}/copies/{}", emma, c),
+            json!({"status": "lost"})
+        );
+
+        let titles = |body: &Value| -> Vec<String> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let req = test::TestRequest::get()
+            .uri("/books?available=true")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(titles(&body), vec!["Dune"]);
+        let req = test::TestRequest::get()
+            .uri("/books?available=false")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(titles(&body), vec!["Emma", "Rust"]);
+
+        let req = test::TestRequest::get()
+            .uri("/books?available=yes")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,207 @@
+use super::super::holds::holds_db;
+use super::super::loans::loan::LoanPolicy;
+use super::copies_queries;
+use super::copy::{BookCopy, Status};
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Change {
+    Done(i64),
+    // Another copy already has this barcode, with the given id.
+    Duplicate(i64),
+    // The change is not allowed, with the reason.
+    Refused(String),
+}
+
+// The copy a new loan of a book goes out on.
+pub enum Lend {
+    Copy(i64),
+    // The book has no copies and is lent as a whole.
+    Book,
+    Unavailable(String),
+}
+
+async fn ensure_book(tx: &mut Transaction<'_, Sqlite>, book_id: i64) -> Result<(), Error> {
+    sqlx::query(&copies_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(tx)
+        .await?;
+
+    Ok(())
+}
+
+async fn barcode_owner(
+    tx: &mut Transaction<'_, Sqlite>,
+    barcode: &Option<String>,
+) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar(&copies_queries::get_copy_by_barcode_query())
+        .bind(barcode)
+        .fetch_optional(tx)
+        .await
+}
+
+pub async fn get_copies(pool: &Pool<Sqlite>, book_id: i64) -> Result<Vec<BookCopy>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_book(&mut tx, book_id).await?;
+
+    sqlx::query_as::<_, BookCopy>(&copies_queries::get_copies_query())
+        .bind(book_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+pub async fn get_copy(pool: &Pool<Sqlite>, book_id: i64, id: i64) -> Result<BookCopy, Error> {
+    sqlx::query_as::<_, BookCopy>(&copies_queries::get_copy_query())
+        .bind(book_id)
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn create_copy(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    copy: BookCopy,
+    policy: &LoanPolicy,
+) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    holds_db::expire_holds(&mut tx, policy).await?;
+    ensure_book(&mut tx, book_id).await?;
+    if let Some(id) = barcode_owner(&mut tx, &copy.barcode).await? {
+        return Ok(Change::Duplicate(id));
+    }
+
+    let r = sqlx::query(&copies_queries::create_copy_query())
+        .bind(book_id)
+        .bind(copy.barcode)
+        .bind(copy.branch)
+        .bind(copy.condition)
+        .bind(copy.status)
+        .execute(&mut tx)
+        .await?;
+    holds_db::assign(&mut tx, book_id, policy).await?;
+    tx.commit().await?;
+
+    Ok(Change::Done(r.last_insert_rowid()))
+}
+
+pub async fn update_copy(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    id: i64,
+    copy: BookCopy,
+    policy: &LoanPolicy,
+) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    holds_db::expire_holds(&mut tx, policy).await?;
+    let current = sqlx::query_as::<_, BookCopy>(&copies_queries::get_copy_query())
+        .bind(book_id)
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+
+    if let (Some(from), Some(to)) = (current.status, copy.status) {
+        if let Err(e) = from.can_be_set_to(to) {
+            return Ok(Change::Refused(e));
+        }
+        // Its next checkout would otherwise run into the open loan.
+        if from == Status::Lost && to == Status::Available {
+            let loan: Option<i64> = sqlx::query_scalar(&copies_queries::get_active_loan_query())
+                .bind(id)
+                .fetch_optional(&mut tx)
+                .await?;
+            if let Some(loan) = loan {
+                return Ok(Change::Refused(format!(
+                    "copy {} is still on loan {}, return it through /loans",
+                    id, loan
+                )));
+            }
+        }
+    }
+    match barcode_owner(&mut tx, &copy.barcode).await? {
+        Some(owner) if owner != id => return Ok(Change::Duplicate(owner)),
+        _ => {}
+    }
+
+    sqlx::query(&copies_queries::update_copy_query())
+        .bind(copy.barcode)
+        .bind(copy.branch)
+        .bind(copy.condition)
+        .bind(copy.status)
+        .bind(book_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    if matches!(copy.status, Some(s) if s != Status::Available) {
+        holds_db::release_copy(&mut tx, id).await?;
+    }
+    holds_db::assign(&mut tx, book_id, policy).await?;
+    tx.commit().await?;
+
+    Ok(Change::Done(id))
+}
+
+// Copies that were ever lent are kept for the loan history.
+pub async fn delete_copy(pool: &Pool<Sqlite>, book_id: i64, id: i64) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    let loans: i64 = sqlx::query_scalar(&copies_queries::count_loans_query())
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+    if loans > 0 {
+        return Ok(Change::Refused(format!(
+            "copy {} has been lent before, mark it lost instead",
+            id
+        )));
+    }
+
+    sqlx::query(&copies_queries::delete_copy_query())
+        .bind(book_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Change::Done(id))
+}
+
+pub async fn get_copy_by_id(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<BookCopy, Error> {
+    sqlx::query_as::<_, BookCopy>(&copies_queries::get_copy_by_id_query())
+        .bind(id)
+        .fetch_one(tx)
+        .await
+}
+
+// Picks the first available copy of a book.
+pub async fn copy_to_lend(tx: &mut Transaction<'_, Sqlite>, book_id: i64) -> Result<Lend, Error> {
+    ensure_book(tx, book_id).await?;
+    let copies: i64 = sqlx::query_scalar(&copies_queries::count_copies_query())
+        .bind(book_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if copies == 0 {
+        return Ok(Lend::Book);
+    }
+
+    let available: Option<i64> = sqlx::query_scalar(&copies_queries::get_available_copy_query())
+        .bind(book_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    Ok(match available {
+        Some(id) => Lend::Copy(id),
+        None => Lend::Unavailable(format!("no copies of book {} are available", book_id)),
+    })
+}
+
+pub async fn set_status(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+    status: Status,
+) -> Result<(), Error> {
+    sqlx::query(&copies_queries::set_status_query())
+        .bind(status)
+        .bind(id)
+        .execute(tx)
+        .await?;
+
+    Ok(())
+}
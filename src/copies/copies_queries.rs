@@ -0,0 +1,73 @@
+use super::super::constants::{BOOKS_TABLE, COPIES_TABLE, HOLDS_TABLE, LOANS_TABLE};
+
+pub fn get_copies_query() -> String {
+    format!("Select * From {} where book_id=? order by id", COPIES_TABLE)
+}
+
+pub fn get_copy_query() -> String {
+    format!("Select * From {} where book_id=? and id=?", COPIES_TABLE)
+}
+
+pub fn get_copy_by_id_query() -> String {
+    format!("Select * From {} where id=?", COPIES_TABLE)
+}
+
+pub fn get_copy_by_barcode_query() -> String {
+    format!("Select id From {} where barcode=?", COPIES_TABLE)
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn count_copies_query() -> String {
+    format!("Select count(*) From {} where book_id=?", COPIES_TABLE)
+}
+
+// Copies set aside for a hold are left for the member who holds them.
+pub fn get_available_copy_query() -> String {
+    format!(
+        "Select id From {copies} where book_id=? and status='available' \
+         and id NOT IN (Select copy_id From {holds} where status='ready' \
+         and copy_id IS NOT NULL) order by id limit 1",
+        copies = COPIES_TABLE,
+        holds = HOLDS_TABLE
+    )
+}
+
+pub fn count_loans_query() -> String {
+    format!("Select count(*) From {} where copy_id=?", LOANS_TABLE)
+}
+
+pub fn get_active_loan_query() -> String {
+    format!(
+        "Select id From {} where copy_id=? and returned_at IS NULL",
+        LOANS_TABLE
+    )
+}
+
+pub fn create_copy_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, barcode, branch, condition, status) \
+         values (?, ?, ?, ?, coalesce(?, 'available')) RETURNING id",
+        COPIES_TABLE
+    )
+}
+
+// Fields that are not sent keep their value.
+pub fn update_copy_query() -> String {
+    format!(
+        "UPDATE {} SET barcode=coalesce(?, barcode), branch=coalesce(?, branch), \
+         condition=coalesce(?, condition), status=coalesce(?, status) \
+         where book_id=? and id=?",
+        COPIES_TABLE
+    )
+}
+
+pub fn set_status_query() -> String {
+    format!("UPDATE {} SET status=? where id=?", COPIES_TABLE)
+}
+
+pub fn delete_copy_query() -> String {
+    format!("DELETE From {} where book_id=? and id=?", COPIES_TABLE)
+}
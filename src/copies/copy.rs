@@ -0,0 +1,112 @@
+use super::super::resources::resource::validate_not_blank;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Status {
+    Available,
+    OnLoan,
+    Lost,
+    InRepair,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Available => "available",
+            Status::OnLoan => "on_loan",
+            Status::Lost => "lost",
+            Status::InRepair => "in_repair",
+        }
+    }
+
+    // A lost copy may turn up again. One still on loan is made available by
+    // returning the loan, see `copies_db::update_copy`.
+    pub fn can_become(&self, next: Status) -> bool {
+        use Status::*;
+        matches!(
+            (self, next),
+            (Available, OnLoan)
+                | (Available, Lost)
+                | (Available, InRepair)
+                | (OnLoan, Available)
+                | (OnLoan, Lost)
+                | (InRepair, Available)
+                | (InRepair, Lost)
+                | (Lost, Available)
+        )
+    }
+
+    // Lending and returning go through `/loans`, which keeps the loan and
+    // the copy in step.
+    pub fn can_be_set_to(&self, next: Status) -> Result<(), String> {
+        if next == Status::OnLoan || (*self == Status::OnLoan && next == Status::Available) {
+            return Err("copies are lent and returned through /loans".to_string());
+        }
+        if *self != next && !self.can_become(next) {
+            return Err(format!(
+                "a copy cannot go from {} to {}",
+                self.as_str(),
+                next.as_str()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct BookCopy {
+    pub id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub book_id: Option<i64>,
+    pub barcode: Option<String>,
+    pub branch: Option<String>,
+    pub condition: Option<String>,
+    pub status: Option<Status>,
+}
+
+impl BookCopy {
+    pub fn normalize(&mut self) {
+        self.barcode = self.barcode.as_deref().map(|b| b.trim().to_string());
+    }
+
+    pub fn validate(&self, creating: bool) -> Result<(), String> {
+        if creating && self.barcode.is_none() {
+            return Err("barcode is required".to_string());
+        }
+        if creating && self.status == Some(Status::OnLoan) {
+            return Err("copies are lent and returned through /loans".to_string());
+        }
+        validate_not_blank("barcode", &self.barcode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Status::*;
+
+    #[test]
+    fn test_transitions() {
+        assert!(Available.can_become(OnLoan));
+        assert!(OnLoan.can_become(Lost));
+        assert!(Lost.can_become(Available));
+        assert!(!Lost.can_become(OnLoan));
+        assert!(!InRepair.can_become(OnLoan));
+        assert!(!OnLoan.can_become(InRepair));
+    }
+
+    #[test]
+    fn test_manual_transitions() {
+        assert!(Available.can_be_set_to(InRepair).is_ok());
+        assert!(Available.can_be_set_to(Available).is_ok());
+        assert!(OnLoan.can_be_set_to(Lost).is_ok());
+        assert!(Available.can_be_set_to(OnLoan).is_err());
+        assert!(OnLoan.can_be_set_to(Available).is_err());
+        assert_eq!(
+            OnLoan.can_be_set_to(InRepair),
+            Err("a copy cannot go from on_loan to in_repair".to_string())
+        );
+    }
+}
@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod copies;
+pub mod copies_db;
+mod copies_queries;
+pub mod copy;
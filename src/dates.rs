@@ -0,0 +1,80 @@
+// Dates are kept as `YYYY-MM-DD` text, or as `YYYY` or `YYYY-MM` when only
+// known that far, which all sort correctly as text.
+
+fn days_in_month(year: u32, month: u32) -> Option<u32> {
+    let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => Some(31),
+        4 | 6 | 9 | 11 => Some(30),
+        2 if leap => Some(29),
+        2 => Some(28),
+        _ => None,
+    }
+}
+
+// The year, then the month and day when given, of a date in one of the forms
+// above. None if it is malformed or does not exist.
+pub fn parse(date: &str) -> Option<Vec<u32>> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let widths = [4, 2, 2];
+    if parts.len() > 3
+        || parts
+            .iter()
+            .zip(widths)
+            .any(|(p, w)| p.len() != w || !p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return None;
+    }
+    let numbers: Vec<u32> = parts.iter().map(|p| p.parse().unwrap()).collect();
+    if let Some(month) = numbers.get(1) {
+        let days = days_in_month(numbers[0], *month)?;
+        if matches!(numbers.get(2), Some(day) if *day == 0 || *day > days) {
+            return None;
+        }
+    }
+    Some(numbers)
+}
+
+// Accepts `YYYY-MM-DD` calendar dates.
+pub fn validate_date(field: &str, value: &Option<String>) -> Result<(), String> {
+    match value {
+        Some(date) if parse(date).is_none_or(|d| d.len() != 3) => Err(format!(
+            "{} must be a date like 2024-12-25 (got {})",
+            field, date
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("1990"), Some(vec![1990]));
+        assert_eq!(parse("1990-05"), Some(vec![1990, 5]));
+        assert_eq!(parse("2000-02-29"), Some(vec![2000, 2, 29]));
+        for date in ["", "90", "1990-5", "1990-13", "1990-04-31", "1990-05-17-01"] {
+            assert_eq!(parse(date), None, "{}", date);
+        }
+    }
+
+    #[test]
+    fn test_validate_date() {
+        for date in ["2024-12-25", "2024-02-29", "2000-02-29"] {
+            assert!(validate_date("date", &Some(date.to_string())).is_ok());
+        }
+        for date in [
+            "2023-02-29",
+            "1900-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "2024-12",
+            "25/12/2024",
+        ] {
+            assert!(validate_date("date", &Some(date.to_string())).is_err());
+        }
+        assert!(validate_date("date", &None).is_ok());
+    }
+}
@@ -14,8 +14,33 @@ pub async fn establish_connection() -> Result<Pool<Sqlite>, Error> {
         .connect_with(connection_options)
         .await?;
 
-    sqlx::query(&format!(
-        "
+    migrate(&sqlite_pool).await?;
+
+    Ok(sqlite_pool)
+}
+
+// A throwaway database that lives as long as the pool. The pool is pinned to
+// a single connection that is never recycled, since closing it would drop
+// the database.
+pub async fn establish_memory_connection() -> Result<Pool<Sqlite>, Error> {
+    let sqlite_pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await?;
+
+    migrate(&sqlite_pool).await?;
+
+    Ok(sqlite_pool)
+}
+
+// Migrations are applied in order and never edited once released; the index
+// of the last applied migration is kept in `PRAGMA user_version`.
+fn migrations() -> Vec<String> {
+    vec![
+        format!(
+            "
     CREATE TABLE IF NOT EXISTS {} (
       id INTEGER PRIMARY KEY AUTOINCREMENT,
       title text,
@@ -26,11 +51,585 @@ pub async fn establish_connection() -> Result<Pool<Sqlite>, Error> {
         name text
       );
     ",
-        constants::BOOKS_TABLE,
-        constants::AUTHORS_TABLE
-    ))
-    .execute(&sqlite_pool.clone())
-    .await?;
+            constants::BOOKS_TABLE,
+            constants::AUTHORS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {publishers} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      name text,
+      country text,
+      website text
+    );
+    ALTER TABLE {books} ADD COLUMN publisher_id INTEGER REFERENCES {publishers}(id);
+    CREATE INDEX IF NOT EXISTS {books}_publisher_id ON {books} (publisher_id);
+    ",
+            publishers = constants::PUBLISHERS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {tags} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      name text NOT NULL COLLATE NOCASE
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {tags}_name ON {tags} (name);
+    CREATE TABLE IF NOT EXISTS {book_tags} (
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      tag_id INTEGER NOT NULL REFERENCES {tags}(id) ON DELETE CASCADE,
+      PRIMARY KEY (book_id, tag_id)
+    );
+    CREATE INDEX IF NOT EXISTS {book_tags}_tag_id ON {book_tags} (tag_id);
+    ",
+            tags = constants::TAGS_TABLE,
+            book_tags = constants::BOOK_TAGS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {reviews} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      reviewer text NOT NULL COLLATE NOCASE,
+      rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+      text text,
+      created_at text NOT NULL,
+      updated_at text NOT NULL
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {reviews}_book_id_reviewer ON {reviews} (book_id, reviewer);
+    ALTER TABLE {books} ADD COLUMN average_rating REAL;
+    ALTER TABLE {books} ADD COLUMN review_count INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX IF NOT EXISTS {books}_average_rating ON {books} (average_rating);
+    ",
+            reviews = constants::REVIEWS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    ALTER TABLE {books} ADD COLUMN isbn text;
+    CREATE UNIQUE INDEX IF NOT EXISTS {books}_isbn ON {books} (isbn);
+    ",
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {members} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      name text,
+      email text COLLATE NOCASE
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {members}_email ON {members} (email);
+    CREATE TABLE IF NOT EXISTS {loans} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      book_id INTEGER NOT NULL REFERENCES {books}(id),
+      member_id INTEGER NOT NULL REFERENCES {members}(id),
+      loaned_at text NOT NULL,
+      due_at text NOT NULL,
+      returned_at text,
+      renewals INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {loans}_active_book_id ON {loans} (book_id) WHERE returned_at IS NULL;
+    CREATE INDEX IF NOT EXISTS {loans}_member_id ON {loans} (member_id);
+    CREATE INDEX IF NOT EXISTS {loans}_due_at ON {loans} (due_at) WHERE returned_at IS NULL;
+    ",
+            members = constants::MEMBERS_TABLE,
+            loans = constants::LOANS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {copies} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      barcode text NOT NULL,
+      branch text,
+      condition text,
+      status text NOT NULL DEFAULT 'available'
+        CHECK (status IN ('available', 'on_loan', 'lost', 'in_repair'))
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {copies}_barcode ON {copies} (barcode);
+    CREATE INDEX IF NOT EXISTS {copies}_book_id_status ON {copies} (book_id, status);
+    ALTER TABLE {loans} ADD COLUMN copy_id INTEGER REFERENCES {copies}(id);
+    DROP INDEX IF EXISTS {loans}_active_book_id;
+    CREATE UNIQUE INDEX IF NOT EXISTS {loans}_active_book_id ON {loans} (book_id)
+      WHERE returned_at IS NULL AND copy_id IS NULL;
+    CREATE UNIQUE INDEX IF NOT EXISTS {loans}_active_copy_id ON {loans} (copy_id)
+      WHERE returned_at IS NULL;
+    ",
+            copies = constants::COPIES_TABLE,
+            loans = constants::LOANS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {holds} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      member_id INTEGER NOT NULL REFERENCES {members}(id),
+      copy_id INTEGER REFERENCES {copies}(id) ON DELETE SET NULL,
+      status text NOT NULL
+        CHECK (status IN ('waiting', 'ready', 'fulfilled', 'cancelled', 'expired')),
+      created_at text NOT NULL,
+      ready_at text,
+      expires_at text
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {holds}_active_book_id_member_id ON {holds} (book_id, member_id)
+      WHERE status IN ('waiting', 'ready');
+    CREATE UNIQUE INDEX IF NOT EXISTS {holds}_ready_copy_id ON {holds} (copy_id)
+      WHERE status = 'ready';
+    CREATE INDEX IF NOT EXISTS {holds}_book_id_status ON {holds} (book_id, status);
+    CREATE INDEX IF NOT EXISTS {holds}_member_id ON {holds} (member_id);
+    CREATE INDEX IF NOT EXISTS {holds}_expires_at ON {holds} (expires_at) WHERE status = 'ready';
+    ",
+            holds = constants::HOLDS_TABLE,
+            books = constants::BOOKS_TABLE,
+            members = constants::MEMBERS_TABLE,
+            copies = constants::COPIES_TABLE
+        ),
+        format!(
+            "
+    ALTER TABLE {members} ADD COLUMN member_type text COLLATE NOCASE;
+    CREATE TABLE IF NOT EXISTS {policies} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      member_type text NOT NULL COLLATE NOCASE,
+      daily_rate_cents INTEGER NOT NULL,
+      grace_days INTEGER,
+      max_fine_cents INTEGER,
+      block_threshold_cents INTEGER
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {policies}_member_type ON {policies} (member_type);
+    INSERT INTO {policies} (member_type, daily_rate_cents, grace_days, max_fine_cents, block_threshold_cents)
+      VALUES ('{default_type}', 25, 0, 1000, 1000);
+    CREATE TABLE IF NOT EXISTS {holidays} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      date text NOT NULL,
+      name text
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {holidays}_date ON {holidays} (date);
+    CREATE TABLE IF NOT EXISTS {fines} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      loan_id INTEGER NOT NULL REFERENCES {loans}(id),
+      member_id INTEGER NOT NULL REFERENCES {members}(id),
+      days_late INTEGER NOT NULL,
+      amount_cents INTEGER NOT NULL,
+      created_at text NOT NULL
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {fines}_loan_id ON {fines} (loan_id);
+    CREATE INDEX IF NOT EXISTS {fines}_member_id ON {fines} (member_id);
+    CREATE TABLE IF NOT EXISTS {payments} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      member_id INTEGER NOT NULL REFERENCES {members}(id),
+      kind text NOT NULL CHECK (kind IN ('payment', 'waiver')),
+      amount_cents INTEGER NOT NULL CHECK (amount_cents > 0),
+      note text,
+      created_at text NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS {payments}_member_id ON {payments} (member_id);
+    ",
+            members = constants::MEMBERS_TABLE,
+            policies = constants::FINE_POLICIES_TABLE,
+            default_type = constants::DEFAULT_MEMBER_TYPE,
+            holidays = constants::HOLIDAYS_TABLE,
+            fines = constants::FINES_TABLE,
+            loans = constants::LOANS_TABLE,
+            payments = constants::FINE_PAYMENTS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {book_contributors} (
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      author_id INTEGER NOT NULL REFERENCES {authors}(id) ON DELETE CASCADE,
+      role text NOT NULL CHECK (role IN ('author', 'editor', 'translator', 'illustrator')),
+      position INTEGER NOT NULL,
+      PRIMARY KEY (book_id, author_id, role)
+    );
+    CREATE INDEX IF NOT EXISTS {book_contributors}_author_id ON {book_contributors} (author_id);
+    ",
+            book_contributors = constants::BOOK_CONTRIBUTORS_TABLE,
+            books = constants::BOOKS_TABLE,
+            authors = constants::AUTHORS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {series} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      name text,
+      description text
+    );
+    CREATE TABLE IF NOT EXISTS {series_books} (
+      series_id INTEGER NOT NULL REFERENCES {series}(id) ON DELETE CASCADE,
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      position REAL NOT NULL,
+      alternate INTEGER NOT NULL DEFAULT 0,
+      PRIMARY KEY (series_id, book_id)
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {series_books}_position ON {series_books} (series_id, position)
+      WHERE alternate = 0;
+    CREATE INDEX IF NOT EXISTS {series_books}_book_id ON {series_books} (book_id);
+    ",
+            series = constants::SERIES_TABLE,
+            series_books = constants::SERIES_BOOKS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {subjects} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      code text,
+      name text,
+      parent_id INTEGER REFERENCES {subjects}(id),
+      path text
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {subjects}_code ON {subjects} (code);
+    CREATE INDEX IF NOT EXISTS {subjects}_parent_id ON {subjects} (parent_id);
+    CREATE INDEX IF NOT EXISTS {subjects}_path ON {subjects} (path);
+    CREATE TRIGGER IF NOT EXISTS {subjects}_path_insert AFTER INSERT ON {subjects}
+    BEGIN
+      UPDATE {subjects} SET path =
+        COALESCE((SELECT path FROM {subjects} WHERE id = NEW.parent_id), '/') || NEW.id || '/'
+        WHERE id = NEW.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS {subjects}_path_update AFTER UPDATE OF parent_id ON {subjects}
+    BEGIN
+      UPDATE {subjects} SET path =
+        COALESCE((SELECT path FROM {subjects} WHERE id = NEW.parent_id), '/') || NEW.id || '/'
+          || substr(path, length(OLD.path) + 1)
+        WHERE path LIKE OLD.path || '%';
+    END;
+    CREATE TABLE IF NOT EXISTS {book_subjects} (
+      book_id INTEGER NOT NULL REFERENCES {books}(id) ON DELETE CASCADE,
+      subject_id INTEGER NOT NULL REFERENCES {subjects}(id) ON DELETE CASCADE,
+      PRIMARY KEY (book_id, subject_id)
+    );
+    CREATE INDEX IF NOT EXISTS {book_subjects}_subject_id ON {book_subjects} (subject_id);
+    ",
+            subjects = constants::SUBJECTS_TABLE,
+            book_subjects = constants::BOOK_SUBJECTS_TABLE,
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    ALTER TABLE {books} ADD COLUMN publication_date text;
+    ALTER TABLE {books} ADD COLUMN language text;
+    ALTER TABLE {books} ADD COLUMN page_count INTEGER CHECK (page_count > 0);
+    ALTER TABLE {books} ADD COLUMN edition text;
+    ALTER TABLE {books} ADD COLUMN description text;
+    CREATE INDEX IF NOT EXISTS {books}_publication_date ON {books} (publication_date);
+    CREATE INDEX IF NOT EXISTS {books}_language ON {books} (language);
+    ",
+            books = constants::BOOKS_TABLE
+        ),
+        format!(
+            "
+    ALTER TABLE {authors} ADD COLUMN birth_date text;
+    ALTER TABLE {authors} ADD COLUMN death_date text;
+    ALTER TABLE {authors} ADD COLUMN nationality text;
+    ALTER TABLE {authors} ADD COLUMN biography text;
+    ALTER TABLE {authors} ADD COLUMN isni text;
+    ALTER TABLE {authors} ADD COLUMN viaf text;
+    ALTER TABLE {authors} ADD COLUMN orcid text;
+    ALTER TABLE {authors} ADD COLUMN wikidata text;
+    CREATE UNIQUE INDEX IF NOT EXISTS {authors}_isni ON {authors} (isni);
+    CREATE UNIQUE INDEX IF NOT EXISTS {authors}_viaf ON {authors} (viaf);
+    CREATE UNIQUE INDEX IF NOT EXISTS {authors}_orcid ON {authors} (orcid);
+    CREATE UNIQUE INDEX IF NOT EXISTS {authors}_wikidata ON {authors} (wikidata);
+    CREATE INDEX IF NOT EXISTS {authors}_birth_date ON {authors} (birth_date);
+    CREATE INDEX IF NOT EXISTS {authors}_nationality ON {authors} (nationality);
+    ",
+            authors = constants::AUTHORS_TABLE
+        ),
+        format!(
+            "
+    CREATE TABLE IF NOT EXISTS {aliases} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      author_id INTEGER NOT NULL REFERENCES {authors}(id) ON DELETE CASCADE,
+      name text NOT NULL COLLATE NOCASE,
+      kind text
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {aliases}_name ON {aliases} (name);
+    CREATE INDEX IF NOT EXISTS {aliases}_author_id ON {aliases} (author_id);
+    CREATE TABLE IF NOT EXISTS {redirects} (
+      author_id INTEGER PRIMARY KEY,
+      target_id INTEGER NOT NULL REFERENCES {authors}(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS {redirects}_target_id ON {redirects} (target_id);
+    ",
+            aliases = constants::AUTHOR_ALIASES_TABLE,
+            redirects = constants::AUTHOR_REDIRECTS_TABLE,
+            authors = constants::AUTHORS_TABLE
+        ),
+        // Books and authors whose suggestions are out of date, for the
+        // in-memory autocomplete index to pick up on its next query.
+        format!(
+            r#"
+    CREATE TABLE IF NOT EXISTS {changes} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      kind TEXT NOT NULL CHECK (kind IN ('book', 'author')),
+      ref_id INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS {loans}_book_id ON {loans} (book_id);
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_insert AFTER INSERT ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_update AFTER UPDATE OF title, author, review_count ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_delete AFTER DELETE ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', OLD.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_loan_insert AFTER INSERT ON {loans}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_insert AFTER INSERT ON {authors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_update AFTER UPDATE OF name ON {authors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_delete AFTER DELETE ON {authors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_insert AFTER INSERT ON {aliases}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_update AFTER UPDATE ON {aliases}
+    BEGIN
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id);
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_delete AFTER DELETE ON {aliases}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_insert AFTER INSERT ON {contributors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_update AFTER UPDATE ON {contributors}
+    BEGIN
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id);
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_delete AFTER DELETE ON {contributors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id); END;
+    "#,
+            changes = constants::AUTOCOMPLETE_CHANGES_TABLE,
+            books = constants::BOOKS_TABLE,
+            loans = constants::LOANS_TABLE,
+            authors = constants::AUTHORS_TABLE,
+            aliases = constants::AUTHOR_ALIASES_TABLE,
+            contributors = constants::BOOK_CONTRIBUTORS_TABLE
+        ),
+        // Books and authors the search backend has yet to reindex, and the
+        // documents of the SQLite backend. Contributors and renamed authors
+        // change the names under which their books are found.
+        format!(
+            r#"
+    CREATE TABLE IF NOT EXISTS {changes} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      kind TEXT NOT NULL CHECK (kind IN ('book', 'author')),
+      ref_id INTEGER NOT NULL
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS {documents} USING fts5(
+      kind UNINDEXED, ref_id UNINDEXED, label UNINDEXED, detail UNINDEXED,
+      title, names, body,
+      tokenize = 'porter unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_insert AFTER INSERT ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_update AFTER UPDATE OF title, author, description, language ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_delete AFTER DELETE ON {books}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', OLD.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_insert AFTER INSERT ON {authors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_update AFTER UPDATE OF name, biography ON {authors}
+    BEGIN
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.id);
+      INSERT INTO {changes} (kind, ref_id)
+        SELECT DISTINCT 'book', book_id FROM {contributors} WHERE author_id = NEW.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_delete AFTER DELETE ON {authors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_insert AFTER INSERT ON {aliases}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_update AFTER UPDATE ON {aliases}
+    BEGIN
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id);
+      INSERT INTO {changes} (kind, ref_id) VALUES ('author', NEW.author_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_alias_delete AFTER DELETE ON {aliases}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('author', OLD.author_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_insert AFTER INSERT ON {contributors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_update AFTER UPDATE ON {contributors}
+    BEGIN
+      INSERT INTO {changes} (kind, ref_id) VALUES ('book', OLD.book_id);
+      INSERT INTO {changes} (kind, ref_id) VALUES ('book', NEW.book_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_delete AFTER DELETE ON {contributors}
+    BEGIN INSERT INTO {changes} (kind, ref_id) VALUES ('book', OLD.book_id); END;
+    "#,
+            changes = constants::SEARCH_CHANGES_TABLE,
+            documents = constants::SEARCH_DOCUMENTS_TABLE,
+            books = constants::BOOKS_TABLE,
+            authors = constants::AUTHORS_TABLE,
+            aliases = constants::AUTHOR_ALIASES_TABLE,
+            contributors = constants::BOOK_CONTRIBUTORS_TABLE
+        ),
+        // Books whose similar books are out of date, and those computed so
+        // far. Renaming what two books share changes the reasons given.
+        format!(
+            r#"
+    CREATE TABLE IF NOT EXISTS {changes} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      book_id INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS {similar} (
+      book_id INTEGER NOT NULL,
+      similar_id INTEGER NOT NULL,
+      score REAL NOT NULL,
+      reasons TEXT NOT NULL,
+      PRIMARY KEY (book_id, similar_id)
+    );
+    CREATE INDEX IF NOT EXISTS {similar}_similar_id ON {similar} (similar_id);
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_insert AFTER INSERT ON {books}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_update AFTER UPDATE OF title, description ON {books}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_book_delete AFTER DELETE ON {books}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (OLD.id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_insert AFTER INSERT ON {contributors}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_update AFTER UPDATE ON {contributors}
+    BEGIN
+      INSERT INTO {changes} (book_id) VALUES (OLD.book_id);
+      INSERT INTO {changes} (book_id) VALUES (NEW.book_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_contributor_delete AFTER DELETE ON {contributors}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (OLD.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_tag_insert AFTER INSERT ON {book_tags}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_tag_delete AFTER DELETE ON {book_tags}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (OLD.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_subject_insert AFTER INSERT ON {book_subjects}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_subject_delete AFTER DELETE ON {book_subjects}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (OLD.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_series_insert AFTER INSERT ON {series_books}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (NEW.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_series_update AFTER UPDATE ON {series_books}
+    BEGIN
+      INSERT INTO {changes} (book_id) VALUES (OLD.book_id);
+      INSERT INTO {changes} (book_id) VALUES (NEW.book_id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_series_delete AFTER DELETE ON {series_books}
+    BEGIN INSERT INTO {changes} (book_id) VALUES (OLD.book_id); END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_author_update AFTER UPDATE OF name ON {authors}
+    BEGIN
+      INSERT INTO {changes} (book_id) SELECT book_id FROM {contributors} WHERE author_id = NEW.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_tag_update AFTER UPDATE OF name ON {tags}
+    BEGIN
+      INSERT INTO {changes} (book_id) SELECT book_id FROM {book_tags} WHERE tag_id = NEW.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_subject_update AFTER UPDATE OF name ON {subjects}
+    BEGIN
+      INSERT INTO {changes} (book_id) SELECT book_id FROM {book_subjects} WHERE subject_id = NEW.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS {changes}_series_rename AFTER UPDATE OF name ON {series}
+    BEGIN
+      INSERT INTO {changes} (book_id) SELECT book_id FROM {series_books} WHERE series_id = NEW.id;
+    END;
+    "#,
+            changes = constants::SIMILAR_CHANGES_TABLE,
+            similar = constants::SIMILAR_BOOKS_TABLE,
+            books = constants::BOOKS_TABLE,
+            authors = constants::AUTHORS_TABLE,
+            contributors = constants::BOOK_CONTRIBUTORS_TABLE,
+            tags = constants::TAGS_TABLE,
+            book_tags = constants::BOOK_TAGS_TABLE,
+            subjects = constants::SUBJECTS_TABLE,
+            book_subjects = constants::BOOK_SUBJECTS_TABLE,
+            series = constants::SERIES_TABLE,
+            series_books = constants::SERIES_BOOKS_TABLE
+        ),
+        // When books and authors were catalogued, for the growth statistics.
+        // Rows from before this are left undated.
+        format!(
+            "
+    ALTER TABLE {books} ADD COLUMN created_at text;
+    ALTER TABLE {authors} ADD COLUMN created_at text;
+    CREATE INDEX IF NOT EXISTS {books}_created_at ON {books} (created_at);
+    CREATE INDEX IF NOT EXISTS {authors}_created_at ON {authors} (created_at);
+    DROP INDEX IF EXISTS {loans}_book_id;
+    CREATE INDEX IF NOT EXISTS {loans}_book_id_loaned_at ON {loans} (book_id, loaned_at);
+    CREATE TRIGGER IF NOT EXISTS {books}_created AFTER INSERT ON {books} WHEN NEW.created_at IS NULL
+    BEGIN UPDATE {books} SET created_at = {now} WHERE id = NEW.id; END;
+    CREATE TRIGGER IF NOT EXISTS {authors}_created AFTER INSERT ON {authors} WHEN NEW.created_at IS NULL
+    BEGIN UPDATE {authors} SET created_at = {now} WHERE id = NEW.id; END;
+    ",
+            books = constants::BOOKS_TABLE,
+            authors = constants::AUTHORS_TABLE,
+            loans = constants::LOANS_TABLE,
+            now = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')"
+        ),
+        // Custom fields, declared per resource. Their expression indexes are
+        // managed by `metadata_db::sync_indexes`.
+        format!(
+            "
+    ALTER TABLE {books} ADD COLUMN metadata text CHECK (metadata IS NULL OR json_valid(metadata));
+    ALTER TABLE {authors} ADD COLUMN metadata text CHECK (metadata IS NULL OR json_valid(metadata));
+    CREATE TABLE IF NOT EXISTS {fields} (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      resource text NOT NULL CHECK (resource IN ('{books}', '{authors}')),
+      name text NOT NULL,
+      type text NOT NULL CHECK (type IN ('text', 'integer', 'number', 'boolean', 'date')),
+      indexed INTEGER,
+      description text
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS {fields}_resource_name ON {fields} (resource, name);
+    ",
+            books = constants::BOOKS_TABLE,
+            authors = constants::AUTHORS_TABLE,
+            fields = constants::METADATA_FIELDS_TABLE
+        ),
+    ]
+}
 
-    Ok(sqlite_pool)
+pub async fn migrate(pool: &Pool<Sqlite>) -> Result<(), Error> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await?;
+
+    for (i, migration) in migrations().iter().enumerate().skip(version as usize) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration).execute(&mut tx).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", i + 1))
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_pool;
+
+    #[actix_web::test]
+    async fn test_migrate_is_idempotent() {
+        let pool = test_pool().await;
+        migrate(&pool).await.unwrap();
+
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(version, migrations().len() as i64);
+    }
+
+    #[actix_web::test]
+    async fn test_loans_index_covers_loaned_at() {
+        let pool = test_pool().await;
+
+        let columns: Vec<String> =
+            sqlx::query_scalar("Select name From pragma_index_info(?) order by seqno")
+                .bind(format!("{}_book_id_loaned_at", constants::LOANS_TABLE))
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(columns, ["book_id", "loaned_at"]);
+    }
 }
@@ -0,0 +1,228 @@
+use super::super::similarity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    JaroWinkler,
+    Levenshtein,
+}
+
+impl Algorithm {
+    pub fn parse(algorithm: &str) -> Result<Algorithm, String> {
+        match algorithm {
+            "jaro_winkler" => Ok(Algorithm::JaroWinkler),
+            "levenshtein" => Ok(Algorithm::Levenshtein),
+            _ => Err(format!(
+                "algorithm must be jaro_winkler or levenshtein (got {})",
+                algorithm
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algorithm::JaroWinkler => "jaro_winkler",
+            Algorithm::Levenshtein => "levenshtein",
+        }
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        match self {
+            Algorithm::JaroWinkler => similarity::jaro_winkler(a, b),
+            Algorithm::Levenshtein => similarity::levenshtein_similarity(a, b),
+        }
+    }
+}
+
+// One compared attribute of a record. A field may have several spellings,
+// such as an author's name and aliases, and the closest pair counts.
+pub struct Field {
+    pub weight: f64,
+    pub variants: Vec<String>,
+}
+
+impl Field {
+    // Variants are compared token sorted, blanks are left out.
+    pub fn new<'a>(weight: f64, values: impl IntoIterator<Item = &'a str>) -> Field {
+        Field {
+            weight,
+            variants: values
+                .into_iter()
+                .map(similarity::token_sort)
+                .filter(|v| !v.is_empty())
+                .collect(),
+        }
+    }
+}
+
+pub struct Candidate {
+    pub id: i64,
+    pub fields: Vec<Field>,
+    // How many attributes the record has filled in, used to pick the
+    // canonical record of a cluster.
+    pub completeness: usize,
+}
+
+pub struct Cluster {
+    // The lowest score among the matched pairs of the cluster.
+    pub score: f64,
+    pub canonical: usize,
+    // Indexes into the candidates, canonical record excluded, by id.
+    pub duplicates: Vec<usize>,
+}
+
+// The weighted mean similarity of the fields both records have.
+fn score(a: &Candidate, b: &Candidate, algorithm: Algorithm) -> f64 {
+    let mut total = 0.0;
+    let mut weights = 0.0;
+    for (fa, fb) in a.fields.iter().zip(&b.fields) {
+        if fa.variants.is_empty() || fb.variants.is_empty() {
+            continue;
+        }
+        let best = fa
+            .variants
+            .iter()
+            .flat_map(|va| fb.variants.iter().map(move |vb| (va, vb)))
+            .map(|(va, vb)| algorithm.similarity(va, vb))
+            .fold(0.0, f64::max);
+        total += fa.weight * best;
+        weights += fa.weight;
+    }
+    if weights == 0.0 {
+        0.0
+    } else {
+        total / weights
+    }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+// Groups candidates whose pairwise score reaches `threshold`, transitively.
+// Every pair is compared, which is fine for a report but grows with the
+// square of the number of rows.
+pub fn clusters(candidates: &[Candidate], algorithm: Algorithm, threshold: f64) -> Vec<Cluster> {
+    let n = candidates.len();
+    let mut parents: Vec<usize> = (0..n).collect();
+    let mut weakest = vec![f64::INFINITY; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let s = score(&candidates[i], &candidates[j], algorithm);
+            if s < threshold {
+                continue;
+            }
+            let (ri, rj) = (find(&mut parents, i), find(&mut parents, j));
+            let link = s.min(weakest[ri]).min(weakest[rj]);
+            parents[rj] = ri;
+            weakest[ri] = link;
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for i in 0..n {
+        let root = find(&mut parents, i);
+        groups[root].push(i);
+    }
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .enumerate()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, mut members)| {
+            members.sort_by_key(|m| candidates[*m].id);
+            let canonical = *members
+                .iter()
+                .max_by_key(|m| {
+                    (
+                        candidates[**m].completeness,
+                        std::cmp::Reverse(candidates[**m].id),
+                    )
+                })
+                .unwrap();
+            Cluster {
+                score: weakest[root],
+                canonical,
+                duplicates: members.into_iter().filter(|m| *m != canonical).collect(),
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(candidates[a.canonical].id.cmp(&candidates[b.canonical].id))
+    });
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: i64, names: &[&str], completeness: usize) -> Candidate {
+        Candidate {
+            id,
+            fields: vec![Field::new(1.0, names.iter().copied())],
+            completeness,
+        }
+    }
+
+    #[test]
+    fn test_clusters() {
+        let candidates = vec![
+            candidate(1, &["J. R. R. Tolkien"], 1),
+            candidate(2, &["Jane Austen"], 1),
+            candidate(3, &["Tolkien, J.R.R."], 3),
+            candidate(4, &["Tolkein, J. R. R."], 1),
+            candidate(5, &["Austen, Jane"], 1),
+            candidate(6, &["Frank Herbert"], 1),
+        ];
+
+        let found = clusters(&candidates, Algorithm::JaroWinkler, 0.9);
+        let ids: Vec<(i64, Vec<i64>)> = found
+            .iter()
+            .map(|c| {
+                (
+                    candidates[c.canonical].id,
+                    c.duplicates.iter().map(|d| candidates[*d].id).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(ids, vec![(2, vec![5]), (3, vec![1, 4])]);
+        assert_eq!(found[0].score, 1.0);
+        assert!(found[1].score >= 0.9 && found[1].score < 1.0);
+
+        let found = clusters(&candidates, Algorithm::Levenshtein, 1.0);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].duplicates.len(), 1);
+    }
+
+    #[test]
+    fn test_clusters_use_every_variant_and_skip_missing_fields() {
+        let candidates = vec![
+            Candidate {
+                id: 1,
+                fields: vec![
+                    Field::new(1.0, ["Mark Twain", "Samuel Clemens"]),
+                    Field::new(1.0, []),
+                ],
+                completeness: 1,
+            },
+            Candidate {
+                id: 2,
+                fields: vec![
+                    Field::new(1.0, ["Samuel Clemens"]),
+                    Field::new(1.0, ["1835"]),
+                ],
+                completeness: 1,
+            },
+        ];
+
+        let found = clusters(&candidates, Algorithm::JaroWinkler, 0.99);
+        assert_eq!(found.len(), 1);
+        assert_eq!(candidates[found[0].canonical].id, 1);
+    }
+}
@@ -0,0 +1,297 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+
+use super::super::authors::alias::Alias;
+use super::super::authors::author::Author;
+use super::super::authors::authors_queries;
+use super::super::books::book::Book;
+use super::super::resources::filter::Filters;
+use super::super::resources::resource::Resource;
+use super::super::resources::resources_repository::Repository;
+use super::super::responses::CustomError;
+use super::cluster::{self, Algorithm, Candidate, Field};
+
+pub const DEFAULT_THRESHOLD: f64 = 0.92;
+
+pub fn config_duplicates(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_duplicates);
+}
+
+#[derive(Deserialize)]
+pub struct DuplicatesParams {
+    pub resource: Option<String>,
+    pub algorithm: Option<String>,
+    pub threshold: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Cluster<R> {
+    pub score: f64,
+    // The record the others would best be merged into.
+    pub canonical: R,
+    pub duplicates: Vec<R>,
+}
+
+#[derive(Serialize)]
+pub struct Report<R> {
+    pub resource: String,
+    pub algorithm: &'static str,
+    pub threshold: f64,
+    pub clusters: Vec<Cluster<R>>,
+}
+
+fn completeness<R: Resource>(r: &R) -> usize {
+    R::FIELDS.iter().filter(|f| !r.get(f).is_null()).count()
+}
+
+fn report<R: Resource>(
+    resource: &str,
+    records: Vec<R>,
+    fields: impl Fn(&R) -> Vec<Field>,
+    algorithm: Algorithm,
+    threshold: f64,
+) -> Report<R> {
+    let candidates: Vec<Candidate> = records
+        .iter()
+        .map(|r| Candidate {
+            id: r.id().unwrap_or_default(),
+            fields: fields(r),
+            completeness: completeness(r),
+        })
+        .collect();
+    let clusters = cluster::clusters(&candidates, algorithm, threshold)
+        .into_iter()
+        .map(|c| Cluster {
+            score: c.score,
+            canonical: records[c.canonical].clone(),
+            duplicates: c.duplicates.iter().map(|d| records[*d].clone()).collect(),
+        })
+        .collect();
+
+    Report {
+        resource: resource.to_string(),
+        algorithm: algorithm.as_str(),
+        threshold,
+        clusters,
+    }
+}
+
+// Books are compared on title and, with less weight, on author; authors on
+// their name and aliases.
+#[get("/admin/duplicates")]
+async fn get_duplicates(
+    params: web::Query<DuplicatesParams>,
+    books: web::Data<dyn Repository<Book>>,
+    authors: web::Data<dyn Repository<Author>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let algorithm = match params.algorithm.as_deref().map(Algorithm::parse) {
+        None => Algorithm::JaroWinkler,
+        Some(Ok(a)) => a,
+        Some(Err(e)) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    let threshold = params.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "threshold must be between 0 and 1 (got {})",
+            threshold
+        )));
+    }
+
+    match params.resource.as_deref() {
+        Some("books") => match books.get_all(&Filters::default()).await {
+            Ok(records) => HttpResponse::Ok().json(report(
+                "books",
+                records,
+                |b: &Book| {
+                    vec![
+                        Field::new(3.0, b.title.as_deref()),
+                        Field::new(1.0, b.author.as_deref()),
+                    ]
+                },
+                algorithm,
+                threshold,
+            )),
+            Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+        },
+        Some("authors") => {
+            let aliases = sqlx::query_as::<_, Alias>(&authors_queries::get_all_aliases_query())
+                .fetch_all(pool.get_ref())
+                .await;
+            let records = authors.get_all(&Filters::default()).await;
+            let (records, aliases) = match (records, aliases) {
+                (Ok(r), Ok(a)) => (r, a),
+                (Err(e), _) | (_, Err(e)) => {
+                    return HttpResponse::InternalServerError().json(CustomError::new(e))
+                }
+            };
+            let mut names: HashMap<i64, Vec<String>> = HashMap::new();
+            for alias in aliases {
+                if let (Some(id), Some(name)) = (alias.author_id, alias.name) {
+                    names.entry(id).or_default().push(name);
+                }
+            }
+            HttpResponse::Ok().json(report(
+                "authors",
+                records,
+                |a: &Author| {
+                    let aliases = a.id.and_then(|id| names.get(&id));
+                    let variants = a
+                        .name
+                        .iter()
+                        .chain(aliases.into_iter().flatten())
+                        .map(String::as_str);
+                    vec![Field::new(1.0, variants)]
+                },
+                algorithm,
+                threshold,
+            ))
+        }
+        _ => HttpResponse::BadRequest()
+            .json(CustomError::message("resource must be books or authors")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, author, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(authors::authors::config_authors)
+                    .configure(super::config_duplicates),
+            )
+            .await
+        }};
+    }
+
+    fn ids(cluster: &Value) -> (i64, Vec<i64>) {
+        (
+            cluster["canonical"]["id"].as_i64().unwrap(),
+            cluster["duplicates"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| d["id"].as_i64().unwrap())
+                .collect(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_duplicate_books() {
+        let conn_pool = test_pool().await;
+        let first = book()
+            .title("The Lord of the Rings")
+            .author("Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let second = book()
+            .title("Lord of the Rings, The")
+            .author("J.R.R. Tolkien")
+            .isbn("9780261103252")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        book()
+            .title("The Hobbit")
+            .author("Tolkien")
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri("/admin/duplicates?resource=books&threshold=0.7")
+            .to_request();
+        let report: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["resource"], "books");
+        assert_eq!(report["algorithm"], "jaro_winkler");
+        let clusters = report["clusters"].as_array().unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(ids(&clusters[0]), (second, vec![first]));
+        assert!(clusters[0]["score"].as_f64().unwrap() >= 0.7);
+    }
+
+    #[actix_web::test]
+    async fn test_duplicate_authors() {
+        let conn_pool = test_pool().await;
+        let tolkien = author()
+            .name("J. R. R. Tolkien")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let variant = author()
+            .name("Tolkien, J.R.R.")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let clemens = author()
+            .name("Samuel Clemens")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let twain = author()
+            .name("Mark Twain")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        author().name("Jane Austen").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/authors/{}/aliases", twain))
+            .set_json(json!({"name": "Samuel L. Clemens"}))
+            .to_request();
+        test::call_service(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/admin/duplicates?resource=authors&algorithm=levenshtein&threshold=0.7")
+            .to_request();
+        let report: Value = test::call_and_read_body_json(&app, req).await;
+        let clusters: Vec<(i64, Vec<i64>)> = report["clusters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(ids)
+            .collect();
+        assert_eq!(
+            clusters,
+            vec![(tolkien, vec![variant]), (clemens, vec![twain])]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_duplicates_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for uri in [
+            "/admin/duplicates",
+            "/admin/duplicates?resource=tags",
+            "/admin/duplicates?resource=books&threshold=2",
+            "/admin/duplicates?resource=books&algorithm=soundex",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+}
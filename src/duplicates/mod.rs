@@ -0,0 +1,3 @@
+pub mod cluster;
+#[allow(clippy::module_inception)]
+pub mod duplicates;
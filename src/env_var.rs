@@ -1,5 +1,9 @@
 use super::constants;
+use super::loans::loan::LoanPolicy;
+use super::search::backend::Boosts;
+use super::similar::scoring::Weights;
 use std::env;
+use std::time::Duration;
 
 pub fn get_addr() -> String {
     match env::var(constants::ADDR) {
@@ -8,3 +12,70 @@ pub fn get_addr() -> String {
         Err(e) => panic!("${} is not set ({})", constants::ADDR, e),
     }
 }
+
+pub fn get_storage() -> String {
+    // storage="sqlite" | "memory"
+    env::var(constants::STORAGE).unwrap_or_else(|_| constants::STORAGE_SQLITE.to_string())
+}
+
+fn get_number(key: &str, default: i64) -> i64 {
+    match env::var(key) {
+        Ok(v) => v
+            .parse()
+            .unwrap_or_else(|e| panic!("${} must be a number ({})", key, e)),
+        Err(_) => default,
+    }
+}
+
+fn get_float(key: &str, default: f32) -> f32 {
+    match env::var(key) {
+        Ok(v) => v
+            .parse()
+            .unwrap_or_else(|e| panic!("${} must be a number ({})", key, e)),
+        Err(_) => default,
+    }
+}
+
+pub fn get_search() -> String {
+    // search="sqlite" | "tantivy"
+    env::var(constants::SEARCH).unwrap_or_else(|_| constants::SEARCH_SQLITE.to_string())
+}
+
+pub fn get_search_boosts() -> Boosts {
+    // title_boost="3", names_boost="2", body_boost="1"
+    Boosts {
+        title: get_float(constants::TITLE_BOOST, constants::DEFAULT_TITLE_BOOST),
+        names: get_float(constants::NAMES_BOOST, constants::DEFAULT_NAMES_BOOST),
+        body: get_float(constants::BODY_BOOST, constants::DEFAULT_BODY_BOOST),
+    }
+}
+
+pub fn get_similar_weights() -> Weights {
+    // contributor_weight="3", tag_weight="1", subject_weight="1.5",
+    // series_weight="4", text_weight="2"
+    Weights {
+        contributor: get_float(
+            constants::CONTRIBUTOR_WEIGHT,
+            constants::DEFAULT_CONTRIBUTOR_WEIGHT,
+        ),
+        tag: get_float(constants::TAG_WEIGHT, constants::DEFAULT_TAG_WEIGHT),
+        subject: get_float(constants::SUBJECT_WEIGHT, constants::DEFAULT_SUBJECT_WEIGHT),
+        series: get_float(constants::SERIES_WEIGHT, constants::DEFAULT_SERIES_WEIGHT),
+        text: get_float(constants::TEXT_WEIGHT, constants::DEFAULT_TEXT_WEIGHT),
+    }
+}
+
+pub fn get_stats_ttl() -> Duration {
+    // stats_ttl="300", in seconds; "0" turns the cache off
+    let ttl = get_number(constants::STATS_TTL, constants::DEFAULT_STATS_TTL);
+    Duration::from_secs(ttl.max(0) as u64)
+}
+
+pub fn get_loan_policy() -> LoanPolicy {
+    // loan_days="14", max_renewals="2", hold_days="3"
+    LoanPolicy {
+        loan_days: get_number(constants::LOAN_DAYS, constants::DEFAULT_LOAN_DAYS),
+        max_renewals: get_number(constants::MAX_RENEWALS, constants::DEFAULT_MAX_RENEWALS),
+        hold_days: get_number(constants::HOLD_DAYS, constants::DEFAULT_HOLD_DAYS),
+    }
+}
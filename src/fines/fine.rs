@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// Amounts are in cents throughout.
+#[derive(Serialize, Debug, FromRow, Clone)]
+pub struct Fine {
+    pub id: i64,
+    pub loan_id: i64,
+    pub member_id: i64,
+    pub days_late: i64,
+    pub amount_cents: i64,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PaymentKind {
+    Payment,
+    Waiver,
+}
+
+// Money paid towards, or written off from, a member's fines.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Payment {
+    pub id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub member_id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub kind: Option<PaymentKind>,
+    pub amount_cents: Option<i64>,
+    pub note: Option<String>,
+    #[serde(skip_deserializing)]
+    pub created_at: Option<String>,
+}
+
+impl Payment {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.amount_cents {
+            None => Err("amount_cents is required".to_string()),
+            Some(a) if a <= 0 => Err("amount_cents must be positive".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, FromRow, Clone)]
+pub struct Balance {
+    pub member_id: i64,
+    pub fines_cents: i64,
+    pub paid_cents: i64,
+    pub waived_cents: i64,
+    pub balance_cents: i64,
+    #[sqlx(default)]
+    pub block_threshold_cents: Option<i64>,
+    #[sqlx(default)]
+    pub blocked: bool,
+}
@@ -0,0 +1,136 @@
+use super::super::constants::FINE_POLICIES_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// Fine rules for one member type. Members without a type fall under
+// `constants::DEFAULT_MEMBER_TYPE`. Amounts are in cents; an empty cap or
+// threshold means there is none.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct FinePolicy {
+    pub id: Option<i64>,
+    pub member_type: Option<String>,
+    pub daily_rate_cents: Option<i64>,
+    pub grace_days: Option<i64>,
+    pub max_fine_cents: Option<i64>,
+    // Members owing more than this may not borrow.
+    pub block_threshold_cents: Option<i64>,
+}
+
+impl FinePolicy {
+    // Only the days past the grace period are charged.
+    pub fn fine_cents(&self, days_late: i64) -> i64 {
+        let days = days_late - self.grace_days.unwrap_or(0);
+        if days <= 0 {
+            return 0;
+        }
+        let fine = days * self.daily_rate_cents.unwrap_or(0);
+        match self.max_fine_cents {
+            Some(max) => fine.min(max),
+            None => fine,
+        }
+    }
+
+    pub fn blocks(&self, balance_cents: i64) -> bool {
+        matches!(self.block_threshold_cents, Some(t) if balance_cents > t)
+    }
+}
+
+impl Resource for FinePolicy {
+    const PATH: &'static str = "/fine-policies";
+    const TABLE: &'static str = FINE_POLICIES_TABLE;
+    const FIELDS: &'static [&'static str] = &[
+        "member_type",
+        "daily_rate_cents",
+        "grace_days",
+        "max_fine_cents",
+        "block_threshold_cents",
+    ];
+    const FILTERABLE: &'static [&'static str] = &["member_type"];
+    const SORTABLE: &'static [&'static str] = &["id", "member_type"];
+    const REQUIRED: &'static [&'static str] = &["member_type", "daily_rate_cents"];
+    const UNIQUE: &'static [&'static str] = &["member_type"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "member_type" => Value::from(self.member_type.clone()),
+            "daily_rate_cents" => Value::from(self.daily_rate_cents),
+            "grace_days" => Value::from(self.grace_days),
+            "max_fine_cents" => Value::from(self.max_fine_cents),
+            "block_threshold_cents" => Value::from(self.block_threshold_cents),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "member_type" => self.member_type = value.into(),
+            "daily_rate_cents" => self.daily_rate_cents = value.into(),
+            "grace_days" => self.grace_days = value.into(),
+            "max_fine_cents" => self.max_fine_cents = value.into(),
+            "block_threshold_cents" => self.block_threshold_cents = value.into(),
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.member_type = self.member_type.as_deref().map(|t| t.trim().to_lowercase());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("member_type", &self.member_type)?;
+        for (field, value) in [
+            ("daily_rate_cents", self.daily_rate_cents),
+            ("grace_days", self.grace_days),
+            ("max_fine_cents", self.max_fine_cents),
+            ("block_threshold_cents", self.block_threshold_cents),
+        ] {
+            if matches!(value, Some(v) if v < 0) {
+                return Err(format!("{} must not be negative", field));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(grace_days: i64, max_fine_cents: Option<i64>) -> FinePolicy {
+        FinePolicy {
+            id: None,
+            member_type: Some("standard".to_string()),
+            daily_rate_cents: Some(25),
+            grace_days: Some(grace_days),
+            max_fine_cents,
+            block_threshold_cents: Some(500),
+        }
+    }
+
+    #[test]
+    fn test_fine_cents() {
+        assert_eq!(policy(0, None).fine_cents(0), 0);
+        assert_eq!(policy(0, None).fine_cents(4), 100);
+        assert_eq!(policy(2, None).fine_cents(2), 0);
+        assert_eq!(policy(2, None).fine_cents(4), 50);
+        assert_eq!(policy(0, Some(60)).fine_cents(4), 60);
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert!(!policy(0, None).blocks(500));
+        assert!(policy(0, None).blocks(501));
+    }
+}
@@ -0,0 +1,321 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::resources::resources;
+use super::super::responses::{CreateResponse, CustomError};
+use super::fine::{Payment, PaymentKind};
+use super::fine_policy::FinePolicy;
+use super::fines_db::{self, Paid};
+use super::holiday::Holiday;
+
+pub fn config_fines(cfg: &mut web::ServiceConfig) {
+    resources::config::<FinePolicy>(cfg);
+    resources::config::<Holiday>(cfg);
+    cfg.service(get_balance)
+        .service(get_fines)
+        .service(get_payments)
+        .service(create_payment)
+        .service(create_waiver);
+}
+
+async fn pay(
+    pool: &Pool<Sqlite>,
+    member_id: i64,
+    kind: PaymentKind,
+    payment: Payment,
+) -> HttpResponse {
+    if let Err(e) = payment.validate() {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = fines_db::create_payment(pool, member_id, kind, payment).await;
+
+    match r {
+        Ok(Paid::Payment(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(Paid::Exceeds(owed)) => HttpResponse::Conflict().json(CustomError::message(format!(
+            "member {} only owes {} cents",
+            member_id, owed
+        ))),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/members/{id}/balance")]
+async fn get_balance(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = fines_db::get_balance(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/members/{id}/fines")]
+async fn get_fines(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = fines_db::get_fines(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Lists payments and waivers together.
+#[get("/members/{id}/payments")]
+async fn get_payments(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = fines_db::get_payments(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[post("/members/{id}/payments")]
+async fn create_payment(
+    id: web::Path<i64>,
+    json: web::Json<Payment>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    pay(
+        pool.get_ref(),
+        id.into_inner(),
+        PaymentKind::Payment,
+        json.into_inner(),
+    )
+    .await
+}
+
+#[post("/members/{id}/waivers")]
+async fn create_waiver(
+    id: web::Path<i64>,
+    json: web::Json<Payment>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    pay(
+        pool.get_ref(),
+        id.into_inner(),
+        PaymentKind::Waiver,
+        json.into_inner(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::loans::loan::LoanPolicy;
+    use crate::test_utils::{self, book, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test, web,
+    };
+    use serde_json::{json, Value};
+    use sqlx::{Pool, Sqlite};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(LoanPolicy {
+                        loan_days: 14,
+                        max_renewals: 1,
+                        hold_days: 3,
+                    }))
+                    .configure(loans::loans::config_loans)
+                    .configure(super::config_fines),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! post {
+        ($app:expr, $uri:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&$uri)
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    // Lends a book that was due `days` days ago and returns it.
+    macro_rules! return_late {
+        ($app:expr, $pool:expr, $member_id:expr, $days:expr) => {{
+            let b = book().create(&$pool).await.id.unwrap();
+            let resp = post!(
+                $app,
+                "/loans",
+                json!({"book_id": b, "member_id": $member_id})
+            );
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let loan: Value = test::read_body_json(resp).await;
+            due_days_ago(&$pool, loan["id"].as_i64().unwrap(), $days).await;
+            let resp = post!($app, format!("/loans/{}/return", loan["id"]), json!({}));
+            let loan: Value = test::read_body_json(resp).await;
+            loan
+        }};
+    }
+
+    async fn due_days_ago(pool: &Pool<Sqlite>, loan_id: i64, days: i64) {
+        sqlx::query("UPDATE loans SET due_at=strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?) where id=?")
+            .bind(format!("-{} days", days))
+            .bind(loan_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn add_holiday(pool: &Pool<Sqlite>, days_ago: i64) {
+        sqlx::query("INSERT INTO holidays (date, name) values (date('now', ?), 'closed')")
+            .bind(format!("-{} days", days_ago))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_fine_is_charged_on_return() {
+        let conn_pool = test_pool().await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let loan = return_late!(app, conn_pool, m, 4);
+        assert_eq!(loan["fine_cents"], 100);
+        let on_time = return_late!(app, conn_pool, m, 0);
+        assert!(on_time["fine_cents"].is_null());
+
+        let fines = get_json!(app, format!("/members/{}/fines", m));
+        assert_eq!(fines[0]["loan_id"], loan["id"]);
+        assert_eq!(fines[0]["days_late"], 4);
+        assert_eq!(fines.as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_fine_follows_member_type_policy() {
+        let conn_pool = test_pool().await;
+        let m = member()
+            .member_type("student")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(
+            app,
+            "/fine-policies",
+            json!({"member_type": "Student", "daily_rate_cents": 10, "grace_days": 2, "max_fine_cents": 25})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        add_holiday(&conn_pool, 1).await;
+
+        // 6 days late, one of them a holiday and two of grace.
+        let loan = return_late!(app, conn_pool, m, 6);
+        assert_eq!(loan["fine_cents"], 25);
+        let loan = return_late!(app, conn_pool, m, 4);
+        assert_eq!(loan["fine_cents"], 10);
+        let loan = return_late!(app, conn_pool, m, 3);
+        assert!(loan["fine_cents"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_payments_and_waivers() {
+        let conn_pool = test_pool().await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        return_late!(app, conn_pool, m, 4);
+
+        let resp = post!(
+            app,
+            format!("/members/{}/payments", m),
+            json!({"amount_cents": 60, "note": "cash"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let resp = post!(
+            app,
+            format!("/members/{}/waivers", m),
+            json!({"amount_cents": 50})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], format!("member {} only owes 40 cents", m));
+        let resp = post!(
+            app,
+            format!("/members/{}/waivers", m),
+            json!({"amount_cents": 40})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let balance = get_json!(app, format!("/members/{}/balance", m));
+        assert_eq!(
+            balance,
+            json!({"member_id": m, "fines_cents": 100, "paid_cents": 60, "waived_cents": 40, "balance_cents": 0, "block_threshold_cents": 1000, "blocked": false})
+        );
+        let payments = get_json!(app, format!("/members/{}/payments", m));
+        assert_eq!(payments[0]["kind"], "waiver");
+        assert_eq!(payments[1]["kind"], "payment");
+        assert_eq!(payments[1]["note"], "cash");
+
+        let resp = post!(
+            app,
+            format!("/members/{}/payments", m),
+            json!({"amount_cents": 0})
+        );
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_balance_above_threshold_blocks_loans() {
+        let conn_pool = test_pool().await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        return_late!(app, conn_pool, m, 100);
+        return_late!(app, conn_pool, m, 1);
+
+        let balance = get_json!(app, format!("/members/{}/balance", m));
+        assert_eq!(balance["balance_cents"], 1025);
+        assert_eq!(balance["blocked"], true);
+
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("member {} owes 1025 cents, more than the limit of 1000", m)
+        );
+
+        post!(
+            app,
+            format!("/members/{}/payments", m),
+            json!({"amount_cents": 25})
+        );
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn test_create_holiday_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/holidays", json!({"date": "2023-02-29"}));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let resp = post!(app, "/holidays", json!({"date": "2024-12-25"}));
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let resp = post!(app, "/holidays", json!({"date": "2024-12-25"}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+}
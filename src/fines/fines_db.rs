@@ -0,0 +1,129 @@
+use super::super::loans::loan::Loan;
+use super::fine::{Balance, Fine, Payment, PaymentKind};
+use super::fine_policy::FinePolicy;
+use super::fines_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Paid {
+    Payment(i64),
+    // The amount is more than the member owes.
+    Exceeds(i64),
+}
+
+async fn ensure_member(tx: &mut Transaction<'_, Sqlite>, member_id: i64) -> Result<(), Error> {
+    sqlx::query(&fines_queries::get_member_id_query())
+        .bind(member_id)
+        .fetch_one(tx)
+        .await?;
+
+    Ok(())
+}
+
+async fn policy(
+    tx: &mut Transaction<'_, Sqlite>,
+    member_id: i64,
+) -> Result<Option<FinePolicy>, Error> {
+    sqlx::query_as::<_, FinePolicy>(&fines_queries::get_policy_query())
+        .bind(member_id)
+        .fetch_optional(tx)
+        .await
+}
+
+pub async fn balance(tx: &mut Transaction<'_, Sqlite>, member_id: i64) -> Result<Balance, Error> {
+    let mut balance = sqlx::query_as::<_, Balance>(&fines_queries::get_balance_query())
+        .bind(member_id)
+        .bind(member_id)
+        .bind(member_id)
+        .bind(member_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if let Some(p) = policy(tx, member_id).await? {
+        balance.block_threshold_cents = p.block_threshold_cents;
+        balance.blocked = p.blocks(balance.balance_cents);
+    }
+
+    Ok(balance)
+}
+
+// Charges the fine for a loan that has just been returned, following the
+// policy for the member's type. Nothing is charged without a policy.
+pub async fn charge_fine(tx: &mut Transaction<'_, Sqlite>, loan: &Loan) -> Result<(), Error> {
+    let member_id = loan.member_id.unwrap();
+    let p = match policy(tx, member_id).await? {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    let days_late: i64 = sqlx::query_scalar(&fines_queries::count_days_late_query())
+        .bind(&loan.due_at)
+        .bind(&loan.returned_at)
+        .bind(&loan.returned_at)
+        .fetch_one(&mut *tx)
+        .await?;
+    let amount = p.fine_cents(days_late);
+    if amount == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(&fines_queries::create_fine_query())
+        .bind(loan.id)
+        .bind(member_id)
+        .bind(days_late)
+        .bind(amount)
+        .execute(tx)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_balance(pool: &Pool<Sqlite>, member_id: i64) -> Result<Balance, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_member(&mut tx, member_id).await?;
+
+    balance(&mut tx, member_id).await
+}
+
+pub async fn get_fines(pool: &Pool<Sqlite>, member_id: i64) -> Result<Vec<Fine>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_member(&mut tx, member_id).await?;
+
+    sqlx::query_as::<_, Fine>(&fines_queries::get_member_fines_query())
+        .bind(member_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+pub async fn get_payments(pool: &Pool<Sqlite>, member_id: i64) -> Result<Vec<Payment>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_member(&mut tx, member_id).await?;
+
+    sqlx::query_as::<_, Payment>(&fines_queries::get_member_payments_query())
+        .bind(member_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+pub async fn create_payment(
+    pool: &Pool<Sqlite>,
+    member_id: i64,
+    kind: PaymentKind,
+    payment: Payment,
+) -> Result<Paid, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_member(&mut tx, member_id).await?;
+    let owed = balance(&mut tx, member_id).await?.balance_cents;
+    if payment.amount_cents.unwrap_or(0) > owed {
+        return Ok(Paid::Exceeds(owed));
+    }
+
+    let r = sqlx::query(&fines_queries::create_payment_query())
+        .bind(member_id)
+        .bind(kind)
+        .bind(payment.amount_cents)
+        .bind(payment.note)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Paid::Payment(r.last_insert_rowid()))
+}
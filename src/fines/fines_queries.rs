@@ -0,0 +1,77 @@
+use super::super::constants::{
+    DEFAULT_MEMBER_TYPE, FINES_TABLE, FINE_PAYMENTS_TABLE, FINE_POLICIES_TABLE, HOLIDAYS_TABLE,
+    MEMBERS_TABLE,
+};
+
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+pub fn get_member_id_query() -> String {
+    format!("Select id From {} where id=?", MEMBERS_TABLE)
+}
+
+pub fn get_policy_query() -> String {
+    format!(
+        "Select * From {} where member_type = \
+         (Select coalesce(member_type, '{}') From {} where id=?)",
+        FINE_POLICIES_TABLE, DEFAULT_MEMBER_TYPE, MEMBERS_TABLE
+    )
+}
+
+// Counts the calendar days after the due date up to and including the day
+// of return, leaving out holidays. Binds: due_at, returned_at, returned_at.
+pub fn count_days_late_query() -> String {
+    format!(
+        "WITH RECURSIVE days(day) AS ( \
+           Select date(?, '+1 day') \
+           UNION ALL Select date(day, '+1 day') From days where day < date(?) \
+         ) \
+         Select count(*) From days where day <= date(?) \
+         and day NOT IN (Select date From {})",
+        HOLIDAYS_TABLE
+    )
+}
+
+pub fn create_fine_query() -> String {
+    format!(
+        "INSERT INTO {} (loan_id, member_id, days_late, amount_cents, created_at) \
+         values (?, ?, ?, ?, {}) RETURNING id",
+        FINES_TABLE, NOW
+    )
+}
+
+pub fn get_member_fines_query() -> String {
+    format!(
+        "Select * From {} where member_id=? order by created_at desc, id desc",
+        FINES_TABLE
+    )
+}
+
+// Binds the member id four times.
+pub fn get_balance_query() -> String {
+    format!(
+        "WITH totals AS (Select ? as member_id, \
+           (Select coalesce(sum(amount_cents), 0) From {fines} where member_id=?) as fines_cents, \
+           (Select coalesce(sum(amount_cents), 0) From {payments} where member_id=? \
+             and kind='payment') as paid_cents, \
+           (Select coalesce(sum(amount_cents), 0) From {payments} where member_id=? \
+             and kind='waiver') as waived_cents) \
+         Select *, fines_cents - paid_cents - waived_cents as balance_cents From totals",
+        fines = FINES_TABLE,
+        payments = FINE_PAYMENTS_TABLE
+    )
+}
+
+pub fn create_payment_query() -> String {
+    format!(
+        "INSERT INTO {} (member_id, kind, amount_cents, note, created_at) \
+         values (?, ?, ?, ?, {}) RETURNING id",
+        FINE_PAYMENTS_TABLE, NOW
+    )
+}
+
+pub fn get_member_payments_query() -> String {
+    format!(
+        "Select * From {} where member_id=? order by created_at desc, id desc",
+        FINE_PAYMENTS_TABLE
+    )
+}
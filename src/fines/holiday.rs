@@ -0,0 +1,58 @@
+use super::super::constants::HOLIDAYS_TABLE;
+use super::super::dates::validate_date;
+use super::super::resources::resource::Resource;
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// A day the library is closed; it is not counted when charging fines.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Holiday {
+    pub id: Option<i64>,
+    pub date: Option<String>,
+    pub name: Option<String>,
+}
+
+impl Resource for Holiday {
+    const PATH: &'static str = "/holidays";
+    const TABLE: &'static str = HOLIDAYS_TABLE;
+    const FIELDS: &'static [&'static str] = &["date", "name"];
+    const FILTERABLE: &'static [&'static str] = &["date", "name"];
+    const SORTABLE: &'static [&'static str] = &["id", "date", "name"];
+    const REQUIRED: &'static [&'static str] = &["date"];
+    const UNIQUE: &'static [&'static str] = &["date"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "date" => Value::from(self.date.clone()),
+            "name" => Value::from(self.name.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "date" => self.date = value.into(),
+            "name" => self.name = value.into(),
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.date = self.date.as_deref().map(|d| d.trim().to_string());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_date("date", &self.date)
+    }
+}
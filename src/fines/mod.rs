@@ -0,0 +1,7 @@
+pub mod fine;
+pub mod fine_policy;
+#[allow(clippy::module_inception)]
+pub mod fines;
+pub mod fines_db;
+mod fines_queries;
+pub mod holiday;
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum HoldStatus {
+    // Queued until a copy comes back.
+    Waiting,
+    // A copy is set aside for the member until `expires_at`.
+    Ready,
+    Fulfilled,
+    Cancelled,
+    Expired,
+}
+
+impl HoldStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HoldStatus::Waiting => "waiting",
+            HoldStatus::Ready => "ready",
+            HoldStatus::Fulfilled => "fulfilled",
+            HoldStatus::Cancelled => "cancelled",
+            HoldStatus::Expired => "expired",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Hold {
+    pub id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub book_id: Option<i64>,
+    pub member_id: Option<i64>,
+    // The copy set aside for a ready hold; empty for books without copies.
+    #[serde(skip_deserializing)]
+    pub copy_id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub status: Option<HoldStatus>,
+    #[serde(skip_deserializing)]
+    pub created_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub ready_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub expires_at: Option<String>,
+    // Place in the queue of a waiting hold, starting at 1.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub position: Option<i64>,
+}
+
+impl Hold {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.member_id {
+            Some(_) => Ok(()),
+            None => Err("member_id is required".to_string()),
+        }
+    }
+}
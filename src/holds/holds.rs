@@ -0,0 +1,405 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::loans::loan::LoanPolicy;
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::hold::Hold;
+use super::holds_db::{self, Change};
+
+pub fn config_holds(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_book_holds)
+        .service(create_hold)
+        .service(get_hold)
+        .service(cancel_hold)
+        .service(get_member_holds);
+}
+
+#[get("/books/{book_id}/holds")]
+async fn get_book_holds(
+    book_id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let r = holds_db::get_book_holds(pool.get_ref(), book_id.into_inner(), policy.get_ref()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The hold is ready straight away when a copy is free.
+#[post("/books/{book_id}/holds")]
+async fn create_hold(
+    book_id: web::Path<i64>,
+    json: web::Json<Hold>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let hold = json.into_inner();
+    if let Err(e) = hold.validate() {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r =
+        holds_db::create_hold(pool.get_ref(), book_id.into_inner(), hold, policy.get_ref()).await;
+
+    match r {
+        Ok(Change::Done(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(Change::Duplicate(id)) => HttpResponse::Conflict().json(ConflictResponse {
+            message: "this member already holds the book".to_string(),
+            id,
+            location: format!("/holds/{}", id),
+        }),
+        Ok(Change::Refused(message)) => {
+            HttpResponse::Conflict().json(CustomError::message(message))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/holds/{id}")]
+async fn get_hold(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let r = holds_db::get_hold(pool.get_ref(), id.into_inner(), policy.get_ref()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// A cancelled ready hold passes its copy on to the next member in line.
+#[post("/holds/{id}/cancel")]
+async fn cancel_hold(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let r = holds_db::cancel_hold(pool.get_ref(), id.into_inner(), policy.get_ref()).await;
+
+    match r {
+        Ok(Change::Refused(message)) => {
+            HttpResponse::Conflict().json(CustomError::message(message))
+        }
+        Ok(_) => HttpResponse::Ok().json("Cancelled"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/members/{id}/holds")]
+async fn get_member_holds(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let r = holds_db::get_member_holds(pool.get_ref(), id.into_inner(), policy.get_ref()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::LoanPolicy;
+    use crate::test_utils::{self, book, copy, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test, web,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(LoanPolicy {
+                        loan_days: 14,
+                        max_renewals: 1,
+                        hold_days: 3,
+                    }))
+                    .configure(loans::loans::config_loans)
+                    .configure(copies::copies::config_copies)
+                    .configure(super::config_holds),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! post {
+        ($app:expr, $uri:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&$uri)
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    macro_rules! lend {
+        ($app:expr, $book_id:expr, $member_id:expr) => {{
+            let resp = post!(
+                $app,
+                "/loans",
+                json!({"book_id": $book_id, "member_id": $member_id})
+            );
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap()
+        }};
+    }
+
+    macro_rules! hold {
+        ($app:expr, $book_id:expr, $member_id:expr) => {{
+            let resp = post!(
+                $app,
+                format!("/books/{}/holds", $book_id),
+                json!({ "member_id": $member_id })
+            );
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap()
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_holds_are_served_in_order() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let c = copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let eve = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let loan = lend!(app, b, ann);
+        let bobs = hold!(app, b, bob);
+        let eves = hold!(app, b, eve);
+
+        let queue = get_json!(app, format!("/books/{}/holds", b));
+        assert_eq!(queue[0]["status"], "waiting");
+        assert_eq!(queue[0]["position"], 1);
+        assert_eq!(queue[1]["position"], 2);
+
+        post!(app, format!("/loans/{}/return", loan), json!({}));
+        let hold = get_json!(app, format!("/holds/{}", bobs));
+        assert_eq!(hold["status"], "ready");
+        assert_eq!(hold["copy_id"], c);
+        assert!(hold["expires_at"].as_str() > hold["ready_at"].as_str());
+        let hold = get_json!(app, format!("/holds/{}", eves));
+        assert_eq!(hold["position"], 1);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": eve}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let resp = post!(app, "/loans", json!({"copy_id": c, "member_id": ann}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("copy {} is held for another member", c)
+        );
+
+        lend!(app, b, bob);
+        let hold = get_json!(app, format!("/holds/{}", bobs));
+        assert_eq!(hold["status"], "fulfilled");
+    }
+
+    #[actix_web::test]
+    async fn test_hold_is_ready_when_a_copy_is_free() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let c = copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let id = hold!(app, b, ann);
+        let hold = get_json!(app, format!("/holds/{}", id));
+        assert_eq!(hold["status"], "ready");
+        assert_eq!(hold["copy_id"], c);
+        assert!(hold["position"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn test_expired_hold_passes_to_next_member() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let anns = hold!(app, b, ann);
+        let bobs = hold!(app, b, bob);
+        sqlx::query("UPDATE holds SET expires_at='2000-01-01T00:00:00Z' where id=?")
+            .bind(anns)
+            .execute(&conn_pool)
+            .await
+            .unwrap();
+
+        let holds = get_json!(app, format!("/members/{}/holds", bob));
+        assert_eq!(holds[0]["id"], bobs);
+        assert_eq!(holds[0]["status"], "ready");
+        let holds = get_json!(app, format!("/members/{}/holds", ann));
+        assert_eq!(holds[0]["status"], "expired");
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_hold() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let anns = hold!(app, b, ann);
+        let bobs = hold!(app, b, bob);
+
+        let resp = post!(app, format!("/holds/{}/cancel", anns), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let hold = get_json!(app, format!("/holds/{}", bobs));
+        assert_eq!(hold["status"], "ready");
+
+        let resp = post!(app, format!("/holds/{}/cancel", anns), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("hold {} is already cancelled", anns)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_create_hold_conflicts() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        lend!(app, b, ann);
+        let resp = post!(
+            app,
+            format!("/books/{}/holds", b),
+            json!({ "member_id": ann })
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let id = hold!(app, b, bob);
+        let resp = post!(
+            app,
+            format!("/books/{}/holds", b),
+            json!({ "member_id": bob })
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["location"], format!("/holds/{}", id));
+
+        let resp = post!(app, format!("/books/{}/holds", b), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_holds_on_books_without_copies() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let loan = lend!(app, b, ann);
+        let id = hold!(app, b, bob);
+        post!(app, format!("/loans/{}/return", loan), json!({}));
+
+        let hold = get_json!(app, format!("/holds/{}", id));
+        assert_eq!(hold["status"], "ready");
+        assert!(hold["copy_id"].is_null());
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        lend!(app, b, bob);
+    }
+
+    #[actix_web::test]
+    async fn test_hold_moves_off_a_copy_leaving_the_shelf() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let c1 = copy(b).create(&conn_pool).await;
+        let c2 = copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let id = hold!(app, b, ann);
+        let req = test::TestRequest::put()
+            .uri(&format!("/books/{}/copies/{}", b, c1))
+            .set_json(json!({"status": "lost"}))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            http::StatusCode::OK
+        );
+        let hold = get_json!(app, format!("/holds/{}", id));
+        assert_eq!(hold["status"], "ready");
+        assert_eq!(hold["copy_id"], c2);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/books/{}/copies/{}", b, c2))
+            .set_json(json!({"status": "in_repair"}))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            http::StatusCode::OK
+        );
+        let hold = get_json!(app, format!("/holds/{}", id));
+        assert_eq!(hold["status"], "waiting");
+        assert!(hold["copy_id"].is_null());
+        assert_eq!(hold["position"], 1);
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_checkout_skips_a_held_copy_that_is_not_available() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let c1 = copy(b).create(&conn_pool).await;
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        hold!(app, b, ann);
+        sqlx::query("UPDATE copies SET status='in_repair' where id=?")
+            .bind(c1)
+            .execute(&conn_pool)
+            .await
+            .unwrap();
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let c2 = copy(b).create(&conn_pool).await;
+        let loan = lend!(app, b, ann);
+        let loan = get_json!(app, format!("/loans/{}", loan));
+        assert_eq!(loan["copy_id"], c2);
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}/copies/{}", b, c1))
+            .to_request();
+        let copy: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(copy["status"], "in_repair");
+    }
+}
@@ -0,0 +1,269 @@
+use super::super::loans::loan::LoanPolicy;
+use super::hold::{Hold, HoldStatus};
+use super::holds_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Change {
+    Done(i64),
+    // The member already holds this book, with the given hold id.
+    Duplicate(i64),
+    // The change is not allowed, with the reason.
+    Refused(String),
+}
+
+async fn ensure(tx: &mut Transaction<'_, Sqlite>, query: &str, id: i64) -> Result<(), Error> {
+    sqlx::query(query).bind(id).fetch_one(tx).await?;
+
+    Ok(())
+}
+
+async fn fetch_hold(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<Hold, Error> {
+    sqlx::query_as::<_, Hold>(&holds_queries::get_hold_query())
+        .bind(id)
+        .fetch_one(tx)
+        .await
+}
+
+// Every transaction that lends, returns or queues starts here. Being a
+// write, it also takes SQLite's write lock up front, so concurrent returns
+// are applied one after the other instead of racing for the same copy.
+pub async fn expire_holds(
+    tx: &mut Transaction<'_, Sqlite>,
+    policy: &LoanPolicy,
+) -> Result<(), Error> {
+    let mut books: Vec<i64> = sqlx::query_scalar(&holds_queries::expire_holds_query())
+        .fetch_all(&mut *tx)
+        .await?;
+    books.sort_unstable();
+    books.dedup();
+    for book_id in books {
+        assign(tx, book_id, policy).await?;
+    }
+
+    Ok(())
+}
+
+// Sets free copies aside for the members at the head of the book's queue.
+pub async fn assign(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    policy: &LoanPolicy,
+) -> Result<(), Error> {
+    let copies: i64 = sqlx::query_scalar(&holds_queries::count_copies_query())
+        .bind(book_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    loop {
+        let next: Option<i64> =
+            sqlx::query_scalar(&holds_queries::get_next_waiting_hold_id_query())
+                .bind(book_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let hold_id = match next {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let copy_id: Option<i64> = if copies == 0 {
+            let free: bool = sqlx::query_scalar(&holds_queries::is_book_free_query())
+                .bind(book_id)
+                .bind(book_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if !free {
+                return Ok(());
+            }
+            None
+        } else {
+            match sqlx::query_scalar(&holds_queries::get_free_copy_query())
+                .bind(book_id)
+                .fetch_optional(&mut *tx)
+                .await?
+            {
+                Some(id) => Some(id),
+                None => return Ok(()),
+            }
+        };
+
+        sqlx::query(&holds_queries::ready_hold_query())
+            .bind(copy_id)
+            .bind(policy.hold_period())
+            .bind(hold_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+}
+
+pub async fn ready_hold(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    member_id: i64,
+) -> Result<Option<Hold>, Error> {
+    sqlx::query_as::<_, Hold>(&holds_queries::get_ready_hold_query())
+        .bind(book_id)
+        .bind(member_id)
+        .fetch_optional(tx)
+        .await
+}
+
+// The member a copy is set aside for, if any.
+pub async fn copy_holder(
+    tx: &mut Transaction<'_, Sqlite>,
+    copy_id: i64,
+) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar(&holds_queries::get_copy_holder_query())
+        .bind(copy_id)
+        .fetch_optional(tx)
+        .await
+}
+
+// For a copy that is no longer available. `assign` then sets another free
+// copy aside for the hold, if there is one.
+pub async fn release_copy(tx: &mut Transaction<'_, Sqlite>, copy_id: i64) -> Result<(), Error> {
+    sqlx::query(&holds_queries::release_copy_hold_query())
+        .bind(copy_id)
+        .execute(tx)
+        .await?;
+
+    Ok(())
+}
+
+// The member a book without copies is set aside for, if any.
+pub async fn book_holder(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar(&holds_queries::get_book_holder_query())
+        .bind(book_id)
+        .fetch_optional(tx)
+        .await
+}
+
+// Called once the member borrowed the book, whichever copy they got.
+pub async fn fulfil_holds(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    member_id: i64,
+) -> Result<(), Error> {
+    sqlx::query(&holds_queries::fulfil_holds_query())
+        .bind(book_id)
+        .bind(member_id)
+        .execute(tx)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_hold(pool: &Pool<Sqlite>, id: i64, policy: &LoanPolicy) -> Result<Hold, Error> {
+    let mut tx = pool.begin().await?;
+    expire_holds(&mut tx, policy).await?;
+    let hold = fetch_hold(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(hold)
+}
+
+pub async fn get_book_holds(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    policy: &LoanPolicy,
+) -> Result<Vec<Hold>, Error> {
+    let mut tx = pool.begin().await?;
+    expire_holds(&mut tx, policy).await?;
+    ensure(&mut tx, &holds_queries::get_book_id_query(), book_id).await?;
+    let holds = sqlx::query_as::<_, Hold>(&holds_queries::get_book_holds_query())
+        .bind(book_id)
+        .fetch_all(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(holds)
+}
+
+pub async fn get_member_holds(
+    pool: &Pool<Sqlite>,
+    member_id: i64,
+    policy: &LoanPolicy,
+) -> Result<Vec<Hold>, Error> {
+    let mut tx = pool.begin().await?;
+    expire_holds(&mut tx, policy).await?;
+    ensure(&mut tx, &holds_queries::get_member_id_query(), member_id).await?;
+    let holds = sqlx::query_as::<_, Hold>(&holds_queries::get_member_holds_query())
+        .bind(member_id)
+        .fetch_all(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(holds)
+}
+
+pub async fn create_hold(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    hold: Hold,
+    policy: &LoanPolicy,
+) -> Result<Change, Error> {
+    let member_id = hold.member_id.unwrap();
+    let mut tx = pool.begin().await?;
+    expire_holds(&mut tx, policy).await?;
+    ensure(&mut tx, &holds_queries::get_book_id_query(), book_id).await?;
+    ensure(&mut tx, &holds_queries::get_member_id_query(), member_id).await?;
+
+    let active: Option<i64> = sqlx::query_scalar(&holds_queries::get_active_hold_id_query())
+        .bind(book_id)
+        .bind(member_id)
+        .fetch_optional(&mut tx)
+        .await?;
+    if let Some(id) = active {
+        return Ok(Change::Duplicate(id));
+    }
+    let lent: Option<i64> = sqlx::query_scalar(&holds_queries::get_member_loan_id_query())
+        .bind(book_id)
+        .bind(member_id)
+        .fetch_optional(&mut tx)
+        .await?;
+    if lent.is_some() {
+        return Ok(Change::Refused(format!(
+            "member {} already has book {} on loan",
+            member_id, book_id
+        )));
+    }
+
+    let r = sqlx::query(&holds_queries::create_hold_query())
+        .bind(book_id)
+        .bind(member_id)
+        .execute(&mut tx)
+        .await?;
+    assign(&mut tx, book_id, policy).await?;
+    tx.commit().await?;
+
+    Ok(Change::Done(r.last_insert_rowid()))
+}
+
+pub async fn cancel_hold(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    policy: &LoanPolicy,
+) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    expire_holds(&mut tx, policy).await?;
+    let hold = fetch_hold(&mut tx, id).await?;
+    let r = sqlx::query(&holds_queries::cancel_hold_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    if r.rows_affected() == 0 {
+        return Ok(Change::Refused(format!(
+            "hold {} is already {}",
+            id,
+            hold.status.map_or("closed", |s| s.as_str())
+        )));
+    }
+    if hold.status == Some(HoldStatus::Ready) {
+        assign(&mut tx, hold.book_id.unwrap(), policy).await?;
+    }
+    tx.commit().await?;
+
+    Ok(Change::Done(id))
+}
@@ -0,0 +1,156 @@
+use super::super::constants::{BOOKS_TABLE, COPIES_TABLE, HOLDS_TABLE, LOANS_TABLE, MEMBERS_TABLE};
+
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+// Holds are served in id order.
+fn select_holds() -> String {
+    format!(
+        "Select h.*, CASE WHEN h.status = 'waiting' THEN \
+         (SELECT COUNT(*) From {holds} w where w.book_id = h.book_id \
+         and w.status = 'waiting' and w.id <= h.id) END as position From {holds} h",
+        holds = HOLDS_TABLE
+    )
+}
+
+pub fn get_hold_query() -> String {
+    format!("{} where h.id=?", select_holds())
+}
+
+pub fn get_book_holds_query() -> String {
+    format!(
+        "{} where h.book_id=? and h.status IN ('ready', 'waiting') \
+         order by h.status = 'waiting', h.id",
+        select_holds()
+    )
+}
+
+pub fn get_member_holds_query() -> String {
+    format!(
+        "{} where h.member_id=? order by h.created_at desc, h.id desc",
+        select_holds()
+    )
+}
+
+pub fn get_active_hold_id_query() -> String {
+    format!(
+        "Select id From {} where book_id=? and member_id=? and status IN ('waiting', 'ready')",
+        HOLDS_TABLE
+    )
+}
+
+pub fn get_ready_hold_query() -> String {
+    format!(
+        "{} where h.book_id=? and h.member_id=? and h.status = 'ready'",
+        select_holds()
+    )
+}
+
+pub fn get_copy_holder_query() -> String {
+    format!(
+        "Select member_id From {} where copy_id=? and status = 'ready'",
+        HOLDS_TABLE
+    )
+}
+
+pub fn get_book_holder_query() -> String {
+    format!(
+        "Select member_id From {} where book_id=? and copy_id IS NULL and status = 'ready'",
+        HOLDS_TABLE
+    )
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn get_member_id_query() -> String {
+    format!("Select id From {} where id=?", MEMBERS_TABLE)
+}
+
+pub fn get_member_loan_id_query() -> String {
+    format!(
+        "Select id From {} where book_id=? and member_id=? and returned_at IS NULL",
+        LOANS_TABLE
+    )
+}
+
+pub fn create_hold_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, member_id, status, created_at) \
+         values (?, ?, 'waiting', {}) RETURNING id",
+        HOLDS_TABLE, NOW
+    )
+}
+
+pub fn get_next_waiting_hold_id_query() -> String {
+    format!(
+        "Select id From {} where book_id=? and status = 'waiting' order by id limit 1",
+        HOLDS_TABLE
+    )
+}
+
+pub fn count_copies_query() -> String {
+    format!("Select count(*) From {} where book_id=?", COPIES_TABLE)
+}
+
+pub fn get_free_copy_query() -> String {
+    format!(
+        "Select id From {copies} where book_id=? and status = 'available' \
+         and id NOT IN (Select copy_id From {holds} where status = 'ready' \
+         and copy_id IS NOT NULL) order by id limit 1",
+        copies = COPIES_TABLE,
+        holds = HOLDS_TABLE
+    )
+}
+
+// A book without copies is free when it is neither lent nor set aside.
+pub fn is_book_free_query() -> String {
+    format!(
+        "Select NOT EXISTS (Select 1 From {loans} where book_id=? and copy_id IS NULL \
+         and returned_at IS NULL) and NOT EXISTS (Select 1 From {holds} where book_id=? \
+         and copy_id IS NULL and status = 'ready')",
+        loans = LOANS_TABLE,
+        holds = HOLDS_TABLE
+    )
+}
+
+// The expiry window is bound as an `strftime` modifier, e.g. `+3 days`.
+pub fn ready_hold_query() -> String {
+    format!(
+        "UPDATE {} SET status='ready', copy_id=?, ready_at={}, \
+         expires_at=strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?) where id=?",
+        HOLDS_TABLE, NOW
+    )
+}
+
+// Back in the queue, where its id keeps it ahead of later holds.
+pub fn release_copy_hold_query() -> String {
+    format!(
+        "UPDATE {} SET status='waiting', copy_id=NULL, ready_at=NULL, expires_at=NULL \
+         where copy_id=? and status = 'ready'",
+        HOLDS_TABLE
+    )
+}
+
+pub fn expire_holds_query() -> String {
+    format!(
+        "UPDATE {} SET status='expired' where status = 'ready' and expires_at < {} \
+         RETURNING book_id",
+        HOLDS_TABLE, NOW
+    )
+}
+
+pub fn fulfil_holds_query() -> String {
+    format!(
+        "UPDATE {} SET status='fulfilled' where book_id=? and member_id=? \
+         and status IN ('waiting', 'ready')",
+        HOLDS_TABLE
+    )
+}
+
+pub fn cancel_hold_query() -> String {
+    format!(
+        "UPDATE {} SET status='cancelled' where id=? and status IN ('waiting', 'ready')",
+        HOLDS_TABLE
+    )
+}
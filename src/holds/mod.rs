@@ -0,0 +1,5 @@
+pub mod hold;
+#[allow(clippy::module_inception)]
+pub mod holds;
+pub mod holds_db;
+mod holds_queries;
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Loan {
+    pub id: Option<i64>,
+    pub book_id: Option<i64>,
+    pub member_id: Option<i64>,
+    // Set when the book has copies; a book without copies is lent as a whole.
+    pub copy_id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub loaned_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub due_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub returned_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub renewals: Option<i64>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub overdue: bool,
+    // Charged when the loan came back late.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub fine_cents: Option<i64>,
+}
+
+impl Loan {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.book_id.is_none() && self.copy_id.is_none() {
+            return Err("book_id or copy_id is required".to_string());
+        }
+        if self.member_id.is_none() {
+            return Err("member_id is required".to_string());
+        }
+        Ok(())
+    }
+}
+
+// How long a loan runs, how many times it may be extended and how long a
+// copy is set aside for a hold; read from the environment at startup, see
+// `env_var::get_loan_policy`.
+#[derive(Debug, Clone, Copy)]
+pub struct LoanPolicy {
+    pub loan_days: i64,
+    pub max_renewals: i64,
+    pub hold_days: i64,
+}
+
+impl LoanPolicy {
+    // An `strftime` modifier that moves a date by one loan period.
+    pub fn period(&self) -> String {
+        format!("+{} days", self.loan_days)
+    }
+
+    pub fn hold_period(&self) -> String {
+        format!("+{} days", self.hold_days)
+    }
+}
@@ -0,0 +1,322 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::loan::{Loan, LoanPolicy};
+use super::loans_db::{self, Checkout, Outcome};
+
+// `/loans/overdue` has to be registered before `/loans/{id}`.
+pub fn config_loans(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_overdue_loans)
+        .service(get_loan)
+        .service(create_loan)
+        .service(return_loan)
+        .service(renew_loan)
+        .service(get_member_loans);
+}
+
+fn outcome(r: Result<Outcome, sqlx::Error>) -> HttpResponse {
+    match r {
+        Ok(Outcome::Done(v)) => HttpResponse::Ok().json(v),
+        Ok(Outcome::Refused(message)) => {
+            HttpResponse::Conflict().json(CustomError::message(message))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/loans/overdue")]
+async fn get_overdue_loans(pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = loans_db::get_overdue_loans(pool.get_ref()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/loans/{id}")]
+async fn get_loan(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = loans_db::get_loan(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[post("/loans")]
+async fn create_loan(
+    json: web::Json<Loan>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    let loan = json.into_inner();
+    if let Err(e) = loan.validate() {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = loans_db::create_loan(pool.get_ref(), loan, policy.get_ref()).await;
+
+    match r {
+        Ok(Checkout::Loan(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(Checkout::OnLoan(id)) => HttpResponse::Conflict().json(ConflictResponse {
+            message: "this book is already on loan".to_string(),
+            id,
+            location: format!("/loans/{}", id),
+        }),
+        Ok(Checkout::Refused(message)) => {
+            HttpResponse::Conflict().json(CustomError::message(message))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[post("/loans/{id}/return")]
+async fn return_loan(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    outcome(loans_db::return_loan(pool.get_ref(), id.into_inner(), policy.get_ref()).await)
+}
+
+#[post("/loans/{id}/renew")]
+async fn renew_loan(
+    id: web::Path<i64>,
+    pool: web::Data<Pool<Sqlite>>,
+    policy: web::Data<LoanPolicy>,
+) -> impl Responder {
+    outcome(loans_db::renew_loan(pool.get_ref(), id.into_inner(), policy.get_ref()).await)
+}
+
+#[get("/members/{id}/loans")]
+async fn get_member_loans(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = loans_db::get_member_loans(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::LoanPolicy;
+    use crate::test_utils::{self, book, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test, web,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(LoanPolicy {
+                        loan_days: 14,
+                        max_renewals: 1,
+                        hold_days: 3,
+                    }))
+                    .configure(super::config_loans),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! post {
+        ($app:expr, $uri:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&$uri)
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    async fn make_overdue(pool: &sqlx::Pool<sqlx::Sqlite>, id: &Value) {
+        sqlx::query("UPDATE loans SET due_at='2000-01-01T00:00:00Z' where id=?")
+            .bind(id.as_i64().unwrap())
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_checkout_and_return() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+
+        let loan = get_json!(app, format!("/loans/{}", created["id"]));
+        assert_eq!(loan["book_id"], b);
+        assert_eq!(loan["member_id"], m);
+        assert_eq!(loan["renewals"], 0);
+        assert_eq!(loan["overdue"], false);
+        assert!(loan["due_at"].as_str() > loan["loaned_at"].as_str());
+        assert!(loan["returned_at"].is_null());
+
+        let resp = post!(app, format!("/loans/{}/return", created["id"]), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let loan: Value = test::read_body_json(resp).await;
+        assert!(loan["returned_at"].is_string());
+
+        let resp = post!(app, format!("/loans/{}/return", created["id"]), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn test_checkout_book_on_loan() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        let created: Value = test::read_body_json(resp).await;
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": bob}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], created["id"]);
+        assert_eq!(body["location"], format!("/loans/{}", created["id"]));
+    }
+
+    #[actix_web::test]
+    async fn test_checkout_bad_request() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b}));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "member_id is required");
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": 0}));
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn test_renew_loan() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        let created: Value = test::read_body_json(resp).await;
+        let before = get_json!(app, format!("/loans/{}", created["id"]));
+
+        let resp = post!(app, format!("/loans/{}/renew", created["id"]), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let renewed: Value = test::read_body_json(resp).await;
+        assert_eq!(renewed["renewals"], 1);
+        assert!(renewed["due_at"].as_str() > before["due_at"].as_str());
+
+        let resp = post!(app, format!("/loans/{}/renew", created["id"]), json!({}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!("loan {} has reached the limit of 1 renewals", created["id"])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_renew_overdue_loan() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+        let created: Value = test::read_body_json(resp).await;
+        make_overdue(&conn_pool, &created["id"]).await;
+
+        let resp = post!(app, format!("/loans/{}/renew", created["id"]), json!({}));
+        let renewed: Value = test::read_body_json(resp).await;
+        assert_eq!(renewed["overdue"], false);
+        assert!(renewed["due_at"].as_str() > renewed["loaned_at"].as_str());
+    }
+
+    #[actix_web::test]
+    async fn test_get_overdue_loans() {
+        let conn_pool = test_pool().await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let b = book().create(&conn_pool).await.id.unwrap();
+            let resp = post!(app, "/loans", json!({"book_id": b, "member_id": m}));
+            let created: Value = test::read_body_json(resp).await;
+            ids.push(created["id"].clone());
+        }
+        make_overdue(&conn_pool, &ids[0]).await;
+        make_overdue(&conn_pool, &ids[1]).await;
+        post!(app, format!("/loans/{}/return", ids[1]), json!({}));
+
+        let req = test::TestRequest::get().uri("/loans/overdue").to_request();
+        let overdue: Value = test::call_and_read_body_json(&app, req).await;
+        let overdue = overdue.as_array().unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0]["id"], ids[0]);
+        assert_eq!(overdue[0]["overdue"], true);
+    }
+
+    #[actix_web::test]
+    async fn test_get_member_loans() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let ann = member().create(&conn_pool).await.id.unwrap();
+        let bob = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        let first: Value = test::read_body_json(resp).await;
+        post!(app, format!("/loans/{}/return", first["id"]), json!({}));
+        let resp = post!(app, "/loans", json!({"book_id": b, "member_id": ann}));
+        let second: Value = test::read_body_json(resp).await;
+
+        let history = get_json!(app, format!("/members/{}/loans", ann));
+        let ids: Vec<&Value> = history
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| &l["id"])
+            .collect();
+        assert_eq!(ids, vec![&second["id"], &first["id"]]);
+
+        let history = get_json!(app, format!("/members/{}/loans", bob));
+        assert!(history.as_array().unwrap().is_empty());
+
+        let req = test::TestRequest::get()
+            .uri("/members/0/loans")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,244 @@
+use super::super::copies::copies_db::{self, Lend};
+use super::super::copies::copy::Status;
+use super::super::fines::fines_db;
+use super::super::holds::holds_db;
+use super::loan::{Loan, LoanPolicy};
+use super::loans_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Checkout {
+    Loan(i64),
+    // The book or copy is still out on the given loan.
+    OnLoan(i64),
+    // No copy can be lent, or the member may not borrow, with the reason.
+    Refused(String),
+}
+
+pub enum Outcome {
+    Done(Loan),
+    // The loan cannot be returned or renewed, with the reason.
+    Refused(String),
+}
+
+async fn ensure(tx: &mut Transaction<'_, Sqlite>, query: &str, id: i64) -> Result<(), Error> {
+    sqlx::query(query).bind(id).fetch_one(tx).await?;
+
+    Ok(())
+}
+
+async fn fetch_loan(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<Loan, Error> {
+    sqlx::query_as::<_, Loan>(&loans_queries::get_loan_query())
+        .bind(id)
+        .fetch_one(tx)
+        .await
+}
+
+pub async fn get_loan(pool: &Pool<Sqlite>, id: i64) -> Result<Loan, Error> {
+    sqlx::query_as::<_, Loan>(&loans_queries::get_loan_query())
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_overdue_loans(pool: &Pool<Sqlite>) -> Result<Vec<Loan>, Error> {
+    sqlx::query_as::<_, Loan>(&loans_queries::get_overdue_loans_query())
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_member_loans(pool: &Pool<Sqlite>, member_id: i64) -> Result<Vec<Loan>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure(&mut tx, &loans_queries::get_member_id_query(), member_id).await?;
+
+    sqlx::query_as::<_, Loan>(&loans_queries::get_member_loans_query())
+        .bind(member_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+pub async fn create_loan(
+    pool: &Pool<Sqlite>,
+    loan: Loan,
+    policy: &LoanPolicy,
+) -> Result<Checkout, Error> {
+    let member_id = loan.member_id.unwrap();
+    let mut tx = pool.begin().await?;
+    holds_db::expire_holds(&mut tx, policy).await?;
+    ensure(&mut tx, &loans_queries::get_member_id_query(), member_id).await?;
+    let balance = fines_db::balance(&mut tx, member_id).await?;
+    if balance.blocked {
+        return Ok(Checkout::Refused(format!(
+            "member {} owes {} cents, more than the limit of {}",
+            member_id,
+            balance.balance_cents,
+            balance.block_threshold_cents.unwrap_or(0)
+        )));
+    }
+
+    let (book_id, copy_id) = match loan.copy_id {
+        Some(copy_id) => {
+            let copy = copies_db::get_copy_by_id(&mut tx, copy_id).await?;
+            let book_id = copy.book_id.unwrap();
+            if let Some(other) = loan.book_id.filter(|b| *b != book_id) {
+                return Ok(Checkout::Refused(format!(
+                    "copy {} is not a copy of book {}",
+                    copy_id, other
+                )));
+            }
+            match copy.status {
+                Some(Status::Available) => {
+                    let holder = holds_db::copy_holder(&mut tx, copy_id).await?;
+                    if matches!(holder, Some(h) if h != member_id) {
+                        return Ok(Checkout::Refused(format!(
+                            "copy {} is held for another member",
+                            copy_id
+                        )));
+                    }
+                    (book_id, Some(copy_id))
+                }
+                Some(Status::OnLoan) => {
+                    let active: i64 =
+                        sqlx::query_scalar(&loans_queries::get_active_copy_loan_id_query())
+                            .bind(copy_id)
+                            .fetch_one(&mut tx)
+                            .await?;
+                    return Ok(Checkout::OnLoan(active));
+                }
+                status => {
+                    return Ok(Checkout::Refused(format!(
+                        "copy {} is {}",
+                        copy_id,
+                        status.map_or("unavailable", |s| s.as_str())
+                    )))
+                }
+            }
+        }
+        None => {
+            let book_id = loan.book_id.unwrap();
+            // A member whose hold is ready gets the copy set aside for them,
+            // unless it has left the shelf since.
+            let held = match holds_db::ready_hold(&mut tx, book_id, member_id).await? {
+                Some(hold) => match hold.copy_id {
+                    Some(copy_id) => {
+                        let copy = copies_db::get_copy_by_id(&mut tx, copy_id).await?;
+                        (copy.status == Some(Status::Available)).then_some(Some(copy_id))
+                    }
+                    None => Some(None),
+                },
+                None => None,
+            };
+            if let Some(copy_id) = held {
+                (book_id, copy_id)
+            } else {
+                match copies_db::copy_to_lend(&mut tx, book_id).await? {
+                    Lend::Copy(copy_id) => (book_id, Some(copy_id)),
+                    Lend::Unavailable(message) => return Ok(Checkout::Refused(message)),
+                    Lend::Book => {
+                        let active: Option<i64> =
+                            sqlx::query_scalar(&loans_queries::get_active_loan_id_query())
+                                .bind(book_id)
+                                .fetch_optional(&mut tx)
+                                .await?;
+                        if let Some(id) = active {
+                            return Ok(Checkout::OnLoan(id));
+                        }
+                        if holds_db::book_holder(&mut tx, book_id).await?.is_some() {
+                            return Ok(Checkout::Refused(format!(
+                                "book {} is held for another member",
+                                book_id
+                            )));
+                        }
+                        (book_id, None)
+                    }
+                }
+            }
+        }
+    };
+
+    let r = sqlx::query(&loans_queries::create_loan_query())
+        .bind(book_id)
+        .bind(copy_id)
+        .bind(member_id)
+        .bind(policy.period())
+        .execute(&mut tx)
+        .await?;
+    if let Some(copy_id) = copy_id {
+        copies_db::set_status(&mut tx, copy_id, Status::OnLoan).await?;
+    }
+    holds_db::fulfil_holds(&mut tx, book_id, member_id).await?;
+    tx.commit().await?;
+
+    Ok(Checkout::Loan(r.last_insert_rowid()))
+}
+
+// The returned copy goes straight to the next hold on the book, if any, and
+// late returns are fined.
+pub async fn return_loan(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    policy: &LoanPolicy,
+) -> Result<Outcome, Error> {
+    let mut tx = pool.begin().await?;
+    holds_db::expire_holds(&mut tx, policy).await?;
+    let r = sqlx::query(&loans_queries::return_loan_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    let loan = fetch_loan(&mut tx, id).await?;
+    if r.rows_affected() == 0 {
+        return Ok(Outcome::Refused(format!(
+            "loan {} was already returned",
+            id
+        )));
+    }
+    if let Some(copy_id) = loan.copy_id {
+        copies_db::set_status(&mut tx, copy_id, Status::Available).await?;
+    }
+    holds_db::assign(&mut tx, loan.book_id.unwrap(), policy).await?;
+    fines_db::charge_fine(&mut tx, &loan).await?;
+    let loan = fetch_loan(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(Outcome::Done(loan))
+}
+
+pub async fn renew_loan(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    policy: &LoanPolicy,
+) -> Result<Outcome, Error> {
+    let mut tx = pool.begin().await?;
+    let loan = fetch_loan(&mut tx, id).await?;
+    if loan.returned_at.is_some() {
+        return Ok(Outcome::Refused(format!(
+            "loan {} was already returned",
+            id
+        )));
+    }
+    if loan.renewals.unwrap_or(0) >= policy.max_renewals {
+        return Ok(Outcome::Refused(format!(
+            "loan {} has reached the limit of {} renewals",
+            id, policy.max_renewals
+        )));
+    }
+    let waiting: i64 = sqlx::query_scalar(&loans_queries::count_waiting_holds_query())
+        .bind(loan.book_id)
+        .fetch_one(&mut tx)
+        .await?;
+    if waiting > 0 {
+        return Ok(Outcome::Refused(format!(
+            "loan {} cannot be renewed, other members are waiting for the book",
+            id
+        )));
+    }
+
+    sqlx::query(&loans_queries::renew_loan_query())
+        .bind(policy.period())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    let loan = fetch_loan(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(Outcome::Done(loan))
+}
@@ -0,0 +1,83 @@
+use super::super::constants::{FINES_TABLE, HOLDS_TABLE, LOANS_TABLE, MEMBERS_TABLE};
+
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+fn select_loans() -> String {
+    format!(
+        "Select *, (returned_at IS NULL and due_at < {now}) as overdue, \
+         (Select amount_cents From {fines} where loan_id = {loans}.id) as fine_cents From {loans}",
+        now = NOW,
+        fines = FINES_TABLE,
+        loans = LOANS_TABLE
+    )
+}
+
+pub fn get_loan_query() -> String {
+    format!("{} where id=?", select_loans())
+}
+
+pub fn get_overdue_loans_query() -> String {
+    format!(
+        "{} where returned_at IS NULL and due_at < {} order by due_at, id",
+        select_loans(),
+        NOW
+    )
+}
+
+pub fn get_member_loans_query() -> String {
+    format!(
+        "{} where member_id=? order by loaned_at desc, id desc",
+        select_loans()
+    )
+}
+
+pub fn get_active_loan_id_query() -> String {
+    format!(
+        "Select id From {} where book_id=? and copy_id IS NULL and returned_at IS NULL",
+        LOANS_TABLE
+    )
+}
+
+pub fn get_active_copy_loan_id_query() -> String {
+    format!(
+        "Select id From {} where copy_id=? and returned_at IS NULL",
+        LOANS_TABLE
+    )
+}
+
+pub fn count_waiting_holds_query() -> String {
+    format!(
+        "Select count(*) From {} where book_id=? and status='waiting'",
+        HOLDS_TABLE
+    )
+}
+
+pub fn get_member_id_query() -> String {
+    format!("Select id From {} where id=?", MEMBERS_TABLE)
+}
+
+// The loan period is bound as an `strftime` modifier, e.g. `+14 days`.
+pub fn create_loan_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, copy_id, member_id, loaned_at, due_at) \
+         values (?, ?, ?, {now}, strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?)) RETURNING id",
+        LOANS_TABLE,
+        now = NOW
+    )
+}
+
+pub fn return_loan_query() -> String {
+    format!(
+        "UPDATE {} SET returned_at={} where id=? and returned_at IS NULL",
+        LOANS_TABLE, NOW
+    )
+}
+
+// Overdue loans are extended from today rather than from their due date.
+pub fn renew_loan_query() -> String {
+    format!(
+        "UPDATE {} SET due_at=strftime('%Y-%m-%dT%H:%M:%SZ', max(due_at, {}), ?), \
+         renewals=renewals + 1 where id=? and returned_at IS NULL",
+        LOANS_TABLE, NOW
+    )
+}
@@ -0,0 +1,5 @@
+pub mod loan;
+#[allow(clippy::module_inception)]
+pub mod loans;
+mod loans_db;
+mod loans_queries;
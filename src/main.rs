@@ -1,24 +1,210 @@
 use actix_web::{web, App, HttpServer};
 
+use repositories::Repositories;
+
 mod authors;
+mod autocomplete;
 mod books;
 mod constants;
+mod contributors;
+mod copies;
+mod dates;
 mod db;
+mod duplicates;
 mod env_var;
+mod fines;
+mod holds;
+mod loans;
+mod members;
+mod metadata;
+mod publishers;
+mod repositories;
+mod resources;
 mod responses;
+mod reviews;
+mod search;
+mod series;
+mod similar;
+mod similarity;
+mod stats;
+mod subjects;
+mod tags;
+#[cfg(test)]
+mod test_utils;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let conn_pool = db::establish_connection().await.unwrap();
     let addr = env_var::get_addr();
+    let storage = env_var::get_storage();
+    let repositories = repositories(&storage).await;
+    let loan_policy = env_var::get_loan_policy();
+    // Shared by every worker, so the index is built once.
+    let autocomplete = web::Data::new(autocomplete::autocomplete::Autocomplete::default());
+    let stats_cache = web::Data::new(stats::report::Cache::new(env_var::get_stats_ttl()));
+    let search = web::Data::new(search::search::Search::new(search_backend(
+        &env_var::get_search(),
+        &storage,
+        &repositories,
+    )));
+
+    let recommender = similar::similar::Recommender::new(env_var::get_similar_weights());
+    let pool = repositories.pool.clone();
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
+        let repositories = repositories.clone();
         App::new()
-            .app_data(web::Data::new(conn_pool.clone()))
+            .configure(move |cfg| repositories.config(cfg))
+            .app_data(web::Data::new(loan_policy))
+            .app_data(autocomplete.clone())
+            .app_data(search.clone())
+            .app_data(stats_cache.clone())
             .configure(books::books::config_books)
             .configure(authors::authors::config_authors)
+            .configure(publishers::publishers::config_publishers)
+            .configure(tags::tags::config_tags)
+            .configure(reviews::reviews::config_reviews)
+            .configure(members::members::config_members)
+            .configure(loans::loans::config_loans)
+            .configure(copies::copies::config_copies)
+            .configure(holds::holds::config_holds)
+            .configure(fines::fines::config_fines)
+            .configure(contributors::contributors::config_contributors)
+            .configure(series::series::config_series)
+            .configure(subjects::subjects::config_subjects)
+            .configure(duplicates::duplicates::config_duplicates)
+            .configure(autocomplete::autocomplete::config_autocomplete)
+            .configure(search::search::config_search)
+            .configure(similar::similar::config_similar)
+            .configure(stats::stats::config_stats)
+            .configure(metadata::metadata::config_metadata)
     })
     .bind(addr)?
-    .run()
-    .await
+    .run();
+
+    // Scores similar books in the background. Should refreshes keep failing,
+    // the server stops and exits with the last error.
+    let (failed, mut refresh_error) = tokio::sync::oneshot::channel();
+    let handle = server.handle();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = recommender.run(pool).await {
+            let _ = failed.send(e);
+            handle.stop(true).await;
+        }
+    });
+
+    server.await?;
+    match refresh_error.try_recv() {
+        Ok(e) => Err(std::io::Error::other(format!(
+            "could not refresh similar books: {}",
+            e
+        ))),
+        Err(_) => Ok(()),
+    }
+}
+
+async fn repositories(storage: &str) -> Repositories {
+    match storage {
+        constants::STORAGE_SQLITE => {
+            let conn_pool = db::establish_connection().await.unwrap();
+            Repositories::sqlite(conn_pool)
+        }
+        // A private SQLite database that goes away with the process, so that
+        // relations, search and statistics see the same rows as the models.
+        constants::STORAGE_MEMORY => {
+            let conn_pool = db::establish_memory_connection().await.unwrap();
+            Repositories::sqlite(conn_pool)
+        }
+        _ => panic!(
+            "${} must be one of {}, {} (got {})",
+            constants::STORAGE,
+            constants::STORAGE_SQLITE,
+            constants::STORAGE_MEMORY,
+            storage
+        ),
+    }
+}
+
+fn search_backend(
+    search: &str,
+    storage: &str,
+    repositories: &Repositories,
+) -> Box<dyn search::backend::Backend> {
+    let boosts = env_var::get_search_boosts();
+    match search {
+        constants::SEARCH_SQLITE => Box::new(search::sqlite_backend::SqliteBackend::new(
+            repositories.pool.clone(),
+            boosts,
+        )),
+        // An in-memory database gets an index that goes away with it.
+        constants::SEARCH_TANTIVY if storage == constants::STORAGE_MEMORY => {
+            Box::new(search::tantivy_backend::TantivyBackend::in_memory(boosts).unwrap())
+        }
+        constants::SEARCH_TANTIVY => Box::new(
+            search::tantivy_backend::TantivyBackend::open(
+                std::path::Path::new(constants::SEARCH_INDEX_DIR),
+                boosts,
+            )
+            .unwrap(),
+        ),
+        _ => panic!(
+            "${} must be one of {}, {} (got {})",
+            constants::SEARCH,
+            constants::SEARCH_SQLITE,
+            constants::SEARCH_TANTIVY,
+            search
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http, test};
+    use serde_json::{json, Value};
+
+    // Every path reads the same in-memory database as the models.
+    #[actix_web::test]
+    async fn test_memory_storage() {
+        let repositories = repositories(constants::STORAGE_MEMORY).await;
+        let app = test::init_service(
+            App::new()
+                .configure(move |cfg| repositories.config(cfg))
+                .configure(books::books::config_books)
+                .configure(tags::tags::config_tags)
+                .configure(metadata::metadata::config_metadata),
+        )
+        .await;
+
+        let field = json!({"resource": "books", "name": "shelf", "type": "text"});
+        let req = test::TestRequest::post()
+            .uri("/metadata/fields")
+            .set_json(field)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        for (title, shelf) in [("Dune", "A3"), ("Emma", "B1")] {
+            let book = json!({"title": title, "author": "author", "metadata": {"shelf": shelf}});
+            let req = test::TestRequest::post()
+                .uri("/books")
+                .set_json(book)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+        }
+        let req = test::TestRequest::put()
+            .uri("/books/1/tags")
+            .set_json(json!(["scifi"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        for uri in ["/books?tags=scifi", "/books?meta.shelf=A3"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK, "{}", uri);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body.as_array().unwrap().len(), 1, "{}", uri);
+            assert_eq!(body[0]["title"], "Dune", "{}", uri);
+        }
+    }
 }
@@ -0,0 +1,66 @@
+use super::super::constants::MEMBERS_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Member {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    // Picks the fine policy; empty means `constants::DEFAULT_MEMBER_TYPE`.
+    pub member_type: Option<String>,
+}
+
+impl Resource for Member {
+    const PATH: &'static str = "/members";
+    const TABLE: &'static str = MEMBERS_TABLE;
+    const FIELDS: &'static [&'static str] = &["name", "email", "member_type"];
+    const FILTERABLE: &'static [&'static str] = &["name", "email", "member_type"];
+    const SORTABLE: &'static [&'static str] = &["id", "name"];
+    const REQUIRED: &'static [&'static str] = &["name"];
+    const UNIQUE: &'static [&'static str] = &["email"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "name" => Value::from(self.name.clone()),
+            "email" => Value::from(self.email.clone()),
+            "member_type" => Value::from(self.member_type.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "name" => self.name = value.into(),
+            "email" => self.email = value.into(),
+            "member_type" => self.member_type = value.into(),
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.email = self.email.as_deref().map(|e| e.trim().to_lowercase());
+        self.member_type = self.member_type.as_deref().map(|t| t.trim().to_lowercase());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("name", &self.name)?;
+        validate_not_blank("member_type", &self.member_type)?;
+        match &self.email {
+            Some(e) if !e.contains('@') => Err("email must be an email address".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
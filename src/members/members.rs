@@ -0,0 +1,91 @@
+use actix_web::web;
+
+use super::super::resources::resources;
+use super::member::Member;
+
+pub fn config_members(cfg: &mut web::ServiceConfig) {
+    resources::config::<Member>(cfg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::members::member::Member;
+    use crate::test_utils::{self, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {
+            test::init_service(
+                App::new()
+                    .configure(super::config_members)
+                    .app_data(test_utils::repository::<Member>(&$pool)),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn test_create_member() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .uri("/members")
+            .set_json(json!({"name": "Ann", "email": " Ann@Example.org "}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/members/{}", body["id"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["email"], "ann@example.org");
+    }
+
+    #[actix_web::test]
+    async fn test_create_member_duplicate_email() {
+        let conn_pool = test_pool().await;
+        let m = member().email("ann@example.org").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .uri("/members")
+            .set_json(json!({"name": "Ann", "email": "ANN@example.org"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["location"], format!("/members/{}", m.id.unwrap()));
+    }
+
+    #[actix_web::test]
+    async fn test_create_member_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for (body, message) in [
+            (json!({"email": "ann@example.org"}), "name is required"),
+            (
+                json!({"name": "Ann", "email": "ann"}),
+                "email must be an email address",
+            ),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/members")
+                .set_json(body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+}
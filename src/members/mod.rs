@@ -0,0 +1,3 @@
+pub mod member;
+#[allow(clippy::module_inception)]
+pub mod members;
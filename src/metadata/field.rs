@@ -0,0 +1,247 @@
+use super::super::constants::METADATA_FIELDS_TABLE;
+use super::super::dates::validate_date;
+use super::super::resources::resource::Resource;
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// The models that have a `metadata` column, by table.
+pub const RESOURCES: &[&str] = &["books", "authors"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    // A `YYYY-MM-DD` calendar date.
+    Date,
+}
+
+impl FieldType {
+    pub fn parse(field_type: &str) -> Result<FieldType, String> {
+        match field_type {
+            "text" => Ok(FieldType::Text),
+            "integer" => Ok(FieldType::Integer),
+            "number" => Ok(FieldType::Number),
+            "boolean" => Ok(FieldType::Boolean),
+            "date" => Ok(FieldType::Date),
+            _ => Err(format!(
+                "type must be one of text, integer, number, boolean, date (got {})",
+                field_type
+            )),
+        }
+    }
+
+    // Checks a value stored under `name`. Null leaves the field unset.
+    pub fn check(&self, name: &str, value: &serde_json::Value) -> Result<(), String> {
+        let ok = match (self, value) {
+            (_, serde_json::Value::Null) => true,
+            (FieldType::Text, v) => v.is_string(),
+            (FieldType::Integer, v) => v.is_i64(),
+            (FieldType::Number, v) => v.is_number(),
+            (FieldType::Boolean, v) => v.is_boolean(),
+            (FieldType::Date, serde_json::Value::String(d)) => {
+                return validate_date(&format!("meta.{}", name), &Some(d.clone()))
+            }
+            (FieldType::Date, _) => false,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!(
+                "meta.{} must be {} (got {})",
+                name,
+                self.described(),
+                value
+            ))
+        }
+    }
+
+    // A filter value, bound the way `json_extract` returns the stored ones:
+    // booleans as 1 and 0, dates as text.
+    pub fn bind(&self, name: &str, value: &str) -> Result<Value, String> {
+        let err = || format!("meta.{} must be {} (got {})", name, self.described(), value);
+        match self {
+            FieldType::Text => Ok(Value::Text(value.to_string())),
+            FieldType::Integer => value.parse().map(Value::Integer).map_err(|_| err()),
+            FieldType::Number => value.parse().map(Value::Real).map_err(|_| err()),
+            FieldType::Boolean => match value {
+                "true" => Ok(Value::Integer(1)),
+                "false" => Ok(Value::Integer(0)),
+                _ => Err(err()),
+            },
+            FieldType::Date => {
+                validate_date(&format!("meta.{}", name), &Some(value.to_string()))?;
+                Ok(Value::Text(value.to_string()))
+            }
+        }
+    }
+
+    fn described(&self) -> &'static str {
+        match self {
+            FieldType::Text => "a string",
+            FieldType::Integer => "an integer",
+            FieldType::Number => "a number",
+            FieldType::Boolean => "true or false",
+            FieldType::Date => "a date like 2024-12-25",
+        }
+    }
+}
+
+// A custom field departments may set in the `metadata` of books or authors.
+// `indexed` fields get an expression index, for those filtered on often.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct MetadataField {
+    pub id: Option<i64>,
+    pub resource: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub field_type: Option<String>,
+    pub indexed: Option<bool>,
+    pub description: Option<String>,
+}
+
+// Names end up in JSON paths and index names, so they are kept to plain
+// lowercase identifiers.
+fn validate_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && name.len() <= 64;
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "name must be lowercase letters, digits and underscores, \
+             starting with a letter (got {})",
+            name
+        ))
+    }
+}
+
+impl Resource for MetadataField {
+    const PATH: &'static str = "/metadata/fields";
+    const TABLE: &'static str = METADATA_FIELDS_TABLE;
+    const FIELDS: &'static [&'static str] = &["resource", "name", "type", "indexed", "description"];
+    const FILTERABLE: &'static [&'static str] = &["resource", "name"];
+    const SORTABLE: &'static [&'static str] = &["id", "resource", "name"];
+    const REQUIRED: &'static [&'static str] = &["resource", "name", "type"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "resource" => Value::from(self.resource.clone()),
+            "name" => Value::from(self.name.clone()),
+            "type" => Value::from(self.field_type.clone()),
+            "indexed" => Value::from(self.indexed.map(i64::from)),
+            "description" => Value::from(self.description.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "resource" => self.resource = value.into(),
+            "name" => self.name = value.into(),
+            "type" => self.field_type = value.into(),
+            "indexed" => self.indexed = Option::<i64>::from(value).map(|i| i != 0),
+            "description" => self.description = value.into(),
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.name = self.name.as_deref().map(|n| n.trim().to_string());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(r) = &self.resource {
+            if !RESOURCES.contains(&r.as_str()) {
+                return Err(format!(
+                    "resource must be one of {} (got {})",
+                    RESOURCES.join(", "),
+                    r
+                ));
+            }
+        }
+        if let Some(n) = &self.name {
+            validate_name(n)?;
+        }
+        if let Some(t) = &self.field_type {
+            FieldType::parse(t)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_check() {
+        assert!(FieldType::Text.check("shelf", &json!("A3")).is_ok());
+        assert!(FieldType::Integer.check("year", &json!(2020)).is_ok());
+        assert!(FieldType::Number.check("price", &json!(9.5)).is_ok());
+        assert!(FieldType::Boolean.check("signed", &json!(true)).is_ok());
+        assert!(FieldType::Date
+            .check("acquired", &json!("2024-02-29"))
+            .is_ok());
+        assert!(FieldType::Integer.check("year", &json!(null)).is_ok());
+
+        assert_eq!(
+            FieldType::Integer.check("year", &json!("2020")),
+            Err("meta.year must be an integer (got \"2020\")".to_string())
+        );
+        assert_eq!(
+            FieldType::Integer.check("year", &json!(20.5)),
+            Err("meta.year must be an integer (got 20.5)".to_string())
+        );
+        assert!(FieldType::Date
+            .check("acquired", &json!("2023-02-29"))
+            .is_err());
+        assert!(FieldType::Text.check("shelf", &json!(["A3"])).is_err());
+    }
+
+    #[test]
+    fn test_bind() {
+        assert_eq!(
+            FieldType::Integer.bind("year", "2020"),
+            Ok(Value::Integer(2020))
+        );
+        assert_eq!(
+            FieldType::Boolean.bind("signed", "false"),
+            Ok(Value::Integer(0))
+        );
+        assert_eq!(
+            FieldType::Text.bind("shelf", "2020"),
+            Ok(Value::Text("2020".to_string()))
+        );
+        assert_eq!(
+            FieldType::Number.bind("price", "cheap"),
+            Err("meta.price must be a number (got cheap)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        for name in ["shelf", "acquired_year", "x2"] {
+            assert!(validate_name(name).is_ok());
+        }
+        for name in ["", "Shelf", "2x", "shelf-no", "a'b", "a.b"] {
+            assert!(validate_name(name).is_err());
+        }
+    }
+}
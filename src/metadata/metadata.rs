@@ -0,0 +1,479 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{Pool, Sqlite};
+
+use super::super::resources::filter::Filters;
+use super::super::resources::resource::Resource;
+use super::super::resources::resources;
+use super::super::resources::resources_repository::Repository;
+use super::super::resources::value::Value;
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::field::MetadataField;
+use super::metadata_db;
+use super::registry::is_meta;
+
+// Values already stored are not checked again when a field changes type or
+// name, nor removed with it; they fail validation on the next update.
+pub fn config_metadata(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource(MetadataField::PATH)
+            .route(web::get().to(resources::get_all::<MetadataField>))
+            .route(web::post().to(create_field)),
+    )
+    .service(
+        resources::item::<MetadataField>()
+            .route(web::get().to(resources::get_one::<MetadataField>))
+            .route(web::put().to(update_field))
+            .route(web::delete().to(delete_field)),
+    );
+}
+
+fn internal_error(e: sqlx::Error) -> HttpResponse {
+    HttpResponse::InternalServerError().json(CustomError::new(e))
+}
+
+// The list filters of a resource with a `metadata` column, `meta.*`
+// parameters included.
+pub async fn filters<R: Resource>(
+    pool: &Pool<Sqlite>,
+    params: &[(String, String)],
+) -> Result<Filters, HttpResponse> {
+    let bad_request = |e| HttpResponse::BadRequest().json(CustomError::message(e));
+    let mut filters = Filters::parse::<R>(params).map_err(bad_request)?;
+    if params.iter().any(|(k, _)| is_meta(k)) {
+        let registry = metadata_db::get_registry(pool, R::TABLE)
+            .await
+            .map_err(internal_error)?;
+        filters
+            .clauses
+            .extend(registry.clauses(params).map_err(bad_request)?);
+    }
+    Ok(filters)
+}
+
+async fn check_metadata<R: Resource>(
+    pool: &Pool<Sqlite>,
+    resource: &R,
+) -> Result<(), HttpResponse> {
+    let metadata = match resource.get("metadata") {
+        Value::Text(m) => m,
+        _ => return Ok(()),
+    };
+    let registry = metadata_db::get_registry(pool, R::TABLE)
+        .await
+        .map_err(internal_error)?;
+    registry
+        .validate(&metadata)
+        .map_err(|e| HttpResponse::BadRequest().json(CustomError::message(e)))
+}
+
+// The generic handlers, for resources whose `metadata` is checked against
+// the fields declared for them.
+pub async fn get_all<R: Resource>(
+    params: web::Query<Vec<(String, String)>>,
+    repo: web::Data<dyn Repository<R>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    let filter = match filters::<R>(pool.get_ref(), &params).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+
+    match repo.get_all(&filter).await {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => internal_error(e),
+    }
+}
+
+pub async fn create<R: Resource>(
+    json: web::Json<R>,
+    repo: web::Data<dyn Repository<R>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    if let Err(resp) = check_metadata(pool.get_ref(), &*json).await {
+        return resp;
+    }
+    resources::create::<R>(json, repo).await
+}
+
+// `metadata` is replaced as a whole.
+pub async fn update<R: Resource>(
+    id: web::Path<i64>,
+    json: web::Json<R>,
+    repo: web::Data<dyn Repository<R>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    if let Err(resp) = check_metadata(pool.get_ref(), &*json).await {
+        return resp;
+    }
+    resources::update::<R>(id, json, repo).await
+}
+
+// Each resource may only declare a name once.
+async fn check_field(
+    field: MetadataField,
+    id: Option<i64>,
+    repo: &dyn Repository<MetadataField>,
+    pool: &Pool<Sqlite>,
+) -> Result<MetadataField, HttpResponse> {
+    let field = resources::check(field, id, repo).await?;
+    // Updates may leave either out, so the stored ones are checked then.
+    let stored = match id {
+        Some(id) => Some(repo.get_one(id).await.map_err(internal_error)?),
+        None => None,
+    };
+    let resource = field
+        .resource
+        .clone()
+        .or_else(|| stored.as_ref().and_then(|s| s.resource.clone()))
+        .unwrap_or_default();
+    let name = field
+        .name
+        .clone()
+        .or_else(|| stored.as_ref().and_then(|s| s.name.clone()))
+        .unwrap_or_default();
+
+    match metadata_db::get_conflict(pool, &resource, &name, id).await {
+        Ok(None) => Ok(field),
+        Ok(Some(other)) => Err(HttpResponse::Conflict().json(ConflictResponse {
+            message: format!("metadata field {}.{} already exists", resource, name),
+            id: other,
+            location: format!("{}/{}", MetadataField::PATH, other),
+        })),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+async fn create_field(
+    json: web::Json<MetadataField>,
+    repo: web::Data<dyn Repository<MetadataField>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    let field = match check_field(json.into_inner(), None, repo.get_ref(), &pool).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    let id = match repo.create(field).await {
+        Ok(id) => id,
+        Err(e) => return internal_error(e),
+    };
+
+    match metadata_db::sync_indexes(pool.get_ref()).await {
+        Ok(_) => HttpResponse::Created().json(CreateResponse { id }),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn update_field(
+    id: web::Path<i64>,
+    json: web::Json<MetadataField>,
+    repo: web::Data<dyn Repository<MetadataField>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    let field = match check_field(json.into_inner(), Some(id), repo.get_ref(), &pool).await {
+        Ok(f) => f,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = repo.update(field, id).await {
+        return internal_error(e);
+    }
+
+    match metadata_db::sync_indexes(pool.get_ref()).await {
+        Ok(_) => HttpResponse::Ok().json("Updated"),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn delete_field(
+    id: web::Path<i64>,
+    repo: web::Data<dyn Repository<MetadataField>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> HttpResponse {
+    if let Err(e) = repo.delete(id.into_inner()).await {
+        return internal_error(e);
+    }
+
+    match metadata_db::sync_indexes(pool.get_ref()).await {
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Err(e) => internal_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, author, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+    use sqlx::{Pool, Sqlite};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(authors::authors::config_authors)
+                    .configure(super::config_metadata),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $req:expr, $uri:expr, $body:expr) => {{
+            let req = $req.uri(&$uri).set_json($body).to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            let status = resp.status();
+            let body: Value = test::read_body_json(resp).await;
+            (status, body)
+        }};
+    }
+
+    async fn indexes(pool: &Pool<Sqlite>) -> Vec<String> {
+        sqlx::query_scalar(
+            "Select name From sqlite_master where type='index' and name GLOB '*_meta_*' \
+             order by name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_metadata_fields() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let shelf = json!({"resource": "books", "name": "shelf", "type": "text", "indexed": true});
+        let resp = send!(app, test::TestRequest::post(), "/metadata/fields", &shelf);
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+        let id = created["id"].as_i64().unwrap();
+        assert_eq!(indexes(&conn_pool).await, vec!["books_meta_shelf"]);
+
+        // The index is used for equality and range filters alike.
+        let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(
+            "EXPLAIN QUERY PLAN Select id From books where json_extract(metadata, '$.shelf') > ?",
+        )
+        .bind("A")
+        .fetch_all(&conn_pool)
+        .await
+        .unwrap();
+        assert!(plan[0].3.contains("USING INDEX books_meta_shelf"));
+
+        let resp = send!(app, test::TestRequest::post(), "/metadata/fields", &shelf);
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "metadata field books.shelf already exists");
+        assert_eq!(body["id"], id);
+        let author_shelf = json!({"resource": "authors", "name": "shelf", "type": "text"});
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            "/metadata/fields",
+            &author_shelf
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/metadata/fields/{}", id),
+            json!({"name": "room", "indexed": true})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(indexes(&conn_pool).await, vec!["books_meta_room"]);
+        let (_, body) = get_json!(app, format!("/metadata/fields/{}", id));
+        assert_eq!(
+            body,
+            json!({"id": id, "resource": "books", "name": "room", "type": "text",
+                "indexed": true, "description": null})
+        );
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/metadata/fields/{}", id),
+            json!({"indexed": false})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert!(indexes(&conn_pool).await.is_empty());
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/metadata/fields/{}", id),
+            json!({"indexed": true})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let req = test::TestRequest::delete()
+            .uri(&format!("/metadata/fields/{}", id))
+            .to_request();
+        test::call_service(&app, req).await;
+        assert!(indexes(&conn_pool).await.is_empty());
+        let (_, body) = get_json!(app, "/metadata/fields?resource=authors");
+        assert_eq!(body.as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_metadata_fields_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        for (body, message) in [
+            (
+                json!({"resource": "loans", "name": "shelf", "type": "text"}),
+                "resource must be one of books, authors (got loans)",
+            ),
+            (
+                json!({"resource": "books", "name": "Shelf no", "type": "text"}),
+                "name must be lowercase letters, digits and underscores, starting with a letter \
+                 (got Shelf no)",
+            ),
+            (
+                json!({"resource": "books", "name": "shelf", "type": "json"}),
+                "type must be one of text, integer, number, boolean, date (got json)",
+            ),
+            (
+                json!({"resource": "books", "name": "shelf"}),
+                "type is required",
+            ),
+        ] {
+            let resp = send!(app, test::TestRequest::post(), "/metadata/fields", body);
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_book_metadata() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        for field in [
+            json!({"resource": "books", "name": "shelf", "type": "text", "indexed": true}),
+            json!({"resource": "books", "name": "acquired_year", "type": "integer"}),
+            json!({"resource": "books", "name": "signed", "type": "boolean"}),
+        ] {
+            let resp = send!(app, test::TestRequest::post(), "/metadata/fields", field);
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+        }
+
+        let mut ids = Vec::new();
+        for (title, metadata) in [
+            (
+                "Dune",
+                json!({"shelf": "A3", "acquired_year": 2021, "signed": true}),
+            ),
+            ("Emma", json!({"shelf": "A3", "acquired_year": 2015})),
+            ("Hyperion", json!({"shelf": "B1", "acquired_year": 2022})),
+        ] {
+            let body = json!({"title": title, "author": "author", "metadata": metadata});
+            let resp = send!(app, test::TestRequest::post(), "/books", body);
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let created: Value = test::read_body_json(resp).await;
+            ids.push(created["id"].clone());
+        }
+        book().title("Undescribed").create(&conn_pool).await;
+
+        let (_, body) = get_json!(app, format!("/books/{}", ids[0]));
+        assert_eq!(
+            body["metadata"],
+            json!({"shelf": "A3", "acquired_year": 2021, "signed": true})
+        );
+        let titles = |body: &Value| -> Vec<String> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let (_, body) = get_json!(app, "/books?meta.shelf=A3&meta.acquired_year%3E=2020");
+        assert_eq!(titles(&body), vec!["Dune"]);
+        let (_, body) = get_json!(app, "/books?meta.acquired_year%3C2020&sort=title");
+        assert_eq!(titles(&body), vec!["Emma"]);
+        let (_, body) = get_json!(app, "/books?meta.shelf!=A3");
+        assert_eq!(titles(&body), vec!["Hyperion"]);
+        let (_, body) = get_json!(app, "/books?meta.signed=true");
+        assert_eq!(titles(&body), vec!["Dune"]);
+        let (_, body) = get_json!(app, "/books?meta.shelf=A3&facets=author&envelope=true");
+        assert_eq!(
+            body["facets"]["author"],
+            json!([{"value": "author", "count": 2}])
+        );
+
+        // `metadata` is replaced as a whole.
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/books/{}", ids[1]),
+            json!({"metadata": {"shelf": "C2"}})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let (_, body) = get_json!(app, format!("/books/{}", ids[1]));
+        assert_eq!(body["metadata"], json!({"shelf": "C2"}));
+        assert_eq!(body["title"], "Emma");
+
+        for (uri, message) in [
+            ("/books?meta.floor=2", "unknown metadata field floor"),
+            (
+                "/books?meta.acquired_year=recent",
+                "meta.acquired_year must be an integer (got recent)",
+            ),
+        ] {
+            let (status, body) = get_json!(app, uri);
+            assert_eq!(status, http::StatusCode::BAD_REQUEST);
+            assert_eq!(body["message"], message);
+        }
+        for (metadata, message) in [
+            (json!({"floor": 2}), "unknown metadata field floor"),
+            (
+                json!({"acquired_year": "2021"}),
+                "meta.acquired_year must be an integer (got \"2021\")",
+            ),
+        ] {
+            let resp = send!(
+                app,
+                test::TestRequest::put(),
+                format!("/books/{}", ids[0]),
+                json!({ "metadata": metadata })
+            );
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_author_metadata() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let field = json!({"resource": "authors", "name": "department", "type": "text"});
+        send!(app, test::TestRequest::post(), "/metadata/fields", field);
+
+        let body = json!({"name": "Herodotus", "metadata": {"department": "history"}});
+        let resp = send!(app, test::TestRequest::post(), "/authors", body);
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        author().name("Austen").create(&conn_pool).await;
+
+        let (_, body) = get_json!(app, "/authors?meta.department=history");
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["name"], "Herodotus");
+        // Fields are declared per resource.
+        let body = json!({"name": "Herbert", "metadata": {"shelf": "A3"}});
+        let resp = send!(app, test::TestRequest::post(), "/authors", body);
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
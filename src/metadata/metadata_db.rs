@@ -0,0 +1,64 @@
+use sqlx::{Error, Pool, Sqlite};
+use std::collections::BTreeSet;
+
+use super::field::{FieldType, RESOURCES};
+use super::metadata_queries;
+use super::registry::Registry;
+
+pub async fn get_registry(pool: &Pool<Sqlite>, resource: &str) -> Result<Registry, Error> {
+    let fields: Vec<(String, String)> = sqlx::query_as(&metadata_queries::get_fields_query())
+        .bind(resource)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Registry::new(fields.into_iter().filter_map(
+        |(name, field_type)| FieldType::parse(&field_type).ok().map(|t| (name, t)),
+    )))
+}
+
+pub async fn get_conflict(
+    pool: &Pool<Sqlite>,
+    resource: &str,
+    name: &str,
+    id: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar(&metadata_queries::get_conflict_query())
+        .bind(resource)
+        .bind(name)
+        .bind(id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Creates the expression index of every indexed field and drops those of
+// fields no longer indexed, renamed or deleted.
+pub async fn sync_indexes(pool: &Pool<Sqlite>) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    let fields: Vec<(String, String)> =
+        sqlx::query_as(&metadata_queries::get_indexed_fields_query())
+            .fetch_all(&mut tx)
+            .await?;
+    let wanted: BTreeSet<String> = fields
+        .iter()
+        .map(|(table, name)| metadata_queries::index_name(table, name))
+        .collect();
+
+    for table in RESOURCES {
+        let existing: Vec<String> = sqlx::query_scalar(&metadata_queries::get_indexes_query())
+            .bind(format!("{}_meta_*", table))
+            .fetch_all(&mut tx)
+            .await?;
+        for index in existing.iter().filter(|i| !wanted.contains(*i)) {
+            sqlx::query(&metadata_queries::drop_index_query(index))
+                .execute(&mut tx)
+                .await?;
+        }
+    }
+    for (table, name) in &fields {
+        sqlx::query(&metadata_queries::create_index_query(table, name))
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await
+}
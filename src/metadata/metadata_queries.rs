@@ -0,0 +1,60 @@
+use super::super::constants::METADATA_FIELDS_TABLE;
+use super::registry::expression;
+
+pub fn get_fields_query() -> String {
+    format!(
+        "Select name, type From {} where resource=?",
+        METADATA_FIELDS_TABLE
+    )
+}
+
+pub fn get_indexed_fields_query() -> String {
+    format!(
+        "Select resource, name From {} where indexed",
+        METADATA_FIELDS_TABLE
+    )
+}
+
+// Another field of the same resource with the same name.
+pub fn get_conflict_query() -> String {
+    format!(
+        "Select id From {} where resource=? and name=? and (? IS NULL or id != ?)",
+        METADATA_FIELDS_TABLE
+    )
+}
+
+pub fn index_name(table: &str, name: &str) -> String {
+    format!("{}_meta_{}", table, name)
+}
+
+// The metadata indexes of a table, bound as `{table}_meta_*`.
+pub fn get_indexes_query() -> String {
+    "Select name From sqlite_master where type='index' and name GLOB ?".to_string()
+}
+
+pub fn create_index_query(table: &str, name: &str) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+        index_name(table, name),
+        table,
+        expression(name)
+    )
+}
+
+pub fn drop_index_query(index: &str) -> String {
+    format!("DROP INDEX IF EXISTS {}", index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_index_query() {
+        assert_eq!(
+            create_index_query("books", "shelf"),
+            "CREATE INDEX IF NOT EXISTS books_meta_shelf ON books \
+             (json_extract(metadata, '$.shelf'))"
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod field;
+#[allow(clippy::module_inception)]
+pub mod metadata;
+mod metadata_db;
+mod metadata_queries;
+pub mod registry;
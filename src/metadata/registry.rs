@@ -0,0 +1,196 @@
+use serde_json::Map;
+use std::collections::HashMap;
+
+use super::super::resources::filter::Clause;
+use super::field::FieldType;
+
+pub const PREFIX: &str = "meta.";
+
+// The metadata fields declared for one resource, by name.
+#[derive(Debug, Default)]
+pub struct Registry {
+    fields: HashMap<String, FieldType>,
+}
+
+pub fn is_meta(key: &str) -> bool {
+    key.starts_with(PREFIX)
+}
+
+// The expression metadata fields are filtered and indexed on; both must read
+// the same for SQLite to use the index.
+pub fn expression(name: &str) -> String {
+    format!("json_extract(metadata, '$.{}')", name)
+}
+
+// Splits `meta.name<op>value` into its parts. Query strings split on the
+// first `=`, so `meta.year>=2020` arrives as `meta.year>` and `2020`, and
+// `meta.year>2020` as `meta.year>2020` with an empty value.
+fn parse_filter<'a>(key: &'a str, value: &'a str) -> Result<(&'a str, &'a str, &'a str), String> {
+    let rest = &key[PREFIX.len()..];
+    let (name, op, value) = match rest.find(['<', '>', '!']) {
+        None => (rest, "=", value),
+        Some(i) => {
+            let (name, tail) = rest.split_at(i);
+            match tail {
+                ">" => (name, ">=", value),
+                "<" => (name, "<=", value),
+                "!" => (name, "!=", value),
+                _ if !value.is_empty() => return Err(format!("unknown filter {}", key)),
+                _ if tail.starts_with(">=") || tail.starts_with("<=") || tail.starts_with("!=") => {
+                    (name, &tail[..2], &tail[2..])
+                }
+                _ if tail.starts_with('>') || tail.starts_with('<') => {
+                    (name, &tail[..1], &tail[1..])
+                }
+                _ => return Err(format!("unknown filter {}", key)),
+            }
+        }
+    };
+    Ok((name, op, value))
+}
+
+impl Registry {
+    pub fn new(fields: impl IntoIterator<Item = (String, FieldType)>) -> Self {
+        Registry {
+            fields: fields.into_iter().collect(),
+        }
+    }
+
+    fn field(&self, name: &str) -> Result<FieldType, String> {
+        self.fields
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown metadata field {}", name))
+    }
+
+    // Every key of `metadata`, a JSON object, must be a declared field and
+    // hold a value of its type.
+    pub fn validate(&self, metadata: &str) -> Result<(), String> {
+        let metadata: Map<String, serde_json::Value> =
+            serde_json::from_str(metadata).map_err(|_| "metadata must be an object".to_string())?;
+        for (name, value) in &metadata {
+            self.field(name)?.check(name, value)?;
+        }
+        Ok(())
+    }
+
+    // The `meta.*` parameters as clauses. Booleans only compare for
+    // (in)equality.
+    pub fn clauses(&self, params: &[(String, String)]) -> Result<Vec<Clause>, String> {
+        let mut clauses = Vec::new();
+        for (key, value) in params.iter().filter(|(k, _)| is_meta(k)) {
+            let (name, op, value) = parse_filter(key, value)?;
+            let field = self.field(name)?;
+            if field == FieldType::Boolean && !["=", "!="].contains(&op) {
+                return Err(format!("meta.{} can only be compared with = or !=", name));
+            }
+            clauses.push(Clause {
+                sql: format!("{} {} ?", expression(name), op),
+                binds: vec![field.bind(name, value)?],
+            });
+        }
+        Ok(clauses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::resources::value::Value;
+    use super::*;
+
+    fn registry() -> Registry {
+        Registry::new([
+            ("shelf".to_string(), FieldType::Text),
+            ("acquired_year".to_string(), FieldType::Integer),
+            ("signed".to_string(), FieldType::Boolean),
+        ])
+    }
+
+    fn params(p: &[(&str, &str)]) -> Vec<(String, String)> {
+        p.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(parse_filter("meta.shelf", "A3"), Ok(("shelf", "=", "A3")));
+        assert_eq!(
+            parse_filter("meta.year>", "2020"),
+            Ok(("year", ">=", "2020"))
+        );
+        assert_eq!(
+            parse_filter("meta.year<", "2020"),
+            Ok(("year", "<=", "2020"))
+        );
+        assert_eq!(parse_filter("meta.shelf!", "A3"), Ok(("shelf", "!=", "A3")));
+        assert_eq!(
+            parse_filter("meta.year>2020", ""),
+            Ok(("year", ">", "2020"))
+        );
+        assert_eq!(
+            parse_filter("meta.year<=2020", ""),
+            Ok(("year", "<=", "2020"))
+        );
+        assert!(parse_filter("meta.year>2020", "1").is_err());
+        assert!(parse_filter("meta.year!2020", "").is_err());
+    }
+
+    #[test]
+    fn test_clauses() {
+        let clauses = registry()
+            .clauses(&params(&[
+                ("title", "Dune"),
+                ("meta.shelf", "A3"),
+                ("meta.acquired_year>", "2020"),
+                ("meta.signed", "true"),
+            ]))
+            .unwrap();
+        let sql: Vec<&str> = clauses.iter().map(|c| c.sql.as_str()).collect();
+        assert_eq!(
+            sql,
+            vec![
+                "json_extract(metadata, '$.shelf') = ?",
+                "json_extract(metadata, '$.acquired_year') >= ?",
+                "json_extract(metadata, '$.signed') = ?",
+            ]
+        );
+        assert_eq!(clauses[1].binds, vec![Value::Integer(2020)]);
+        assert_eq!(clauses[2].binds, vec![Value::Integer(1)]);
+
+        for (key, value, message) in [
+            ("meta.floor", "2", "unknown metadata field floor"),
+            (
+                "meta.acquired_year",
+                "recent",
+                "meta.acquired_year must be an integer (got recent)",
+            ),
+            (
+                "meta.signed>",
+                "true",
+                "meta.signed can only be compared with = or !=",
+            ),
+        ] {
+            assert_eq!(
+                registry().clauses(&params(&[(key, value)])).err(),
+                Some(message.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let registry = registry();
+        assert!(registry
+            .validate(r#"{"shelf": "A3", "acquired_year": 2021, "signed": null}"#)
+            .is_ok());
+        assert_eq!(
+            registry.validate(r#"{"floor": 2}"#),
+            Err("unknown metadata field floor".to_string())
+        );
+        assert_eq!(
+            registry.validate(r#"{"signed": "yes"}"#),
+            Err("meta.signed must be true or false (got \"yes\")".to_string())
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod publisher;
+#[allow(clippy::module_inception)]
+pub mod publishers;
+mod publishers_db;
+mod publishers_queries;
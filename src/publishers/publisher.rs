@@ -0,0 +1,60 @@
+use super::super::constants::PUBLISHERS_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Publisher {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    pub country: Option<String>,
+    pub website: Option<String>,
+}
+
+impl Resource for Publisher {
+    const PATH: &'static str = "/publishers";
+    const TABLE: &'static str = PUBLISHERS_TABLE;
+    const FIELDS: &'static [&'static str] = &["name", "country", "website"];
+    const FILTERABLE: &'static [&'static str] = &["name", "country"];
+    const SORTABLE: &'static [&'static str] = &["id", "name", "country"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "name" => Value::from(self.name.clone()),
+            "country" => Value::from(self.country.clone()),
+            "website" => Value::from(self.website.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "name" => self.name = value.into(),
+            "country" => self.country = value.into(),
+            "website" => self.website = value.into(),
+            _ => {}
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("name", &self.name)?;
+        validate_not_blank("country", &self.country)?;
+        match &self.website {
+            Some(w) if !w.starts_with("http://") && !w.starts_with("https://") => {
+                Err("website must be an http(s) URL".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+
+use super::super::books::book::Book;
+use super::super::resources::filter::Filters;
+use super::super::resources::resources;
+use super::super::resources::resources_repository::Repository;
+use super::super::responses::CustomError;
+use super::publisher::Publisher;
+use super::publishers_db::{self, Delete};
+
+pub fn config_publishers(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_publisher_books)
+        .service(resources::collection::<Publisher>())
+        .service(
+            resources::item::<Publisher>()
+                .route(web::get().to(resources::get_one::<Publisher>))
+                .route(web::put().to(resources::update::<Publisher>))
+                .route(web::delete().to(delete_publisher)),
+        );
+}
+
+#[derive(Deserialize)]
+pub struct DeleteParams {
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[get("/publishers/{id}/books")]
+async fn get_publisher_books(
+    id: web::Path<i64>,
+    params: web::Query<Vec<(String, String)>>,
+    publishers: web::Data<dyn Repository<Publisher>>,
+    books: web::Data<dyn Repository<Book>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let filter = match Filters::parse::<Book>(&params) {
+        Ok(f) => f.by("publisher_id", id),
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    if let Err(e) = publishers.get_one(id).await {
+        return HttpResponse::InternalServerError().json(CustomError::new(e));
+    }
+    let r = books.get_all(&filter).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// A publisher that still has books is only removed when `?cascade=true` is
+// passed, in which case its books are deleted with it.
+async fn delete_publisher(
+    id: web::Path<i64>,
+    params: web::Query<DeleteParams>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let r = publishers_db::delete_publisher(pool.get_ref(), id, params.cascade).await;
+
+    match r {
+        Ok(Delete::Done) => HttpResponse::Ok().json("Deleted"),
+        Ok(Delete::Refused(books)) => HttpResponse::Conflict().json(CustomError::message(format!(
+            "publisher {} still has {} books, pass cascade=true to delete them too",
+            id, books
+        ))),
+        Ok(Delete::Lent(books)) => HttpResponse::Conflict().json(CustomError::message(format!(
+            "books {} of publisher {} have been lent before and are kept for the loan history",
+            books
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            id
+        ))),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::books::book::Book;
+    use crate::resources::resources_db::SqliteRepository;
+    use crate::resources::resources_repository::Repository;
+    use crate::test_utils::{self, book, member, publisher, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_publishers),
+            )
+            .await
+        }};
+    }
+
+    fn titles(body: &Value) -> Vec<&str> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["title"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_create_publisher() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(json!({
+                "name": "Penguin",
+                "country": "UK",
+                "website": "https://penguin.co.uk"
+            }))
+            .uri("/publishers")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let body: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/publishers/{}", body["id"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "Penguin");
+        assert_eq!(body["website"], "https://penguin.co.uk");
+    }
+
+    #[actix_web::test]
+    async fn test_create_publisher_bad_website() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(json!({"name": "Penguin", "website": "penguin"}))
+            .uri("/publishers")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "website must be an http(s) URL");
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_by_publisher() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().name("Penguin").create(&conn_pool).await;
+        let tor = publisher().name("Tor").create(&conn_pool).await;
+        book()
+            .title("Emma")
+            .publisher(&penguin)
+            .create(&conn_pool)
+            .await;
+        book()
+            .title("Dune")
+            .publisher(&tor)
+            .create(&conn_pool)
+            .await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/books?publisher_id={}", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Emma"]);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/publishers/{}/books", tor.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(titles(&body), vec!["Dune"]);
+    }
+
+    #[actix_web::test]
+    async fn test_delete_publisher_with_books_conflict() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().create(&conn_pool).await;
+        book().publisher(&penguin).create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/publishers/{}", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains("cascade=true"));
+    }
+
+    #[actix_web::test]
+    async fn test_delete_publisher_cascade() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().create(&conn_pool).await;
+        let emma = book().publisher(&penguin).create(&conn_pool).await;
+        let dune = book().create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/publishers/{}?cascade=true", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let books = SqliteRepository::<Book>::new(conn_pool);
+        assert!(books.get_one(emma.id.unwrap()).await.is_err());
+        assert!(books.get_one(dune.id.unwrap()).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_publisher_cascade_is_atomic() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().create(&conn_pool).await;
+        let emma = book().publisher(&penguin).create(&conn_pool).await;
+        sqlx::query(
+            "CREATE TRIGGER keep_publishers BEFORE DELETE ON publishers \
+             BEGIN SELECT RAISE(ABORT, 'kept'); END",
+        )
+        .execute(&conn_pool)
+        .await
+        .unwrap();
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/publishers/{}?cascade=true", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let books = SqliteRepository::<Book>::new(conn_pool);
+        assert!(books.get_one(emma.id.unwrap()).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_publisher_cascade_with_lent_books() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().create(&conn_pool).await;
+        let emma = book().publisher(&penguin).create(&conn_pool).await;
+        let persuasion = book().publisher(&penguin).create(&conn_pool).await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        sqlx::query(
+            "INSERT INTO loans (book_id, member_id, loaned_at, due_at, returned_at) \
+             values (?, ?, '2024-01-01', '2024-01-15', '2024-01-10')",
+        )
+        .bind(emma.id)
+        .bind(m)
+        .execute(&conn_pool)
+        .await
+        .unwrap();
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/publishers/{}?cascade=true", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            format!(
+                "books {} of publisher {} have been lent before and are kept for the loan history",
+                emma.id.unwrap(),
+                penguin.id.unwrap()
+            )
+        );
+        let books = SqliteRepository::<Book>::new(conn_pool);
+        assert!(books.get_one(persuasion.id.unwrap()).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_publisher_without_books() {
+        let conn_pool = test_pool().await;
+        let penguin = publisher().create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/publishers/{}", penguin.id.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, "Deleted");
+    }
+}
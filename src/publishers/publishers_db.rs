@@ -0,0 +1,45 @@
+use super::publishers_queries;
+use sqlx::{Error, Pool, Sqlite};
+
+pub enum Delete {
+    Done,
+    // The publisher still has this many books and `cascade` was not given.
+    Refused(i64),
+    // These books have been lent before and are kept for the loan history.
+    Lent(Vec<i64>),
+}
+
+// Deletes the publisher and, with `cascade`, its books, all or nothing.
+pub async fn delete_publisher(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    cascade: bool,
+) -> Result<Delete, Error> {
+    let mut tx = pool.begin().await?;
+    let books: i64 = sqlx::query_scalar(&publishers_queries::count_books_query())
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+    if books > 0 && !cascade {
+        return Ok(Delete::Refused(books));
+    }
+    let lent: Vec<i64> = sqlx::query_scalar(&publishers_queries::get_lent_books_query())
+        .bind(id)
+        .fetch_all(&mut tx)
+        .await?;
+    if !lent.is_empty() {
+        return Ok(Delete::Lent(lent));
+    }
+
+    sqlx::query(&publishers_queries::delete_books_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query(&publishers_queries::delete_publisher_query())
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Delete::Done)
+}
@@ -0,0 +1,21 @@
+use super::super::constants::{BOOKS_TABLE, LOANS_TABLE, PUBLISHERS_TABLE};
+
+pub fn count_books_query() -> String {
+    format!("Select count(*) From {} where publisher_id=?", BOOKS_TABLE)
+}
+
+pub fn get_lent_books_query() -> String {
+    format!(
+        "Select DISTINCT book_id From {} where book_id IN \
+         (Select id From {} where publisher_id=?) order by book_id",
+        LOANS_TABLE, BOOKS_TABLE
+    )
+}
+
+pub fn delete_books_query() -> String {
+    format!("DELETE From {} where publisher_id=?", BOOKS_TABLE)
+}
+
+pub fn delete_publisher_query() -> String {
+    format!("DELETE From {} where id=?", PUBLISHERS_TABLE)
+}
@@ -0,0 +1,63 @@
+use super::authors::author::Author;
+use super::books::book::Book;
+use super::fines::fine_policy::FinePolicy;
+use super::fines::holiday::Holiday;
+use super::members::member::Member;
+use super::metadata::field::MetadataField;
+use super::publishers::publisher::Publisher;
+use super::resources::resources_db::SqliteRepository;
+use super::resources::resources_repository::Repository;
+use super::series::book_series::BookSeries;
+use super::subjects::subject::Subject;
+use super::tags::tag::Tag;
+use actix_web::web;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Repositories {
+    pub books: Arc<dyn Repository<Book>>,
+    pub authors: Arc<dyn Repository<Author>>,
+    pub publishers: Arc<dyn Repository<Publisher>>,
+    pub tags: Arc<dyn Repository<Tag>>,
+    pub members: Arc<dyn Repository<Member>>,
+    pub fine_policies: Arc<dyn Repository<FinePolicy>>,
+    pub holidays: Arc<dyn Repository<Holiday>>,
+    pub series: Arc<dyn Repository<BookSeries>>,
+    pub subjects: Arc<dyn Repository<Subject>>,
+    pub metadata_fields: Arc<dyn Repository<MetadataField>>,
+    // Relations that span several tables (tags, loans, ...) are queried directly.
+    pub pool: Pool<Sqlite>,
+}
+
+impl Repositories {
+    pub fn sqlite(pool: Pool<Sqlite>) -> Self {
+        Repositories {
+            books: Arc::new(SqliteRepository::<Book>::new(pool.clone())),
+            authors: Arc::new(SqliteRepository::<Author>::new(pool.clone())),
+            publishers: Arc::new(SqliteRepository::<Publisher>::new(pool.clone())),
+            tags: Arc::new(SqliteRepository::<Tag>::new(pool.clone())),
+            members: Arc::new(SqliteRepository::<Member>::new(pool.clone())),
+            fine_policies: Arc::new(SqliteRepository::<FinePolicy>::new(pool.clone())),
+            holidays: Arc::new(SqliteRepository::<Holiday>::new(pool.clone())),
+            series: Arc::new(SqliteRepository::<BookSeries>::new(pool.clone())),
+            subjects: Arc::new(SqliteRepository::<Subject>::new(pool.clone())),
+            metadata_fields: Arc::new(SqliteRepository::<MetadataField>::new(pool.clone())),
+            pool,
+        }
+    }
+
+    pub fn config(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::from(self.books.clone()))
+            .app_data(web::Data::from(self.authors.clone()))
+            .app_data(web::Data::from(self.publishers.clone()))
+            .app_data(web::Data::from(self.tags.clone()))
+            .app_data(web::Data::from(self.members.clone()))
+            .app_data(web::Data::from(self.fine_policies.clone()))
+            .app_data(web::Data::from(self.holidays.clone()))
+            .app_data(web::Data::from(self.series.clone()))
+            .app_data(web::Data::from(self.subjects.clone()))
+            .app_data(web::Data::from(self.metadata_fields.clone()))
+            .app_data(web::Data::new(self.pool.clone()));
+    }
+}
@@ -0,0 +1,124 @@
+use super::super::metadata::registry::is_meta;
+use super::resource::Resource;
+use super::value::Value;
+
+pub struct Sort {
+    pub column: String,
+    pub descending: bool,
+}
+
+// A raw `where` fragment produced by `Resource::clauses`. Only the SQLite
+// repository can evaluate these.
+pub struct Clause {
+    pub sql: String,
+    pub binds: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct Filters {
+    pub conditions: Vec<(String, String)>,
+    pub clauses: Vec<Clause>,
+    pub sort: Option<Sort>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl Filters {
+    pub fn parse<R: Resource>(params: &[(String, String)]) -> Result<Filters, String> {
+        let mut filters = Filters::default();
+
+        for (key, value) in params {
+            match key.as_str() {
+                "limit" => filters.limit = Some(parse_number(key, value)?),
+                "offset" => filters.offset = Some(parse_number(key, value)?),
+                "sort" => {
+                    let (column, descending) = match value.strip_prefix('-') {
+                        Some(column) => (column, true),
+                        None => (value.as_str(), false),
+                    };
+                    if !R::SORTABLE.contains(&column) {
+                        return Err(format!("cannot sort by {}", column));
+                    }
+                    filters.sort = Some(Sort {
+                        column: column.to_string(),
+                        descending,
+                    });
+                }
+                column if R::FILTERABLE.contains(&column) => {
+                    filters.conditions.push((key.clone(), value.clone()))
+                }
+                param if R::PARAMS.contains(&param) => {}
+                // Typed by the metadata registry, see `metadata::filters`.
+                param if R::METADATA && is_meta(param) => {}
+                _ => return Err(format!("unknown filter {}", key)),
+            }
+        }
+        filters.clauses = R::clauses(params)?;
+
+        Ok(filters)
+    }
+
+    pub fn by(mut self, column: &str, value: impl ToString) -> Self {
+        self.conditions
+            .push((column.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+pub fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a non-negative integer", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Book;
+
+    fn params(p: &[(&str, &str)]) -> Vec<(String, String)> {
+        p.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let f = Filters::parse::<Book>(&params(&[
+            ("author", "saeb"),
+            ("sort", "-title"),
+            ("limit", "2"),
+            ("offset", "4"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            f.conditions,
+            vec![("author".to_string(), "saeb".to_string())]
+        );
+        let sort = f.sort.unwrap();
+        assert_eq!(sort.column, "title");
+        assert!(sort.descending);
+        assert_eq!(f.limit, Some(2));
+        assert_eq!(f.offset, Some(4));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_columns() {
+        assert!(Filters::parse::<Book>(&params(&[("publisher", "x")])).is_err());
+        assert!(Filters::parse::<Book>(&params(&[("sort", "publisher")])).is_err());
+        assert!(Filters::parse::<Book>(&params(&[("limit", "-1")])).is_err());
+    }
+}
@@ -0,0 +1,10 @@
+pub mod filter;
+pub mod resource;
+#[allow(clippy::module_inception)]
+pub mod resources;
+pub mod resources_db;
+#[cfg(test)]
+pub mod resources_memory;
+pub mod resources_queries;
+pub mod resources_repository;
+pub mod value;
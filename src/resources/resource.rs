@@ -0,0 +1,74 @@
+use super::filter::Clause;
+use super::value::Value;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{sqlite::SqliteRow, FromRow};
+
+/// A model exposed as a CRUD resource. Implementing this trait is enough to
+/// get the list, get, create, update and delete handlers as well as the
+/// SQLite repository (and the in-memory one used in tests) for it.
+pub trait Resource:
+    Serialize
+    + DeserializeOwned
+    + for<'r> FromRow<'r, SqliteRow>
+    + Clone
+    + Send
+    + Sync
+    + Unpin
+    + 'static
+{
+    /// Route prefix, e.g. `/books`.
+    const PATH: &'static str;
+    const TABLE: &'static str;
+    /// Writable columns, in insert order. `id` is implicit.
+    const FIELDS: &'static [&'static str];
+    /// Columns accepted as `?column=value` on the list endpoint.
+    const FILTERABLE: &'static [&'static str];
+    /// Columns accepted as `?sort=column` or `?sort=-column`.
+    const SORTABLE: &'static [&'static str];
+    /// Select list, for models that expose computed read-only columns.
+    const SELECT: &'static str = "*";
+    /// Columns that must be set when a row is created.
+    const REQUIRED: &'static [&'static str] = &[];
+    /// Columns whose values may only appear once; clashes are a 409.
+    const UNIQUE: &'static [&'static str] = &[];
+    /// Extra list parameters that are turned into SQL by `clauses`.
+    const PARAMS: &'static [&'static str] = &[];
+    /// Whether the model has a `metadata` JSON column, filtered on with
+    /// `?meta.field=value` and the like.
+    const METADATA: bool = false;
+
+    fn id(&self) -> Option<i64>;
+    // Only the in-memory repository the contract tests also run against
+    // writes fields back, SQLite does it in SQL.
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64);
+    fn get(&self, field: &str) -> Value;
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value);
+
+    fn normalize(&mut self) {}
+
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn clauses(_params: &[(String, String)]) -> Result<Vec<Clause>, String> {
+        Ok(Vec::new())
+    }
+
+    #[cfg(test)]
+    fn column(&self, column: &str) -> Value {
+        if column == "id" {
+            Value::from(self.id())
+        } else {
+            self.get(column)
+        }
+    }
+}
+
+pub fn validate_not_blank(field: &str, value: &Option<String>) -> Result<(), String> {
+    match value {
+        Some(v) if v.trim().is_empty() => Err(format!("{} must not be blank", field)),
+        _ => Ok(()),
+    }
+}
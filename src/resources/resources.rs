@@ -0,0 +1,146 @@
+use actix_web::{web, HttpResponse, Responder};
+
+use super::super::responses::ConflictResponse;
+use super::super::responses::CreateResponse;
+use super::super::responses::CustomError;
+use super::filter::Filters;
+use super::resource::Resource;
+use super::resources_repository::Repository;
+
+pub fn config<R: Resource>(cfg: &mut web::ServiceConfig) {
+    cfg.service(collection::<R>()).service(
+        item::<R>()
+            .route(web::get().to(get_one::<R>))
+            .route(web::put().to(update::<R>))
+            .route(web::delete().to(delete::<R>)),
+    );
+}
+
+pub fn collection<R: Resource>() -> actix_web::Resource {
+    web::resource(R::PATH)
+        .route(web::get().to(get_all::<R>))
+        .route(web::post().to(create::<R>))
+}
+
+// Routes for `{PATH}/{id}` are left to the caller so that a model can swap
+// any of the generic handlers for its own.
+pub fn item<R: Resource>() -> actix_web::Resource {
+    web::resource(format!("{}/{{id}}", R::PATH))
+}
+
+pub async fn get_all<R: Resource>(
+    params: web::Query<Vec<(String, String)>>,
+    repo: web::Data<dyn Repository<R>>,
+) -> impl Responder {
+    let filter = match Filters::parse::<R>(&params) {
+        Ok(f) => f,
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    let r = repo.get_all(&filter).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+pub async fn get_one<R: Resource>(
+    id: web::Path<i64>,
+    repo: web::Data<dyn Repository<R>>,
+) -> impl Responder {
+    let r = repo.get_one(id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+pub async fn create<R: Resource>(
+    json: web::Json<R>,
+    repo: web::Data<dyn Repository<R>>,
+) -> HttpResponse {
+    let resource = match check(json.into_inner(), None, repo.get_ref()).await {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+    let r = repo.create(resource).await;
+
+    match r {
+        Ok(id) => HttpResponse::Created().json(CreateResponse { id }),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+pub async fn update<R: Resource>(
+    id: web::Path<i64>,
+    json: web::Json<R>,
+    repo: web::Data<dyn Repository<R>>,
+) -> HttpResponse {
+    let id = id.into_inner();
+    let resource = match check(json.into_inner(), Some(id), repo.get_ref()).await {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+    let r = repo.update(resource, id).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Updated"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+pub async fn delete<R: Resource>(
+    id: web::Path<i64>,
+    repo: web::Data<dyn Repository<R>>,
+) -> impl Responder {
+    let r = repo.delete(id.into_inner()).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Normalizes and validates an incoming resource, then makes sure none of its
+// unique columns clash with another row. `id` is the row being updated, if any.
+pub async fn check<R: Resource>(
+    mut resource: R,
+    id: Option<i64>,
+    repo: &dyn Repository<R>,
+) -> Result<R, HttpResponse> {
+    resource.normalize();
+    if id.is_none() {
+        if let Some(column) = R::REQUIRED.iter().find(|c| resource.get(c).is_null()) {
+            return Err(HttpResponse::BadRequest()
+                .json(CustomError::message(format!("{} is required", column))));
+        }
+    }
+    if let Err(e) = resource.validate() {
+        return Err(HttpResponse::BadRequest().json(CustomError::message(e)));
+    }
+
+    for column in R::UNIQUE {
+        let value = resource.get(column);
+        if value.is_null() {
+            continue;
+        }
+        let existing = repo
+            .get_all(&Filters::default().by(column, &value).limit(2))
+            .await
+            .map_err(|e| HttpResponse::InternalServerError().json(CustomError::new(e)))?;
+        if let Some(other) = existing
+            .iter()
+            .filter_map(|r| r.id())
+            .find(|o| Some(*o) != id)
+        {
+            return Err(HttpResponse::Conflict().json(ConflictResponse {
+                message: format!("{} {} already exists", column, value),
+                id: other,
+                location: format!("{}/{}", R::PATH, other),
+            }));
+        }
+    }
+
+    Ok(resource)
+}
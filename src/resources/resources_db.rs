@@ -0,0 +1,123 @@
+use super::filter::Filters;
+use super::resource::Resource;
+use super::resources_queries;
+use super::resources_repository::Repository;
+use super::value::Value;
+use async_trait::async_trait;
+use sqlx::{
+    query::{Query, QueryAs},
+    sqlite::SqliteArguments,
+    Error, Pool, Sqlite,
+};
+use std::marker::PhantomData;
+
+pub struct SqliteRepository<R> {
+    pool: Pool<Sqlite>,
+    resource: PhantomData<R>,
+}
+
+impl<R> SqliteRepository<R> {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        SqliteRepository {
+            pool,
+            resource: PhantomData,
+        }
+    }
+}
+
+fn bind<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: Value,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Integer(i) => query.bind(i),
+        Value::Real(f) => query.bind(f),
+        Value::Text(t) => query.bind(t),
+    }
+}
+
+fn bind_as<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    value: Value,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Integer(i) => query.bind(i),
+        Value::Real(f) => query.bind(f),
+        Value::Text(t) => query.bind(t),
+    }
+}
+
+// Binds the values of `resources_queries::conditions`.
+pub fn bind_filters<'q, O>(
+    mut query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    filter: &'q Filters,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    for (_, value) in &filter.conditions {
+        query = query.bind(value);
+    }
+    for clause in &filter.clauses {
+        for value in &clause.binds {
+            query = bind_as(query, value.clone());
+        }
+    }
+    query
+}
+
+#[async_trait]
+impl<R: Resource> Repository<R> for SqliteRepository<R> {
+    async fn get_all(&self, filter: &Filters) -> Result<Vec<R>, Error> {
+        let query = resources_queries::get_all_query::<R>(filter);
+
+        bind_filters(sqlx::query_as::<_, R>(&query), filter)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    async fn get_one(&self, id: i64) -> Result<R, Error> {
+        let query = resources_queries::get_one_query::<R>();
+
+        sqlx::query_as::<_, R>(&query)
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn create(&self, resource: R) -> Result<i64, Error> {
+        let query = resources_queries::create_query::<R>();
+        let mut q = sqlx::query(&query);
+        for field in R::FIELDS {
+            q = bind(q, resource.get(field));
+        }
+        let r = q.execute(&self.pool).await?;
+
+        Ok(r.last_insert_rowid())
+    }
+
+    async fn update(&self, resource: R, id: i64) -> Result<(), Error> {
+        let fields: Vec<&str> = R::FIELDS
+            .iter()
+            .copied()
+            .filter(|f| !resource.get(f).is_null())
+            .collect();
+        let query = resources_queries::update_query::<R>(&fields);
+        if query.is_empty() {
+            return Ok(());
+        }
+        let mut q = sqlx::query(&query);
+        for field in &fields {
+            q = bind(q, resource.get(field));
+        }
+        q.bind(id).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> Result<(), Error> {
+        let query = resources_queries::delete_query::<R>();
+        sqlx::query(&query).bind(id).execute(&self.pool).await?;
+
+        Ok(())
+    }
+}
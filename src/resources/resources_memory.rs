@@ -0,0 +1,110 @@
+use super::filter::Filters;
+use super::resource::Resource;
+use super::resources_repository::Repository;
+use async_trait::async_trait;
+use sqlx::Error;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub struct InMemoryRepository<R> {
+    state: Mutex<State<R>>,
+}
+
+struct State<R> {
+    last_id: i64,
+    rows: BTreeMap<i64, R>,
+}
+
+impl<R> InMemoryRepository<R> {
+    pub fn new() -> Self {
+        InMemoryRepository {
+            state: Mutex::new(State {
+                last_id: 0,
+                rows: BTreeMap::new(),
+            }),
+        }
+    }
+}
+
+impl<R> Default for InMemoryRepository<R> {
+    fn default() -> Self {
+        InMemoryRepository::new()
+    }
+}
+
+#[async_trait]
+impl<R: Resource> Repository<R> for InMemoryRepository<R> {
+    async fn get_all(&self, filter: &Filters) -> Result<Vec<R>, Error> {
+        if !filter.clauses.is_empty() {
+            return Err(Error::Configuration(
+                "SQL filters are not supported by the in-memory repository".into(),
+            ));
+        }
+        let state = self.state.lock().unwrap();
+        let mut rows: Vec<R> = state
+            .rows
+            .values()
+            .filter(|r| {
+                filter
+                    .conditions
+                    .iter()
+                    .all(|(column, value)| r.column(column).matches(value))
+            })
+            .cloned()
+            .collect();
+
+        if let Some(sort) = &filter.sort {
+            rows.sort_by(|a, b| {
+                let ordering = a
+                    .column(&sort.column)
+                    .partial_cmp(&b.column(&sort.column))
+                    .unwrap_or(std::cmp::Ordering::Equal);
+                if sort.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+
+        let offset = filter.offset.unwrap_or(0) as usize;
+        let limit = filter.limit.map_or(usize::MAX, |l| l as usize);
+        Ok(rows.into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn get_one(&self, id: i64) -> Result<R, Error> {
+        let state = self.state.lock().unwrap();
+        state.rows.get(&id).cloned().ok_or(Error::RowNotFound)
+    }
+
+    async fn create(&self, mut resource: R) -> Result<i64, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.last_id += 1;
+        let id = state.last_id;
+        resource.set_id(id);
+        state.rows.insert(id, resource);
+
+        Ok(id)
+    }
+
+    async fn update(&self, resource: R, id: i64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(existing) = state.rows.get_mut(&id) {
+            for field in R::FIELDS {
+                let value = resource.get(field);
+                if !value.is_null() {
+                    existing.set(field, value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.rows.remove(&id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use super::filter::Filters;
+use super::resource::Resource;
+
+// The `where` conditions of a filter, in the order their values are bound.
+pub fn conditions(filter: &Filters) -> Vec<String> {
+    filter
+        .conditions
+        .iter()
+        .map(|(column, _)| format!("{}=?", column))
+        .chain(filter.clauses.iter().map(|c| format!("({})", c.sql)))
+        .collect()
+}
+
+pub fn get_all_query<R: Resource>(filter: &Filters) -> String {
+    let mut query = format!("Select {} From {}", R::SELECT, R::TABLE);
+    let conditions = conditions(filter);
+    if !conditions.is_empty() {
+        query = format!("{} where {}", query, conditions.join(" and "));
+    }
+    if let Some(sort) = &filter.sort {
+        let direction = if sort.descending { "desc" } else { "asc" };
+        query = format!("{} order by {} {}", query, sort.column, direction);
+    }
+    if filter.limit.is_some() || filter.offset.is_some() {
+        query = format!(
+            "{} limit {} offset {}",
+            query,
+            filter.limit.map_or(-1, i64::from),
+            filter.offset.unwrap_or(0)
+        );
+    }
+    query
+}
+
+pub fn get_one_query<R: Resource>() -> String {
+    format!("Select {} From {} where id=?", R::SELECT, R::TABLE)
+}
+
+pub fn create_query<R: Resource>() -> String {
+    let placeholders = vec!["?"; R::FIELDS.len()];
+    format!(
+        "INSERT INTO {} ({}) values ({}) RETURNING id",
+        R::TABLE,
+        R::FIELDS.join(", "),
+        placeholders.join(", ")
+    )
+}
+
+pub fn update_query<R: Resource>(fields: &[&str]) -> String {
+    if fields.is_empty() {
+        return String::new();
+    }
+    let assignments: Vec<String> = fields.iter().map(|f| format!("{}=?", f)).collect();
+    format!(
+        "UPDATE {} SET {} where id=?",
+        R::TABLE,
+        assignments.join(", ")
+    )
+}
+
+pub fn delete_query<R: Resource>() -> String {
+    format!("DELETE From {} where id=?", R::TABLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::filter::{Clause, Sort};
+    use super::*;
+    use crate::books::book::Book;
+
+    #[test]
+    fn test_get_all_query() {
+        let filter = Filters {
+            conditions: vec![("author".to_string(), "saeb".to_string())],
+            clauses: vec![Clause {
+                sql: "id > ?".to_string(),
+                binds: vec![],
+            }],
+            sort: Some(Sort {
+                column: "title".to_string(),
+                descending: true,
+            }),
+            limit: Some(2),
+            offset: None,
+        };
+
+        assert_eq!(
+            get_all_query::<Book>(&filter),
+            format!(
+                "Select {} From books where author=? and (id > ?) order by title desc limit 2 offset 0",
+                Book::SELECT
+            )
+        );
+        assert_eq!(
+            get_all_query::<Book>(&Filters::default()),
+            format!("Select {} From books", Book::SELECT)
+        );
+    }
+
+    #[test]
+    fn test_create_query() {
+        assert_eq!(
+            create_query::<Book>(),
+            "INSERT INTO books (title, author, publisher_id, isbn, publication_date, language, \
+             page_count, edition, description, metadata) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             RETURNING id"
+        );
+    }
+
+    #[test]
+    fn test_update_query() {
+        assert_eq!(
+            update_query::<Book>(&["title"]),
+            "UPDATE books SET title=? where id=?"
+        );
+        assert_eq!(update_query::<Book>(&[]), "");
+    }
+}
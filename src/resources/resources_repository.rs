@@ -0,0 +1,184 @@
+use super::filter::Filters;
+use super::resource::Resource;
+use async_trait::async_trait;
+use sqlx::Error;
+
+#[async_trait]
+pub trait Repository<R: Resource>: Send + Sync {
+    async fn get_all(&self, filter: &Filters) -> Result<Vec<R>, Error>;
+    async fn get_one(&self, id: i64) -> Result<R, Error>;
+    async fn create(&self, resource: R) -> Result<i64, Error>;
+    async fn update(&self, resource: R, id: i64) -> Result<(), Error>;
+    async fn delete(&self, id: i64) -> Result<(), Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::resources_db::SqliteRepository;
+    use super::super::resources_memory::InMemoryRepository;
+    use super::*;
+    use crate::books::book::Book;
+    use crate::test_utils::test_pool;
+
+    async fn sqlite() -> SqliteRepository<Book> {
+        SqliteRepository::new(test_pool().await)
+    }
+
+    async fn memory() -> InMemoryRepository<Book> {
+        InMemoryRepository::new()
+    }
+
+    fn book(title: &str, author: &str) -> Book {
+        Book {
+            id: None,
+            title: Some(title.to_string()),
+            author: Some(author.to_string()),
+            publisher_id: None,
+            isbn: None,
+            publication_date: None,
+            language: None,
+            page_count: None,
+            edition: None,
+            description: None,
+            average_rating: None,
+            review_count: None,
+            copy_count: None,
+            available_copies: None,
+            contributors: None,
+            series: None,
+            metadata: None,
+        }
+    }
+
+    fn filters(params: &[(&str, &str)]) -> Filters {
+        let params: Vec<(String, String)> = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Filters::parse::<Book>(&params).unwrap()
+    }
+
+    fn titles(books: &[Book]) -> Vec<&str> {
+        books.iter().map(|b| b.title.as_deref().unwrap()).collect()
+    }
+
+    async fn create_and_get(repo: &dyn Repository<Book>) {
+        let id = repo.create(book("Dune", "Herbert")).await.unwrap();
+        let b = repo.get_one(id).await.unwrap();
+
+        assert_eq!(b.id, Some(id));
+        assert_eq!(b.title.as_deref(), Some("Dune"));
+        assert_eq!(b.author.as_deref(), Some("Herbert"));
+    }
+
+    async fn create_assigns_new_ids(repo: &dyn Repository<Book>) {
+        let first = repo.create(book("Dune", "Herbert")).await.unwrap();
+        let second = repo.create(book("Dune", "Herbert")).await.unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    async fn get_missing(repo: &dyn Repository<Book>) {
+        let r = repo.get_one(42).await;
+
+        assert!(matches!(r, Err(Error::RowNotFound)));
+    }
+
+    async fn list_filters_by_column_and_limit(repo: &dyn Repository<Book>) {
+        repo.create(book("Dune", "Herbert")).await.unwrap();
+        repo.create(book("Emma", "Austen")).await.unwrap();
+        repo.create(book("Persuasion", "Austen")).await.unwrap();
+
+        let all = repo.get_all(&filters(&[])).await.unwrap();
+        assert_eq!(all.len(), 3);
+
+        let austen = repo
+            .get_all(&filters(&[("author", "Austen")]))
+            .await
+            .unwrap();
+        assert_eq!(titles(&austen), vec!["Emma", "Persuasion"]);
+
+        let one = repo
+            .get_all(&filters(&[("author", "Austen"), ("limit", "1")]))
+            .await
+            .unwrap();
+        assert_eq!(one.len(), 1);
+    }
+
+    async fn list_sorts_and_pages(repo: &dyn Repository<Book>) {
+        repo.create(book("Emma", "Austen")).await.unwrap();
+        repo.create(book("Dune", "Herbert")).await.unwrap();
+        repo.create(book("Persuasion", "Austen")).await.unwrap();
+
+        let asc = repo.get_all(&filters(&[("sort", "title")])).await.unwrap();
+        assert_eq!(titles(&asc), vec!["Dune", "Emma", "Persuasion"]);
+
+        let desc = repo
+            .get_all(&filters(&[("sort", "-title"), ("offset", "1")]))
+            .await
+            .unwrap();
+        assert_eq!(titles(&desc), vec!["Emma", "Dune"]);
+    }
+
+    async fn update_keeps_missing_fields(repo: &dyn Repository<Book>) {
+        let id = repo.create(book("Dune", "Herbert")).await.unwrap();
+        let patch = Book {
+            id: None,
+            title: Some("Dune Messiah".to_string()),
+            author: None,
+            publisher_id: None,
+            isbn: None,
+            publication_date: None,
+            language: None,
+            page_count: None,
+            edition: None,
+            description: None,
+            average_rating: None,
+            review_count: None,
+            copy_count: None,
+            available_copies: None,
+            contributors: None,
+            series: None,
+            metadata: None,
+        };
+        repo.update(patch, id).await.unwrap();
+
+        let b = repo.get_one(id).await.unwrap();
+        assert_eq!(b.title.as_deref(), Some("Dune Messiah"));
+        assert_eq!(b.author.as_deref(), Some("Herbert"));
+    }
+
+    async fn delete_removes(repo: &dyn Repository<Book>) {
+        let id = repo.create(book("Dune", "Herbert")).await.unwrap();
+        repo.delete(id).await.unwrap();
+
+        assert!(matches!(repo.get_one(id).await, Err(Error::RowNotFound)));
+        assert!(repo.get_all(&filters(&[])).await.unwrap().is_empty());
+    }
+
+    macro_rules! contract {
+        ($($backend:ident),* => $names:tt) => {
+            $(contract!(@backend $backend $names);)*
+        };
+        (@backend $backend:ident [$($name:ident),*]) => {
+            mod $backend {
+                $(
+                    #[actix_web::test]
+                    async fn $name() {
+                        super::$name(&super::$backend().await).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    contract!(sqlite, memory => [
+        create_and_get,
+        create_assigns_new_ids,
+        get_missing,
+        list_filters_by_column_and_limit,
+        list_sorts_and_pages,
+        update_keeps_missing_fields,
+        delete_removes
+    ]);
+}
@@ -0,0 +1,88 @@
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, PartialEq, PartialOrd)]
+#[serde(untagged)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Integer(i) => s.parse::<i64>() == Ok(*i),
+            Value::Real(f) => s.parse::<f64>() == Ok(*f),
+            Value::Text(t) => t == s,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Real(r) => write!(f, "{}", r),
+            Value::Text(t) => write!(f, "{}", t),
+        }
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(v: Option<String>) -> Self {
+        v.map_or(Value::Null, Value::Text)
+    }
+}
+
+impl From<Option<i64>> for Value {
+    fn from(v: Option<i64>) -> Self {
+        v.map_or(Value::Null, Value::Integer)
+    }
+}
+
+impl From<Option<f64>> for Value {
+    fn from(v: Option<f64>) -> Self {
+        v.map_or(Value::Null, Value::Real)
+    }
+}
+
+impl From<Value> for Option<String> {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => None,
+            Value::Integer(i) => Some(i.to_string()),
+            Value::Real(f) => Some(f.to_string()),
+            Value::Text(t) => Some(t),
+        }
+    }
+}
+
+impl From<Value> for Option<i64> {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => None,
+            Value::Integer(i) => Some(i),
+            Value::Real(f) => Some(f as i64),
+            Value::Text(t) => t.parse().ok(),
+        }
+    }
+}
+
+impl From<Value> for Option<f64> {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => None,
+            Value::Integer(i) => Some(i as f64),
+            Value::Real(f) => Some(f),
+            Value::Text(t) => t.parse().ok(),
+        }
+    }
+}
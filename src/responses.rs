@@ -12,9 +12,22 @@ impl CustomError {
             message: e.to_string(),
         }
     }
+
+    pub fn message(message: impl Into<String>) -> Self {
+        CustomError {
+            message: message.into(),
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct CreateResponse {
     pub id: i64,
 }
+
+#[derive(Serialize)]
+pub struct ConflictResponse {
+    pub message: String,
+    pub id: i64,
+    pub location: String,
+}
@@ -0,0 +1,5 @@
+pub mod review;
+#[allow(clippy::module_inception)]
+pub mod reviews;
+mod reviews_db;
+mod reviews_queries;
@@ -0,0 +1,37 @@
+use super::super::resources::resource::validate_not_blank;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Review {
+    pub id: Option<i64>,
+    #[serde(skip_deserializing)]
+    pub book_id: Option<i64>,
+    pub reviewer: Option<String>,
+    pub rating: Option<i64>,
+    pub text: Option<String>,
+    #[serde(skip_deserializing)]
+    pub created_at: Option<String>,
+    #[serde(skip_deserializing)]
+    pub updated_at: Option<String>,
+}
+
+impl Review {
+    pub fn normalize(&mut self) {
+        self.reviewer = self.reviewer.as_deref().map(|r| r.trim().to_string());
+    }
+
+    pub fn validate(&self, creating: bool) -> Result<(), String> {
+        if creating && self.reviewer.is_none() {
+            return Err("reviewer is required".to_string());
+        }
+        if creating && self.rating.is_none() {
+            return Err("rating is required".to_string());
+        }
+        validate_not_blank("reviewer", &self.reviewer)?;
+        match self.rating {
+            Some(r) if !(1..=5).contains(&r) => Err("rating must be between 1 and 5".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::responses::{ConflictResponse, CreateResponse, CustomError};
+use super::review::Review;
+use super::reviews_db::{self, Created};
+
+pub fn config_reviews(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_reviews)
+        .service(get_review)
+        .service(create_review)
+        .service(update_review)
+        .service(delete_review);
+}
+
+#[get("/books/{book_id}/reviews")]
+async fn get_reviews(book_id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = reviews_db::get_reviews(pool.get_ref(), book_id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/books/{book_id}/reviews/{id}")]
+async fn get_review(path: web::Path<(i64, i64)>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let r = reviews_db::get_review(pool.get_ref(), book_id, id).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[post("/books/{book_id}/reviews")]
+async fn create_review(
+    book_id: web::Path<i64>,
+    json: web::Json<Review>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let book_id = book_id.into_inner();
+    let mut review = json.into_inner();
+    review.normalize();
+    if let Err(e) = review.validate(true) {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = reviews_db::create_review(pool.get_ref(), book_id, review).await;
+
+    match r {
+        Ok(Created::Review(id)) => HttpResponse::Created().json(CreateResponse { id }),
+        Ok(Created::Duplicate(id)) => HttpResponse::Conflict().json(ConflictResponse {
+            message: "this reviewer has already reviewed the book".to_string(),
+            id,
+            location: format!("/books/{}/reviews/{}", book_id, id),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The reviewer of an existing review cannot be changed.
+#[put("/books/{book_id}/reviews/{id}")]
+async fn update_review(
+    path: web::Path<(i64, i64)>,
+    json: web::Json<Review>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let review = json.into_inner();
+    if let Err(e) = review.validate(false) {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let r = reviews_db::update_review(pool.get_ref(), book_id, id, review).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Updated"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[delete("/books/{book_id}/reviews/{id}")]
+async fn delete_review(
+    path: web::Path<(i64, i64)>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (book_id, id) = path.into_inner();
+    let r = reviews_db::delete_review(pool.get_ref(), book_id, id).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_reviews),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! review {
+        ($app:expr, $book_id:expr, $body:expr) => {{
+            let req = test::TestRequest::post()
+                .uri(&format!("/books/{}/reviews", $book_id))
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_create_review_updates_book_rating() {
+        let conn_pool = test_pool().await;
+        let id = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = review!(
+            app,
+            id,
+            json!({"reviewer": "ann", "rating": 5, "text": "Great"})
+        );
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+        let resp = review!(app, id, json!({"reviewer": "bob", "rating": 2}));
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let b = get_json!(app, format!("/books/{}", id));
+        assert_eq!(b["average_rating"], 3.5);
+        assert_eq!(b["review_count"], 2);
+
+        let r = get_json!(app, format!("/books/{}/reviews/{}", id, created["id"]));
+        assert_eq!(r["reviewer"], "ann");
+        assert_eq!(r["rating"], 5);
+        assert_eq!(r["text"], "Great");
+        assert!(r["created_at"].is_string());
+
+        let list = get_json!(app, format!("/books/{}/reviews", id));
+        assert_eq!(list.as_array().unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_create_review_once_per_reviewer() {
+        let conn_pool = test_pool().await;
+        let id = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let resp = review!(app, id, json!({"reviewer": "ann", "rating": 5}));
+        let created: Value = test::read_body_json(resp).await;
+
+        let resp = review!(app, id, json!({"reviewer": " ANN ", "rating": 1}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], created["id"]);
+
+        let b = get_json!(app, format!("/books/{}", id));
+        assert_eq!(b["review_count"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_create_review_bad_request() {
+        let conn_pool = test_pool().await;
+        let id = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        for (body, message) in [
+            (
+                json!({"reviewer": "ann", "rating": 6}),
+                "rating must be between 1 and 5",
+            ),
+            (json!({"reviewer": "ann"}), "rating is required"),
+            (json!({"rating": 3}), "reviewer is required"),
+        ] {
+            let resp = review!(app, id, body);
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_update_and_delete_review_refresh_rating() {
+        let conn_pool = test_pool().await;
+        let id = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let resp = review!(
+            app,
+            id,
+            json!({"reviewer": "ann", "rating": 5, "text": "Great"})
+        );
+        let created: Value = test::read_body_json(resp).await;
+        let uri = format!("/books/{}/reviews/{}", id, created["id"]);
+
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .set_json(json!({"rating": 3}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let r = get_json!(app, uri);
+        assert_eq!(r["rating"], 3);
+        assert_eq!(r["text"], "Great");
+        assert_eq!(
+            get_json!(app, format!("/books/{}", id))["average_rating"],
+            3.0
+        );
+
+        let req = test::TestRequest::delete().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let b = get_json!(app, format!("/books/{}", id));
+        assert_eq!(b["average_rating"], Value::Null);
+        assert_eq!(b["review_count"], 0);
+    }
+
+    #[actix_web::test]
+    async fn test_review_unknown_book() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let resp = review!(app, 42, json!({"reviewer": "ann", "rating": 5}));
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_by_rating() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await.id.unwrap();
+        let emma = book().title("Emma").create(&conn_pool).await.id.unwrap();
+        book().title("Unrated").create(&conn_pool).await;
+        let app = app!(conn_pool);
+        review!(app, dune, json!({"reviewer": "ann", "rating": 5}));
+        review!(app, emma, json!({"reviewer": "ann", "rating": 3}));
+
+        let titles = |body: &Value| -> Vec<String> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let body = get_json!(app, "/books?min_rating=4");
+        assert_eq!(titles(&body), vec!["Dune"]);
+        let body = get_json!(app, "/books?max_rating=4&min_rating=1");
+        assert_eq!(titles(&body), vec!["Emma"]);
+        let body = get_json!(app, "/books?sort=-average_rating");
+        assert_eq!(titles(&body), vec!["Dune", "Emma", "Unrated"]);
+    }
+}
@@ -0,0 +1,116 @@
+use super::review::Review;
+use super::reviews_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Created {
+    Review(i64),
+    // The reviewer already reviewed this book, with the given review id.
+    Duplicate(i64),
+}
+
+async fn ensure_book(tx: &mut Transaction<'_, Sqlite>, book_id: i64) -> Result<(), Error> {
+    sqlx::query(&reviews_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(tx)
+        .await?;
+
+    Ok(())
+}
+
+async fn refresh_book_rating(tx: &mut Transaction<'_, Sqlite>, book_id: i64) -> Result<(), Error> {
+    sqlx::query(&reviews_queries::refresh_book_rating_query())
+        .bind(book_id)
+        .execute(tx)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_reviews(pool: &Pool<Sqlite>, book_id: i64) -> Result<Vec<Review>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_book(&mut tx, book_id).await?;
+
+    sqlx::query_as::<_, Review>(&reviews_queries::get_reviews_query())
+        .bind(book_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+pub async fn get_review(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    review_id: i64,
+) -> Result<Review, Error> {
+    sqlx::query_as::<_, Review>(&reviews_queries::get_review_query())
+        .bind(book_id)
+        .bind(review_id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn create_review(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    review: Review,
+) -> Result<Created, Error> {
+    let mut tx = pool.begin().await?;
+    ensure_book(&mut tx, book_id).await?;
+
+    let existing: Option<i64> =
+        sqlx::query_scalar(&reviews_queries::get_review_by_reviewer_query())
+            .bind(book_id)
+            .bind(&review.reviewer)
+            .fetch_optional(&mut tx)
+            .await?;
+    if let Some(id) = existing {
+        return Ok(Created::Duplicate(id));
+    }
+
+    let r = sqlx::query(&reviews_queries::create_review_query())
+        .bind(book_id)
+        .bind(review.reviewer)
+        .bind(review.rating)
+        .bind(review.text)
+        .execute(&mut tx)
+        .await?;
+    refresh_book_rating(&mut tx, book_id).await?;
+    tx.commit().await?;
+
+    Ok(Created::Review(r.last_insert_rowid()))
+}
+
+pub async fn update_review(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    review_id: i64,
+    review: Review,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    let r = sqlx::query(&reviews_queries::update_review_query())
+        .bind(review.rating)
+        .bind(review.text)
+        .bind(book_id)
+        .bind(review_id)
+        .execute(&mut tx)
+        .await?;
+    if r.rows_affected() == 0 {
+        return Err(Error::RowNotFound);
+    }
+    refresh_book_rating(&mut tx, book_id).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_review(pool: &Pool<Sqlite>, book_id: i64, review_id: i64) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&reviews_queries::delete_review_query())
+        .bind(book_id)
+        .bind(review_id)
+        .execute(&mut tx)
+        .await?;
+    refresh_book_rating(&mut tx, book_id).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::super::constants::{BOOKS_TABLE, REVIEWS_TABLE};
+
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+pub fn get_reviews_query() -> String {
+    format!(
+        "Select * From {} where book_id=? order by created_at desc, id desc",
+        REVIEWS_TABLE
+    )
+}
+
+pub fn get_review_query() -> String {
+    format!("Select * From {} where book_id=? and id=?", REVIEWS_TABLE)
+}
+
+pub fn get_review_by_reviewer_query() -> String {
+    format!(
+        "Select id From {} where book_id=? and reviewer=?",
+        REVIEWS_TABLE
+    )
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn create_review_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, reviewer, rating, text, created_at, updated_at) \
+         values (?, ?, ?, ?, {now}, {now}) RETURNING id",
+        REVIEWS_TABLE,
+        now = NOW
+    )
+}
+
+// Fields that are not sent keep their value.
+pub fn update_review_query() -> String {
+    format!(
+        "UPDATE {} SET rating=coalesce(?, rating), text=coalesce(?, text), updated_at={} \
+         where book_id=? and id=?",
+        REVIEWS_TABLE, NOW
+    )
+}
+
+pub fn delete_review_query() -> String {
+    format!("DELETE From {} where book_id=? and id=?", REVIEWS_TABLE)
+}
+
+pub fn refresh_book_rating_query() -> String {
+    format!(
+        "UPDATE {books} SET \
+         average_rating=(SELECT avg(rating) From {reviews} where book_id={books}.id), \
+         review_count=(SELECT count(*) From {reviews} where book_id={books}.id) \
+         where id=?",
+        books = BOOKS_TABLE,
+        reviews = REVIEWS_TABLE
+    )
+}
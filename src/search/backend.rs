@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::Error;
+
+pub use super::super::autocomplete::index::Kind;
+
+pub const MAX_LIMIT: usize = 100;
+// Backends collect `limit + offset` hits up front, so deep pages are refused.
+pub const MAX_OFFSET: usize = 10_000;
+pub const MAX_FUZZY: u8 = 2;
+
+// A book by its title, author and contributors, or an author by their name
+// and aliases. `language` picks the stemmer of the title and body.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub kind: Kind,
+    pub id: i64,
+    pub title: String,
+    pub detail: Option<String>,
+    pub names: Vec<String>,
+    pub body: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub kind: Kind,
+    pub id: i64,
+    pub label: String,
+    pub detail: Option<String>,
+    pub score: f32,
+}
+
+// Every word of `text` must match. `fuzzy` is the number of typos allowed
+// per word.
+pub struct Query {
+    pub text: String,
+    pub kinds: Vec<Kind>,
+    pub fuzzy: u8,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+// How much a match in each field weighs.
+#[derive(Debug, Clone, Copy)]
+pub struct Boosts {
+    pub title: f32,
+    pub names: f32,
+    pub body: f32,
+}
+
+// Where books and authors are searched. The documents come from the
+// database, a backend only indexes them.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    async fn is_empty(&self) -> Result<bool, Error>;
+
+    // Adds the documents, replacing any previous version.
+    async fn upsert(&self, documents: Vec<Document>) -> Result<(), Error>;
+
+    async fn remove(&self, removed: Vec<(Kind, i64)>) -> Result<(), Error>;
+
+    // Drops everything indexed for `documents`.
+    async fn rebuild(&self, documents: Vec<Document>) -> Result<(), Error>;
+
+    // Best hits first. Backends without typo tolerance ignore `fuzzy`.
+    async fn search(&self, query: &Query) -> Result<Vec<Hit>, Error>;
+}
+
+// The words of a query, as the backends match them.
+pub fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
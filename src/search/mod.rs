@@ -0,0 +1,8 @@
+pub mod backend;
+#[allow(clippy::module_inception)]
+pub mod search;
+mod search_db;
+mod search_queries;
+pub mod spelling;
+pub mod sqlite_backend;
+pub mod tantivy_backend;
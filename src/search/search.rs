@@ -0,0 +1,593 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, Pool, Sqlite};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+use super::super::books::books_db;
+use super::super::books::books_queries::books_with_ids_clause;
+use super::super::books::facet::{self, Facet, FacetCount};
+use super::super::resources::filter::Filters;
+use super::super::responses::CustomError;
+use super::backend::{Backend, Hit, Kind, Query, MAX_FUZZY, MAX_LIMIT, MAX_OFFSET};
+use super::search_db;
+use super::spelling::{self, Dictionary, DidYouMean};
+
+pub const DEFAULT_LIMIT: usize = 20;
+// Facets count at most this many of the best matching books.
+pub const MAX_FACET_HITS: usize = 10_000;
+
+// The configured backend, kept in step with the database through the changes
+// its triggers record, and the dictionary spelling suggestions come from.
+pub struct Search {
+    backend: Box<dyn Backend>,
+    dictionary: RwLock<Dictionary>,
+    // The last change applied, once this process has looked at the index.
+    last_change: Mutex<Option<i64>>,
+}
+
+impl Search {
+    pub fn new(backend: Box<dyn Backend>) -> Self {
+        Search {
+            backend,
+            dictionary: RwLock::new(Dictionary::default()),
+            last_change: Mutex::new(None),
+        }
+    }
+
+    // An index that was never filled is built from scratch; an existing one
+    // catches up on the changes it missed, including before a restart. The
+    // dictionary only lives in memory and is always loaded in full first.
+    async fn refresh(&self, pool: &Pool<Sqlite>) -> Result<(), Error> {
+        let mut last_change = self.last_change.lock().await;
+        let after = match *last_change {
+            Some(last) => last,
+            None => {
+                let indexed = !self.backend.is_empty().await?;
+                let last = self.load(pool, !indexed).await?.0;
+                if !indexed {
+                    *last_change = Some(last);
+                    return Ok(());
+                }
+                0
+            }
+        };
+
+        let changes = search_db::get_changes(pool, after).await?;
+        {
+            let mut dictionary = self.dictionary.write().unwrap();
+            for document in &changes.documents {
+                dictionary.insert(document);
+            }
+            for (kind, id) in &changes.removed {
+                dictionary.remove(*kind, *id);
+            }
+        }
+        if !changes.documents.is_empty() {
+            self.backend.upsert(changes.documents).await?;
+        }
+        if !changes.removed.is_empty() {
+            self.backend.remove(changes.removed).await?;
+        }
+        *last_change = Some(changes.last);
+        search_db::delete_changes(pool, changes.last).await
+    }
+
+    // Loads the dictionary and, with `rebuild`, the index. Returns the last
+    // change covered and the number of documents.
+    async fn load(&self, pool: &Pool<Sqlite>, rebuild: bool) -> Result<(i64, usize), Error> {
+        let last = search_db::get_last_change(pool).await?;
+        let documents = search_db::get_documents(pool).await?;
+        let count = documents.len();
+        *self.dictionary.write().unwrap() = Dictionary::build(&documents);
+        if rebuild {
+            self.backend.rebuild(documents).await?;
+            search_db::delete_changes(pool, last).await?;
+        }
+        Ok((last, count))
+    }
+
+    pub async fn rebuild(&self, pool: &Pool<Sqlite>) -> Result<usize, Error> {
+        let mut last_change = self.last_change.lock().await;
+        let (last, count) = self.load(pool, true).await?;
+        *last_change = Some(last);
+        Ok(count)
+    }
+
+    pub async fn search(&self, pool: &Pool<Sqlite>, query: &Query) -> Result<Vec<Hit>, Error> {
+        self.refresh(pool).await?;
+        self.backend.search(query).await
+    }
+
+    // `text` spelt as the indexed titles and names are, if it is not already.
+    pub async fn suggest(&self, pool: &Pool<Sqlite>, text: &str) -> Result<Option<String>, Error> {
+        self.refresh(pool).await?;
+        Ok(self.dictionary.read().unwrap().correct(text))
+    }
+}
+
+pub fn config_search(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_search).service(rebuild_search);
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    pub q: Option<String>,
+    pub types: Option<String>,
+    pub fuzzy: Option<u8>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+// The body of `/search`, and of `/books` with `envelope=true`, which has the
+// same keys whatever was asked and found. `facets` is empty unless asked for,
+// `did_you_mean` null unless nothing was found and the query can be corrected.
+#[derive(Serialize)]
+pub struct Hits<T> {
+    pub hits: Vec<T>,
+    pub facets: BTreeMap<&'static str, Vec<FacetCount>>,
+    pub did_you_mean: Option<DidYouMean>,
+}
+
+// The correction of `text`, given as `key` among `params`, with the link
+// to `path` that runs it.
+pub async fn did_you_mean(
+    search: &Search,
+    pool: &Pool<Sqlite>,
+    path: &str,
+    params: &[(String, String)],
+    key: &str,
+    text: &str,
+) -> Result<Option<DidYouMean>, Error> {
+    Ok(search
+        .suggest(pool, text)
+        .await?
+        .map(|corrected| DidYouMean {
+            link: spelling::link(path, params, key, &corrected),
+            text: corrected,
+        }))
+}
+
+// Counts for each of `facets` over the books matching `query`, whatever page
+// of them is shown.
+async fn get_facets(
+    search: &Search,
+    pool: &Pool<Sqlite>,
+    query: &Query,
+    facets: Vec<Facet>,
+    limit: u32,
+) -> Result<BTreeMap<&'static str, Vec<FacetCount>>, Error> {
+    let mut counts = BTreeMap::new();
+    if facets.is_empty() {
+        return Ok(counts);
+    }
+    let ids: Vec<i64> = if query.kinds.contains(&Kind::Book) {
+        let books = Query {
+            text: query.text.clone(),
+            kinds: vec![Kind::Book],
+            fuzzy: query.fuzzy,
+            limit: MAX_FACET_HITS,
+            offset: 0,
+        };
+        search
+            .search(pool, &books)
+            .await?
+            .iter()
+            .map(|h| h.id)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let filter = Filters {
+        clauses: vec![books_with_ids_clause(&ids)],
+        ..Filters::default()
+    };
+    for facet in facets {
+        let r = books_db::get_facet_counts(pool, facet, &filter, limit).await?;
+        counts.insert(facet.as_str(), r);
+    }
+    Ok(counts)
+}
+
+// Full-text search over book titles, authors and descriptions, and over
+// author names, aliases and biographies. Every word has to match.
+#[get("/search")]
+async fn get_search(
+    params: web::Query<SearchParams>,
+    raw: web::Query<Vec<(String, String)>>,
+    search: web::Data<Search>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let text = match &params.q {
+        Some(q) => q.clone(),
+        None => return HttpResponse::BadRequest().json(CustomError::message("q is required")),
+    };
+    let kinds = match &params.types {
+        None => Kind::ALL.to_vec(),
+        Some(types) => match types.split(',').map(|t| Kind::parse(t.trim())).collect() {
+            Ok(kinds) => kinds,
+            Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+        },
+    };
+    let fuzzy = params.fuzzy.unwrap_or(0);
+    if fuzzy > MAX_FUZZY {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "fuzzy must be between 0 and {} (got {})",
+            MAX_FUZZY, fuzzy
+        )));
+    }
+    let (facets, facet_limit) = match facet::parse_params(&raw) {
+        Ok(f) => f,
+        Err(e) => return HttpResponse::BadRequest().json(CustomError::message(e)),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "limit must be between 1 and {} (got {})",
+            MAX_LIMIT, limit
+        )));
+    }
+    let offset = params.offset.unwrap_or(0);
+    if offset > MAX_OFFSET {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "offset must be at most {} (got {})",
+            MAX_OFFSET, offset
+        )));
+    }
+    let query = Query {
+        text,
+        kinds,
+        fuzzy,
+        limit,
+        offset,
+    };
+
+    let hits = match search.search(pool.get_ref(), &query).await {
+        Ok(hits) => hits,
+        Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+    };
+    let facets = match get_facets(&search, pool.get_ref(), &query, facets, facet_limit).await {
+        Ok(f) => f,
+        Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+    };
+    let did_you_mean = if hits.is_empty() {
+        match did_you_mean(&search, pool.get_ref(), "/search", &raw, "q", &query.text).await {
+            Ok(d) => d,
+            Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+        }
+    } else {
+        None
+    };
+    HttpResponse::Ok().json(Hits {
+        hits,
+        facets,
+        did_you_mean,
+    })
+}
+
+#[derive(Serialize)]
+pub struct Rebuilt {
+    pub documents: usize,
+}
+
+// Reindexes every book and author, e.g. after changing backend or boosts.
+#[post("/admin/search/rebuild")]
+async fn rebuild_search(
+    search: web::Data<Search>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    match search.rebuild(pool.get_ref()).await {
+        Ok(documents) => HttpResponse::Ok().json(Rebuilt { documents }),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::backend::{Backend, Boosts};
+    use super::super::sqlite_backend::SqliteBackend;
+    use super::super::tantivy_backend::TantivyBackend;
+    use crate::test_utils::{self, author, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    const BOOSTS: Boosts = Boosts {
+        title: 3.0,
+        names: 2.0,
+        body: 1.0,
+    };
+
+    macro_rules! app {
+        ($pool:expr, $backend:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(super::Search::new($backend)))
+                    .configure(authors::authors::config_authors)
+                    .configure(books::books::config_books)
+                    .configure(super::config_search),
+            )
+            .await
+        }};
+    }
+
+    fn backends(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Box<dyn Backend>> {
+        vec![
+            Box::new(SqliteBackend::new(pool.clone(), BOOSTS)),
+            Box::new(TantivyBackend::in_memory(BOOSTS).unwrap()),
+        ]
+    }
+
+    fn labels_of<'a>(values: &'a Value, key: &str) -> Vec<&'a str> {
+        values
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v[key].as_str().unwrap())
+            .collect()
+    }
+
+    fn hits_of(body: Value) -> Value {
+        assert!(body.get("facets").is_some() && body.get("did_you_mean").is_some());
+        body["hits"].clone()
+    }
+
+    fn labels(hits: &Value) -> Vec<&str> {
+        labels_of(hits, "label")
+    }
+
+    #[actix_web::test]
+    async fn test_search() {
+        let conn_pool = test_pool().await;
+        book()
+            .title("Dune")
+            .author("Frank Herbert")
+            .language("en")
+            .create(&conn_pool)
+            .await;
+        book()
+            .title("The Dragon Reborn")
+            .author("Robert Jordan")
+            .create(&conn_pool)
+            .await;
+        author().name("Frank Herbert").create(&conn_pool).await;
+
+        for backend in backends(&conn_pool) {
+            let app = app!(conn_pool, backend);
+
+            // Titles weigh more than names.
+            let req = test::TestRequest::get()
+                .uri("/search?q=herbert")
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Frank Herbert", "Dune"]);
+            assert_eq!(hits[0]["kind"], "author");
+            assert_eq!(hits[1]["detail"], "Frank Herbert");
+
+            let req = test::TestRequest::get()
+                .uri("/search?q=dune%20frank&types=books")
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Dune"]);
+
+            let req = test::TestRequest::get()
+                .uri("/search?q=herbert&limit=1&offset=1")
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Dune"]);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_search_follows_changes() {
+        let conn_pool = test_pool().await;
+        for backend in backends(&conn_pool) {
+            let dune = book().title("Dune").create(&conn_pool).await;
+            let app = app!(conn_pool, backend);
+            let req = test::TestRequest::get().uri("/search?q=dune").to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Dune"]);
+
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}", dune.id.unwrap()))
+                .set_json(json!({"title": "Dune", "description": "Sandworms of Arrakis."}))
+                .to_request();
+            test::call_service(&app, req).await;
+            let req = test::TestRequest::get()
+                .uri("/search?q=arrakis")
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Dune"]);
+
+            // The rebuild catches what the change log no longer holds.
+            sqlx::query("DELETE FROM books")
+                .execute(&conn_pool)
+                .await
+                .unwrap();
+            sqlx::query("DELETE FROM search_changes")
+                .execute(&conn_pool)
+                .await
+                .unwrap();
+            let req = test::TestRequest::post()
+                .uri("/admin/search/rebuild")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(body, json!({"documents": 0}));
+            let req = test::TestRequest::get().uri("/search?q=dune").to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(hits, json!([]));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_did_you_mean() {
+        let conn_pool = test_pool().await;
+        book()
+            .title("Dune Messiah")
+            .author("Frank Herbert")
+            .create(&conn_pool)
+            .await;
+        book().title("Children of Dune").create(&conn_pool).await;
+
+        for backend in backends(&conn_pool) {
+            let app = app!(conn_pool, backend);
+
+            let req = test::TestRequest::get()
+                .uri("/search?q=dunr%20mesiah&limit=5")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(
+                body,
+                json!({
+                    "hits": [],
+                    "facets": {},
+                    "did_you_mean": {"text": "Dune Messiah", "link": "/search?q=Dune+Messiah&limit=5"}
+                })
+            );
+            let req = test::TestRequest::get()
+                .uri(body["did_you_mean"]["link"].as_str().unwrap())
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels(&hits), ["Dune Messiah"]);
+
+            // The book list only has room for a correction in its envelope.
+            let req = test::TestRequest::get()
+                .uri("/books?title=Dune%20Mesiah")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(body, json!([]));
+            let req = test::TestRequest::get()
+                .uri("/books?title=Dune%20Mesiah&envelope=true")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(body["hits"], json!([]));
+            assert_eq!(
+                body["did_you_mean"]["link"],
+                "/books?title=Dune+Messiah&envelope=true"
+            );
+            let req = test::TestRequest::get()
+                .uri(body["did_you_mean"]["link"].as_str().unwrap())
+                .to_request();
+            let books = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(labels_of(&books, "title"), ["Dune Messiah"]);
+
+            // Nothing close enough, nothing suggested.
+            for uri in [
+                "/search?q=xylophone",
+                "/books?title=Xylophone&envelope=true",
+            ] {
+                let req = test::TestRequest::get().uri(uri).to_request();
+                let body: Value = test::call_and_read_body_json(&app, req).await;
+                assert_eq!(
+                    body,
+                    json!({"hits": [], "facets": {}, "did_you_mean": null}),
+                    "{}",
+                    uri
+                );
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_search_facets() {
+        let conn_pool = test_pool().await;
+        for (title, author, published) in [
+            ("Dune", "Frank Herbert", "1965-08-01"),
+            ("Dune Messiah", "Frank Herbert", "1969"),
+            ("The Dune Encyclopedia", "Willis McNelly", "1984"),
+            ("Emma", "Jane Austen", "1815"),
+        ] {
+            book()
+                .title(title)
+                .author(author)
+                .published(published)
+                .create(&conn_pool)
+                .await;
+        }
+        author().name("Dune Fan").create(&conn_pool).await;
+
+        for backend in backends(&conn_pool) {
+            let app = app!(conn_pool, backend);
+
+            // Every matching book is counted, not only those on the page.
+            let req = test::TestRequest::get()
+                .uri("/search?q=dune&facets=author,decade&limit=1")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(body["hits"].as_array().unwrap().len(), 1);
+            assert_eq!(
+                body["facets"],
+                json!({
+                    "author": [
+                        {"value": "Frank Herbert", "count": 2},
+                        {"value": "Willis McNelly", "count": 1}
+                    ],
+                    "decade": [
+                        {"value": "1960s", "count": 2},
+                        {"value": "1980s", "count": 1}
+                    ]
+                })
+            );
+
+            // Only books have facets.
+            let req = test::TestRequest::get()
+                .uri("/search?q=dune&types=authors&facets=author")
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(labels(&body["hits"]), ["Dune Fan"]);
+            assert_eq!(body["facets"], json!({"author": []}));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_search_bad_request() {
+        let conn_pool = test_pool().await;
+        book().title("Dune").create(&conn_pool).await;
+
+        for backend in backends(&conn_pool) {
+            let app = app!(conn_pool, backend);
+            // The deepest page allowed is still served, past the last hit.
+            let req = test::TestRequest::get()
+                .uri("/search?q=dune&offset=10000")
+                .to_request();
+            let hits = hits_of(test::call_and_read_body_json(&app, req).await);
+            assert_eq!(hits, json!([]));
+
+            for (uri, message) in [
+                ("/search", "q is required"),
+                (
+                    "/search?q=a&types=tags",
+                    "types must be books or authors (got tags)",
+                ),
+                (
+                    "/search?q=a&fuzzy=3",
+                    "fuzzy must be between 0 and 2 (got 3)",
+                ),
+                (
+                    "/search?q=a&limit=0",
+                    "limit must be between 1 and 100 (got 0)",
+                ),
+                (
+                    "/search?q=a&offset=1000000000000",
+                    "offset must be at most 10000 (got 1000000000000)",
+                ),
+                (
+                    "/search?q=a&facets=publisher",
+                    "facets must be one of author, tag, language, decade (got publisher)",
+                ),
+            ] {
+                let req = test::TestRequest::get().uri(uri).to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{}", uri);
+                let body: Value = test::read_body_json(resp).await;
+                assert_eq!(body["message"], message);
+            }
+        }
+    }
+}
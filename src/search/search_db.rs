@@ -0,0 +1,138 @@
+use super::backend::{Document, Kind};
+use super::search_queries;
+use sqlx::{Error, FromRow, Pool, Sqlite};
+
+#[derive(FromRow)]
+struct BookRow {
+    id: i64,
+    title: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    language: Option<String>,
+    names: Option<String>,
+}
+
+#[derive(FromRow)]
+struct AuthorRow {
+    id: i64,
+    name: Option<String>,
+    biography: Option<String>,
+    names: Option<String>,
+}
+
+fn split(names: Option<String>) -> Vec<String> {
+    names
+        .map(|n| n.split('\u{1f}').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+impl From<BookRow> for Document {
+    fn from(row: BookRow) -> Self {
+        let mut names = split(row.names);
+        names.extend(row.author.clone());
+        Document {
+            kind: Kind::Book,
+            id: row.id,
+            title: row.title.unwrap_or_default(),
+            detail: row.author,
+            names,
+            body: row.description,
+            language: row.language,
+        }
+    }
+}
+
+impl From<AuthorRow> for Document {
+    fn from(row: AuthorRow) -> Self {
+        Document {
+            kind: Kind::Author,
+            id: row.id,
+            title: row.name.unwrap_or_default(),
+            detail: None,
+            names: split(row.names),
+            body: row.biography,
+            language: None,
+        }
+    }
+}
+
+pub async fn get_last_change(pool: &Pool<Sqlite>) -> Result<i64, Error> {
+    sqlx::query_scalar(&search_queries::get_last_change_query())
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_documents(pool: &Pool<Sqlite>) -> Result<Vec<Document>, Error> {
+    let books: Vec<BookRow> = sqlx::query_as(&search_queries::get_books_query())
+        .fetch_all(pool)
+        .await?;
+    let authors: Vec<AuthorRow> = sqlx::query_as(&search_queries::get_authors_query())
+        .fetch_all(pool)
+        .await?;
+
+    let mut documents: Vec<Document> = books.into_iter().map(Document::from).collect();
+    documents.extend(authors.into_iter().map(Document::from));
+    Ok(documents)
+}
+
+pub struct Changes {
+    pub last: i64,
+    pub documents: Vec<Document>,
+    pub removed: Vec<(Kind, i64)>,
+}
+
+// The records changed since `after`, as they are now.
+pub async fn get_changes(pool: &Pool<Sqlite>, after: i64) -> Result<Changes, Error> {
+    let rows: Vec<(i64, String, i64)> = sqlx::query_as(&search_queries::get_changes_query())
+        .bind(after)
+        .fetch_all(pool)
+        .await?;
+    let last = rows.last().map_or(after, |(id, _, _)| *id);
+    let mut changed: Vec<(Kind, i64)> = rows
+        .into_iter()
+        .map(|(_, kind, id)| {
+            let kind = if kind == "book" {
+                Kind::Book
+            } else {
+                Kind::Author
+            };
+            (kind, id)
+        })
+        .collect();
+    changed.sort_unstable();
+    changed.dedup();
+
+    let mut changes = Changes {
+        last,
+        documents: Vec::new(),
+        removed: Vec::new(),
+    };
+    for (kind, id) in changed {
+        let document = match kind {
+            Kind::Book => sqlx::query_as::<_, BookRow>(&search_queries::get_book_query())
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .map(Document::from),
+            Kind::Author => sqlx::query_as::<_, AuthorRow>(&search_queries::get_author_query())
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .map(Document::from),
+        };
+        match document {
+            Some(document) => changes.documents.push(document),
+            None => changes.removed.push((kind, id)),
+        }
+    }
+    Ok(changes)
+}
+
+pub async fn delete_changes(pool: &Pool<Sqlite>, upto: i64) -> Result<(), Error> {
+    sqlx::query(&search_queries::delete_changes_query())
+        .bind(upto)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use super::super::constants::{
+    AUTHORS_TABLE, AUTHOR_ALIASES_TABLE, BOOKS_TABLE, BOOK_CONTRIBUTORS_TABLE,
+    SEARCH_CHANGES_TABLE, SEARCH_DOCUMENTS_TABLE,
+};
+
+// Names are joined with the unit separator, which never appears in them.
+fn books_query() -> String {
+    format!(
+        "Select b.id, b.title, b.author, b.description, b.language, \
+         (Select group_concat(a.name, char(31)) From {} c JOIN {} a ON a.id = c.author_id \
+           where c.book_id = b.id) AS names \
+         From {} b",
+        BOOK_CONTRIBUTORS_TABLE, AUTHORS_TABLE, BOOKS_TABLE
+    )
+}
+
+fn authors_query() -> String {
+    format!(
+        "Select a.id, a.name, a.biography, \
+         (Select group_concat(l.name, char(31)) From {} l where l.author_id = a.id) AS names \
+         From {} a",
+        AUTHOR_ALIASES_TABLE, AUTHORS_TABLE
+    )
+}
+
+pub fn get_books_query() -> String {
+    books_query()
+}
+
+pub fn get_book_query() -> String {
+    format!("{} where b.id=?", books_query())
+}
+
+pub fn get_authors_query() -> String {
+    authors_query()
+}
+
+pub fn get_author_query() -> String {
+    format!("{} where a.id=?", authors_query())
+}
+
+pub fn get_last_change_query() -> String {
+    format!("Select COALESCE(MAX(id), 0) From {}", SEARCH_CHANGES_TABLE)
+}
+
+pub fn get_changes_query() -> String {
+    format!(
+        "Select id, kind, ref_id From {} where id > ? order by id",
+        SEARCH_CHANGES_TABLE
+    )
+}
+
+pub fn delete_changes_query() -> String {
+    format!("DELETE From {} where id <= ?", SEARCH_CHANGES_TABLE)
+}
+
+pub fn is_empty_query() -> String {
+    format!(
+        "Select NOT EXISTS (Select 1 From {})",
+        SEARCH_DOCUMENTS_TABLE
+    )
+}
+
+pub fn clear_documents_query() -> String {
+    format!("DELETE From {}", SEARCH_DOCUMENTS_TABLE)
+}
+
+pub fn delete_document_query() -> String {
+    format!("DELETE From {} where rowid=?", SEARCH_DOCUMENTS_TABLE)
+}
+
+pub fn create_document_query() -> String {
+    format!(
+        "INSERT INTO {} (rowid, kind, ref_id, label, detail, title, names, body) \
+         values (?, ?, ?, ?, ?, ?, ?, ?)",
+        SEARCH_DOCUMENTS_TABLE
+    )
+}
+
+// Binds the title, names and body boosts, the match expression, then the
+// `kinds` and the limit and offset.
+pub fn search_query(kinds: usize) -> String {
+    let placeholders = vec!["?"; kinds].join(", ");
+    format!(
+        "Select kind, ref_id AS id, label, detail, \
+         -bm25({d}, 0, 0, 0, 0, ?, ?, ?) AS score \
+         From {d} where {d} MATCH ? and kind IN ({}) \
+         order by score desc, rowid limit ? offset ?",
+        placeholders,
+        d = SEARCH_DOCUMENTS_TABLE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_query() {
+        assert_eq!(
+            search_query(2),
+            "Select kind, ref_id AS id, label, detail, \
+             -bm25(search_documents, 0, 0, 0, 0, ?, ?, ?) AS score \
+             From search_documents where search_documents MATCH ? and kind IN (?, ?) \
+             order by score desc, rowid limit ? offset ?"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use super::super::similarity::{levenshtein, normalize};
+use super::backend::{Document, Kind};
+
+// A corrected query and where to run it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DidYouMean {
+    pub text: String,
+    pub link: String,
+}
+
+#[derive(Default)]
+struct Term {
+    count: u32,
+    chars: usize,
+    // How the term was written, e.g. `Dune` for `dune`, by frequency.
+    forms: HashMap<String, u32>,
+}
+
+impl Term {
+    fn form(&self) -> &str {
+        self.forms
+            .iter()
+            .max_by_key(|(form, count)| (**count, Reverse(form.as_str())))
+            .map_or("", |(form, _)| form)
+    }
+}
+
+// The words of every indexed title and author name, with how many times each
+// occurs. Words are compared normalized, as `similarity::normalize` does.
+#[derive(Default)]
+pub struct Dictionary {
+    terms: HashMap<String, Term>,
+    // The words each document added, to take them back out.
+    documents: HashMap<(Kind, i64), Vec<String>>,
+}
+
+fn words(document: &Document) -> Vec<String> {
+    std::iter::once(&document.title)
+        .chain(document.names.iter())
+        .flat_map(|text| {
+            spans(text)
+                .into_iter()
+                .map(|(start, end)| &text[start..end])
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+// The byte ranges of the words of `text`.
+fn spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans
+}
+
+// Longer words may be further off.
+fn max_distance(chars: usize) -> usize {
+    match chars {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+impl Dictionary {
+    pub fn build(documents: &[Document]) -> Self {
+        let mut dictionary = Dictionary::default();
+        for document in documents {
+            dictionary.insert(document);
+        }
+        dictionary
+    }
+
+    pub fn insert(&mut self, document: &Document) {
+        self.remove(document.kind, document.id);
+        let words = words(document);
+        for word in &words {
+            let key = normalize(word);
+            let chars = key.chars().count();
+            let term = self.terms.entry(key).or_default();
+            term.count += 1;
+            term.chars = chars;
+            *term.forms.entry(word.clone()).or_default() += 1;
+        }
+        self.documents.insert((document.kind, document.id), words);
+    }
+
+    pub fn remove(&mut self, kind: Kind, id: i64) {
+        for word in self.documents.remove(&(kind, id)).unwrap_or_default() {
+            let key = normalize(&word);
+            let term = self.terms.get_mut(&key).unwrap();
+            term.count -= 1;
+            if term.count == 0 {
+                self.terms.remove(&key);
+                continue;
+            }
+            let form = term.forms.get_mut(&word).unwrap();
+            *form -= 1;
+            if *form == 0 {
+                term.forms.remove(&word);
+            }
+        }
+    }
+
+    // The closest known word, the most frequent among equally close ones.
+    fn closest(&self, word: &str) -> Option<&Term> {
+        let key = normalize(word);
+        let chars = key.chars().count();
+        let max = max_distance(chars);
+        self.terms
+            .iter()
+            .filter(|(_, term)| term.chars.abs_diff(chars) <= max)
+            .map(|(k, term)| (levenshtein(&key, k), term, k))
+            .filter(|(distance, _, _)| *distance <= max)
+            .min_by_key(|(distance, term, k)| (*distance, Reverse(term.count), *k))
+            .map(|(_, term, _)| term)
+    }
+
+    // `text` with its unknown words replaced by the closest known ones, or
+    // nothing when it has no unknown word or one without a close match.
+    pub fn correct(&self, text: &str) -> Option<String> {
+        let mut corrected = String::with_capacity(text.len());
+        let mut changed = false;
+        let mut last = 0;
+        for (start, end) in spans(text) {
+            let word = &text[start..end];
+            corrected.push_str(&text[last..start]);
+            last = end;
+            let key = normalize(word);
+            // Words too short to correct are left as typed.
+            if self.terms.contains_key(&key) || max_distance(key.chars().count()) == 0 {
+                corrected.push_str(word);
+                continue;
+            }
+            corrected.push_str(self.closest(word)?.form());
+            changed = true;
+        }
+        corrected.push_str(&text[last..]);
+
+        if changed {
+            Some(corrected)
+        } else {
+            None
+        }
+    }
+}
+
+// `path` with `params`, in which `key` is set to `text`.
+pub fn link(path: &str, params: &[(String, String)], key: &str, text: &str) -> String {
+    let params: Vec<(&str, &str)> = params
+        .iter()
+        .map(|(k, v)| {
+            if k == key {
+                (k.as_str(), text)
+            } else {
+                (k.as_str(), v.as_str())
+            }
+        })
+        .collect();
+    format!(
+        "{}?{}",
+        path,
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(id: i64, title: &str, author: &str) -> Document {
+        Document {
+            kind: Kind::Book,
+            id,
+            title: title.to_string(),
+            detail: None,
+            names: vec![author.to_string()],
+            body: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_correct() {
+        let mut dictionary = Dictionary::build(&[
+            book(1, "Dune Messiah", "Frank Herbert"),
+            book(2, "Children of Dune", "Frank Herbert"),
+            book(3, "Dunk", "Hubert"),
+        ]);
+
+        assert_eq!(dictionary.correct("children of dune"), None);
+        // The more frequent of two equally close words wins.
+        assert_eq!(
+            dictionary.correct("Dunr Mesiah, by Frank"),
+            Some("Dune Messiah, by Frank".to_string())
+        );
+        assert_eq!(dictionary.correct("Hebert"), Some("Herbert".to_string()));
+        assert_eq!(dictionary.correct("Dune xylophone"), None);
+
+        dictionary.remove(Kind::Book, 1);
+        dictionary.remove(Kind::Book, 2);
+        assert_eq!(dictionary.correct("Dunr"), Some("Dunk".to_string()));
+        dictionary.insert(&book(3, "Persuasion", "Austen"));
+        assert_eq!(dictionary.correct("Dunr"), None);
+        assert_eq!(
+            dictionary.correct("persuasian"),
+            Some("Persuasion".to_string())
+        );
+    }
+
+    #[test]
+    fn test_link() {
+        let params = vec![
+            ("q".to_string(), "dunr".to_string()),
+            ("limit".to_string(), "5".to_string()),
+        ];
+        assert_eq!(
+            link("/search", &params, "q", "Dune Messiah"),
+            "/search?q=Dune+Messiah&limit=5"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use sqlx::{Error, FromRow, Pool, Sqlite, Transaction};
+
+use super::backend::{words, Backend, Boosts, Document, Hit, Kind, Query};
+use super::search_queries;
+
+// FTS5 over the application database, ranked by BM25. Stemming is Porter's,
+// which only suits English, and typos are not forgiven.
+pub struct SqliteBackend {
+    pool: Pool<Sqlite>,
+    boosts: Boosts,
+}
+
+impl SqliteBackend {
+    pub fn new(pool: Pool<Sqlite>, boosts: Boosts) -> Self {
+        SqliteBackend { pool, boosts }
+    }
+}
+
+#[derive(FromRow)]
+struct HitRow {
+    kind: String,
+    id: i64,
+    label: String,
+    detail: Option<String>,
+    score: f64,
+}
+
+// Books and authors share the table, their ids are interleaved.
+fn rowid(kind: Kind, id: i64) -> i64 {
+    match kind {
+        Kind::Book => id * 2,
+        Kind::Author => id * 2 + 1,
+    }
+}
+
+async fn insert(tx: &mut Transaction<'_, Sqlite>, documents: Vec<Document>) -> Result<(), Error> {
+    for document in documents {
+        sqlx::query(&search_queries::delete_document_query())
+            .bind(rowid(document.kind, document.id))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&search_queries::create_document_query())
+            .bind(rowid(document.kind, document.id))
+            .bind(document.kind.as_str())
+            .bind(document.id)
+            .bind(&document.title)
+            .bind(&document.detail)
+            .bind(&document.title)
+            .bind(document.names.join(" "))
+            .bind(&document.body)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl Backend for SqliteBackend {
+    async fn is_empty(&self) -> Result<bool, Error> {
+        sqlx::query_scalar(&search_queries::is_empty_query())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn upsert(&self, documents: Vec<Document>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        insert(&mut tx, documents).await?;
+        tx.commit().await
+    }
+
+    async fn remove(&self, removed: Vec<(Kind, i64)>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        for (kind, id) in removed {
+            sqlx::query(&search_queries::delete_document_query())
+                .bind(rowid(kind, id))
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await
+    }
+
+    async fn rebuild(&self, documents: Vec<Document>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&search_queries::clear_documents_query())
+            .execute(&mut tx)
+            .await?;
+        insert(&mut tx, documents).await?;
+        tx.commit().await
+    }
+
+    async fn search(&self, query: &Query) -> Result<Vec<Hit>, Error> {
+        let words = words(&query.text);
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Quoted, words are matched as such rather than as FTS5 syntax.
+        let expression: Vec<String> = words
+            .iter()
+            .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+            .collect();
+
+        let sql = search_queries::search_query(query.kinds.len());
+        let mut q = sqlx::query_as::<_, HitRow>(&sql)
+            .bind(self.boosts.title)
+            .bind(self.boosts.names)
+            .bind(self.boosts.body)
+            .bind(expression.join(" "));
+        for kind in &query.kinds {
+            q = q.bind(kind.as_str());
+        }
+        let rows = q
+            .bind(query.limit as i64)
+            .bind(query.offset as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Hit {
+                kind: if row.kind == "book" {
+                    Kind::Book
+                } else {
+                    Kind::Author
+                },
+                id: row.id,
+                label: row.label,
+                detail: row.detail,
+                score: row.score as f32,
+            })
+            .collect())
+    }
+}
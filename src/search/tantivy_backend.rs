@@ -0,0 +1,415 @@
+use async_trait::async_trait;
+use sqlx::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{
+    BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, Query as IndexQuery, TermQuery,
+};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING,
+};
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    TextAnalyzer,
+};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+use super::backend::{words, Backend, Boosts, Document, Hit, Kind, Query};
+
+type Clauses = Vec<(Occur, Box<dyn IndexQuery>)>;
+
+const WRITER_MEMORY: usize = 15_000_000;
+const FOLDED: &str = "folded";
+
+// The stemmers, by the ISO 639-1 and 639-2 codes books may be tagged with.
+// The first code names the fields.
+const STEMMERS: &[(&[&str], Language)] = &[
+    (&["ar", "ara"], Language::Arabic),
+    (&["da", "dan"], Language::Danish),
+    (&["nl", "nld", "dut"], Language::Dutch),
+    (&["en", "eng"], Language::English),
+    (&["fi", "fin"], Language::Finnish),
+    (&["fr", "fra", "fre"], Language::French),
+    (&["de", "deu", "ger"], Language::German),
+    (&["el", "ell", "gre"], Language::Greek),
+    (&["hu", "hun"], Language::Hungarian),
+    (&["it", "ita"], Language::Italian),
+    (
+        &["no", "nb", "nn", "nor", "nob", "nno"],
+        Language::Norwegian,
+    ),
+    (&["pt", "por"], Language::Portuguese),
+    (&["ro", "ron", "rum"], Language::Romanian),
+    (&["ru", "rus"], Language::Russian),
+    (&["es", "spa"], Language::Spanish),
+    (&["sv", "swe"], Language::Swedish),
+    (&["ta", "tam"], Language::Tamil),
+    (&["tr", "tur"], Language::Turkish),
+];
+
+fn index_error(e: impl std::fmt::Display) -> Error {
+    Error::Io(std::io::Error::other(format!("search index: {}", e)))
+}
+
+fn analyzer(language: Option<Language>) -> TextAnalyzer {
+    let builder = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(AsciiFoldingFilter);
+    match language {
+        Some(language) => builder.filter(Stemmer::new(language)).build(),
+        None => builder.build(),
+    }
+}
+
+fn text(tokenizer: &str) -> TextOptions {
+    TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(tokenizer)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    )
+}
+
+// Titles and bodies are indexed once per stemmer, each document in the
+// fields of its language, the first ones being for unknown languages.
+struct Fields {
+    key: Field,
+    kind: Field,
+    id: Field,
+    label: Field,
+    detail: Field,
+    names: Field,
+    titles: Vec<Field>,
+    bodies: Vec<Field>,
+}
+
+impl Fields {
+    fn schema() -> (Schema, Fields) {
+        let mut builder = Schema::builder();
+        let mut titles = vec![builder.add_text_field("title", text(FOLDED))];
+        let mut bodies = vec![builder.add_text_field("body", text(FOLDED))];
+        for (codes, _) in STEMMERS {
+            let tokenizer = format!("stem_{}", codes[0]);
+            titles.push(builder.add_text_field(&format!("title_{}", codes[0]), text(&tokenizer)));
+            bodies.push(builder.add_text_field(&format!("body_{}", codes[0]), text(&tokenizer)));
+        }
+        let fields = Fields {
+            key: builder.add_text_field("key", STRING),
+            kind: builder.add_text_field("kind", STRING | STORED),
+            id: builder.add_i64_field("id", STORED),
+            label: builder.add_text_field("label", STORED),
+            detail: builder.add_text_field("detail", STORED),
+            names: builder.add_text_field("names", text(FOLDED)),
+            titles,
+            bodies,
+        };
+        (builder.build(), fields)
+    }
+
+    fn language(language: Option<&str>) -> usize {
+        language
+            .and_then(|l| STEMMERS.iter().position(|(codes, _)| codes.contains(&l)))
+            .map_or(0, |i| i + 1)
+    }
+
+    fn document(&self, document: &Document) -> TantivyDocument {
+        let language = Fields::language(document.language.as_deref());
+        let mut doc = TantivyDocument::default();
+        doc.add_text(self.key, key(document.kind, document.id));
+        doc.add_text(self.kind, document.kind.as_str());
+        doc.add_i64(self.id, document.id);
+        doc.add_text(self.label, &document.title);
+        if let Some(detail) = &document.detail {
+            doc.add_text(self.detail, detail);
+        }
+        for name in &document.names {
+            doc.add_text(self.names, name);
+        }
+        doc.add_text(self.titles[language], &document.title);
+        if let Some(body) = &document.body {
+            doc.add_text(self.bodies[language], body);
+        }
+        doc
+    }
+}
+
+fn key(kind: Kind, id: i64) -> String {
+    format!("{}:{}", kind.as_str(), id)
+}
+
+// An embedded Tantivy index, on disk or in memory. Titles and bodies are
+// stemmed by language, and words may be misspelt when `fuzzy` is set.
+pub struct TantivyBackend {
+    index: Index,
+    fields: Fields,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    boosts: Boosts,
+}
+
+impl TantivyBackend {
+    pub fn open(path: &Path, boosts: Boosts) -> Result<Self, Error> {
+        std::fs::create_dir_all(path)?;
+        let (schema, fields) = Fields::schema();
+        let directory = MmapDirectory::open(path).map_err(index_error)?;
+        let index = Index::open_or_create(directory, schema).map_err(index_error)?;
+        TantivyBackend::new(index, fields, boosts)
+    }
+
+    pub fn in_memory(boosts: Boosts) -> Result<Self, Error> {
+        let (schema, fields) = Fields::schema();
+        TantivyBackend::new(Index::create_in_ram(schema), fields, boosts)
+    }
+
+    fn new(index: Index, fields: Fields, boosts: Boosts) -> Result<Self, Error> {
+        let tokenizers = index.tokenizers();
+        tokenizers.register(FOLDED, analyzer(None));
+        for (codes, language) in STEMMERS {
+            tokenizers.register(&format!("stem_{}", codes[0]), analyzer(Some(*language)));
+        }
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(index_error)?;
+        let writer = index
+            .writer_with_num_threads(1, WRITER_MEMORY)
+            .map_err(index_error)?;
+
+        Ok(TantivyBackend {
+            index,
+            fields,
+            reader,
+            writer: Mutex::new(writer),
+            boosts,
+        })
+    }
+
+    fn write(&self, f: impl FnOnce(&mut IndexWriter) -> tantivy::Result<()>) -> Result<(), Error> {
+        let mut writer = self.writer.lock().unwrap();
+        f(&mut writer).map_err(index_error)?;
+        writer.commit().map_err(index_error)?;
+        self.reader.reload().map_err(index_error)
+    }
+
+    // Matches `word` in any field, as the field's analyzer turns it into
+    // terms. Fuzzy matches add to the exact ones, so that those rank first.
+    fn word_query(&self, word: &str, fuzzy: u8) -> Result<Clauses, Error> {
+        let fields = self
+            .fields
+            .titles
+            .iter()
+            .map(|f| (*f, self.boosts.title))
+            .chain(self.fields.bodies.iter().map(|f| (*f, self.boosts.body)))
+            .chain(std::iter::once((self.fields.names, self.boosts.names)));
+
+        let mut clauses: Clauses = Vec::new();
+        for (field, boost) in fields {
+            let mut analyzer = self.index.tokenizer_for_field(field).map_err(index_error)?;
+            let mut stream = analyzer.token_stream(word);
+            while let Some(token) = stream.next() {
+                let term = Term::from_field_text(field, &token.text);
+                let exact = TermQuery::new(term.clone(), IndexRecordOption::WithFreqs);
+                clauses.push((
+                    Occur::Should,
+                    Box::new(BoostQuery::new(Box::new(exact), boost)),
+                ));
+                if fuzzy > 0 {
+                    let fuzzy = FuzzyTermQuery::new(term, fuzzy, true);
+                    clauses.push((
+                        Occur::Should,
+                        Box::new(BoostQuery::new(Box::new(fuzzy), boost)),
+                    ));
+                }
+            }
+        }
+        Ok(clauses)
+    }
+}
+
+#[async_trait]
+impl Backend for TantivyBackend {
+    async fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.reader.searcher().num_docs() == 0)
+    }
+
+    async fn upsert(&self, documents: Vec<Document>) -> Result<(), Error> {
+        self.write(|writer| {
+            for document in &documents {
+                let key = key(document.kind, document.id);
+                writer.delete_term(Term::from_field_text(self.fields.key, &key));
+                writer.add_document(self.fields.document(document))?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn remove(&self, removed: Vec<(Kind, i64)>) -> Result<(), Error> {
+        self.write(|writer| {
+            for (kind, id) in removed {
+                writer.delete_term(Term::from_field_text(self.fields.key, &key(kind, id)));
+            }
+            Ok(())
+        })
+    }
+
+    async fn rebuild(&self, documents: Vec<Document>) -> Result<(), Error> {
+        self.write(|writer| {
+            writer.delete_all_documents()?;
+            for document in &documents {
+                writer.add_document(self.fields.document(document))?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn search(&self, query: &Query) -> Result<Vec<Hit>, Error> {
+        let mut clauses: Clauses = Vec::new();
+        for word in words(&query.text) {
+            let word = self.word_query(word, query.fuzzy)?;
+            if !word.is_empty() {
+                clauses.push((Occur::Must, Box::new(BooleanQuery::new(word))));
+            }
+        }
+        if clauses.is_empty() {
+            return Ok(Vec::new());
+        }
+        for kind in Kind::ALL.iter().filter(|k| !query.kinds.contains(k)) {
+            let term = Term::from_field_text(self.fields.kind, kind.as_str());
+            clauses.push((
+                Occur::MustNot,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
+        let searcher = self.reader.searcher();
+        let top = searcher
+            .search(
+                &BooleanQuery::new(clauses),
+                &TopDocs::with_limit(query.limit).and_offset(query.offset),
+            )
+            .map_err(index_error)?;
+
+        let mut hits = Vec::new();
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address).map_err(index_error)?;
+            let text = |field| {
+                doc.get_first(field)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            };
+            hits.push(Hit {
+                kind: if text(self.fields.kind).as_deref() == Some("book") {
+                    Kind::Book
+                } else {
+                    Kind::Author
+                },
+                id: doc
+                    .get_first(self.fields.id)
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or_default(),
+                label: text(self.fields.label).unwrap_or_default(),
+                detail: text(self.fields.detail),
+                score,
+            });
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boosts() -> Boosts {
+        Boosts {
+            title: 3.0,
+            names: 2.0,
+            body: 1.0,
+        }
+    }
+
+    fn book(id: i64, title: &str, author: &str, body: &str, language: &str) -> Document {
+        Document {
+            kind: Kind::Book,
+            id,
+            title: title.to_string(),
+            detail: Some(author.to_string()),
+            names: vec![author.to_string()],
+            body: Some(body.to_string()),
+            language: Some(language.to_string()),
+        }
+    }
+
+    fn query(text: &str, fuzzy: u8) -> Query {
+        Query {
+            text: text.to_string(),
+            kinds: Kind::ALL.to_vec(),
+            fuzzy,
+            limit: 10,
+            offset: 0,
+        }
+    }
+
+    async fn ids(backend: &TantivyBackend, q: Query) -> Vec<i64> {
+        backend
+            .search(&q)
+            .await
+            .unwrap()
+            .iter()
+            .map(|h| h.id)
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_stemming_by_language() {
+        let backend = TantivyBackend::in_memory(boosts()).unwrap();
+        assert!(backend.is_empty().await.unwrap());
+        backend
+            .rebuild(vec![
+                book(1, "Running Horses", "Smith", "", "en"),
+                book(2, "Les chevaux courants", "Dupont", "", "fr"),
+                book(3, "Running", "Nobody", "", "xx"),
+            ])
+            .await
+            .unwrap();
+
+        // English titles are stemmed as English, French ones as French, and
+        // others not at all.
+        assert_eq!(ids(&backend, query("horse run", 0)).await, vec![1]);
+        assert_eq!(ids(&backend, query("cheval courant", 0)).await, vec![2]);
+        assert_eq!(
+            ids(&backend, query("horses courant", 0)).await,
+            Vec::<i64>::new()
+        );
+        assert_eq!(ids(&backend, query("running", 0)).await.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_boosts_and_fuzzy_terms() {
+        let backend = TantivyBackend::in_memory(boosts()).unwrap();
+        backend
+            .upsert(vec![
+                book(1, "A History", "Jones", "All about dragons.", "en"),
+                book(2, "Dragons", "Jones", "A history.", "en"),
+            ])
+            .await
+            .unwrap();
+
+        // A match in the title outweighs one in the description.
+        assert_eq!(ids(&backend, query("dragons", 0)).await, vec![2, 1]);
+        assert_eq!(ids(&backend, query("history", 0)).await, vec![1, 2]);
+        assert_eq!(ids(&backend, query("dargons", 0)).await, Vec::<i64>::new());
+        assert_eq!(ids(&backend, query("dargons", 1)).await, vec![2, 1]);
+
+        backend
+            .upsert(vec![book(2, "Wyverns", "Jones", "", "en")])
+            .await
+            .unwrap();
+        backend.remove(vec![(Kind::Book, 1)]).await.unwrap();
+        assert_eq!(ids(&backend, query("dragons", 0)).await, Vec::<i64>::new());
+        assert_eq!(ids(&backend, query("wyvern jones", 0)).await, vec![2]);
+    }
+}
@@ -0,0 +1,101 @@
+use super::super::constants::SERIES_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct BookSeries {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    // Set through `PUT /series/{id}/volumes/{book_id}`, in reading order.
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub volumes: Option<Json<Vec<Volume>>>,
+}
+
+impl Resource for BookSeries {
+    const PATH: &'static str = "/series";
+    const TABLE: &'static str = SERIES_TABLE;
+    const FIELDS: &'static [&'static str] = &["name", "description"];
+    const FILTERABLE: &'static [&'static str] = &["name"];
+    const SORTABLE: &'static [&'static str] = &["id", "name"];
+    const SELECT: &'static str = "*, \
+        (SELECT json_group_array(json_object('book_id', v.book_id, 'title', v.title, \
+          'position', v.position, 'alternate', json(CASE WHEN v.alternate THEN 'true' ELSE 'false' END))) \
+          FROM (SELECT sb.book_id, b.title, sb.position, sb.alternate FROM series_books sb \
+          JOIN books b ON b.id = sb.book_id WHERE sb.series_id = series.id \
+          ORDER BY sb.position, sb.alternate, sb.book_id) v) AS volumes";
+    const REQUIRED: &'static [&'static str] = &["name"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "name" => Value::from(self.name.clone()),
+            "description" => Value::from(self.description.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "name" => self.name = value.into(),
+            "description" => self.description = value.into(),
+            _ => {}
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("name", &self.name)
+    }
+}
+
+// A book's place in a series. Positions are decimal so that novellas can sit
+// between volumes, e.g. 2.5. Only alternate editions may share a position.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Volume {
+    #[serde(default)]
+    #[sqlx(default)]
+    pub book_id: i64,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub title: Option<String>,
+    pub position: Option<f64>,
+    #[serde(default)]
+    pub alternate: bool,
+}
+
+impl Volume {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.position {
+            None => Err("position is required".to_string()),
+            Some(p) if !p.is_finite() || p < 0.0 => Err(format!(
+                "position must be a number of at least 0 (got {})",
+                p
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+// A series a book belongs to, as listed on the book, with its neighbours.
+// Alternate editions are skipped when looking for the neighbours.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeriesLink {
+    pub series_id: i64,
+    pub name: Option<String>,
+    pub position: f64,
+    pub alternate: bool,
+    pub previous_book_id: Option<i64>,
+    pub next_book_id: Option<i64>,
+}
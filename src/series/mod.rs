@@ -0,0 +1,5 @@
+pub mod book_series;
+#[allow(clippy::module_inception)]
+pub mod series;
+mod series_db;
+mod series_queries;
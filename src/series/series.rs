@@ -0,0 +1,242 @@
+use actix_web::{delete, get, put, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::resources::resources;
+use super::super::responses::{ConflictResponse, CustomError};
+use super::book_series::{BookSeries, Volume};
+use super::series_db::{self, Change};
+
+pub fn config_series(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_volumes)
+        .service(set_volume)
+        .service(delete_volume);
+    resources::config::<BookSeries>(cfg);
+}
+
+#[get("/series/{id}/volumes")]
+async fn get_volumes(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = series_db::get_volumes(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The body is `{"position": 2.5, "alternate": false}`; `alternate` marks an
+// alternate edition, which may share its position with another volume.
+#[put("/series/{id}/volumes/{book_id}")]
+async fn set_volume(
+    path: web::Path<(i64, i64)>,
+    json: web::Json<Volume>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (id, book_id) = path.into_inner();
+    let volume = json.into_inner();
+    if let Err(e) = volume.validate() {
+        return HttpResponse::BadRequest().json(CustomError::message(e));
+    }
+    let position = volume.position.unwrap_or_default();
+    let r = series_db::set_volume(pool.get_ref(), id, book_id, volume).await;
+
+    match r {
+        Ok(Change::Done(v)) => HttpResponse::Ok().json(v),
+        Ok(Change::Duplicate(other)) => HttpResponse::Conflict().json(ConflictResponse {
+            message: format!("position {} of series {} is already taken", position, id),
+            id: other,
+            location: format!("/books/{}", other),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[delete("/series/{id}/volumes/{book_id}")]
+async fn delete_volume(
+    path: web::Path<(i64, i64)>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (id, book_id) = path.into_inner();
+    let r = series_db::delete_volume(pool.get_ref(), id, book_id).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_series),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $req:expr, $uri:expr, $body:expr) => {{
+            let req = $req.uri(&$uri).set_json($body).to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    macro_rules! create_series {
+        ($app:expr, $name:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/series")
+                .set_json(json!({ "name": $name }))
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap()
+        }};
+    }
+
+    macro_rules! set_volume {
+        ($app:expr, $series_id:expr, $book_id:expr, $body:expr) => {{
+            send!(
+                $app,
+                test::TestRequest::put(),
+                format!("/series/{}/volumes/{}", $series_id, $book_id),
+                $body
+            )
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_series_volumes_in_order() {
+        let conn_pool = test_pool().await;
+        let first = book().title("Mort").create(&conn_pool).await.id.unwrap();
+        let second = book()
+            .title("Sourcery")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let novella = book()
+            .title("Troll Bridge")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let app = app!(conn_pool);
+        let s = create_series!(app, "Discworld");
+
+        set_volume!(app, s, second, json!({"position": 2}));
+        set_volume!(app, s, novella, json!({"position": 1.5}));
+        let resp = set_volume!(app, s, first, json!({"position": 1}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let series = get_json!(app, format!("/series/{}", s));
+        assert_eq!(series["name"], "Discworld");
+        assert_eq!(
+            series["volumes"],
+            json!([
+                {"book_id": first, "title": "Mort", "position": 1.0, "alternate": false},
+                {"book_id": novella, "title": "Troll Bridge", "position": 1.5, "alternate": false},
+                {"book_id": second, "title": "Sourcery", "position": 2.0, "alternate": false}
+            ])
+        );
+        assert_eq!(
+            get_json!(app, format!("/series/{}/volumes", s)),
+            series["volumes"]
+        );
+
+        let book = get_json!(app, format!("/books/{}", novella));
+        assert_eq!(
+            book["series"],
+            json!([{"series_id": s, "name": "Discworld", "position": 1.5, "alternate": false,
+                "previous_book_id": first, "next_book_id": second}])
+        );
+        let book = get_json!(app, format!("/books/{}", first));
+        assert_eq!(book["series"][0]["previous_book_id"], Value::Null);
+        assert_eq!(book["series"][0]["next_book_id"], novella);
+    }
+
+    #[actix_web::test]
+    async fn test_series_position_taken() {
+        let conn_pool = test_pool().await;
+        let first = book().create(&conn_pool).await.id.unwrap();
+        let other = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let s = create_series!(app, "Dune");
+
+        set_volume!(app, s, first, json!({"position": 2.5}));
+        let resp = set_volume!(app, s, other, json!({"position": 2.5}));
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["location"], format!("/books/{}", first));
+
+        let resp = set_volume!(app, s, other, json!({"position": 2.5, "alternate": true}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let book = get_json!(app, format!("/books/{}", other));
+        assert_eq!(book["series"][0]["alternate"], true);
+
+        // Moving a book onto its own position is not a clash.
+        let resp = set_volume!(app, s, first, json!({"position": 2.5}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/series/{}/volumes/{}", s, first))
+            .to_request();
+        test::call_service(&app, req).await;
+        let resp = set_volume!(app, s, other, json!({"position": 2.5}));
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let volumes = get_json!(app, format!("/series/{}/volumes", s));
+        assert_eq!(
+            volumes,
+            json!([{"book_id": other, "title": "title", "position": 2.5, "alternate": false}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_set_volume_bad_request() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let s = create_series!(app, "Dune");
+
+        let resp = set_volume!(app, s, b, json!({}));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "position is required");
+
+        let resp = set_volume!(app, s, b, json!({"position": -1}));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let resp = set_volume!(app, s, 0, json!({"position": 1}));
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = test::TestRequest::post()
+            .uri("/series")
+            .set_json(json!({"name": " "}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,77 @@
+use super::book_series::Volume;
+use super::series_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Change {
+    Done(Vec<Volume>),
+    // Another book already holds the position, with the given id.
+    Duplicate(i64),
+}
+
+async fn ensure(tx: &mut Transaction<'_, Sqlite>, query: &str, id: i64) -> Result<(), Error> {
+    sqlx::query(query).bind(id).fetch_one(tx).await?;
+
+    Ok(())
+}
+
+async fn fetch_volumes(
+    tx: &mut Transaction<'_, Sqlite>,
+    series_id: i64,
+) -> Result<Vec<Volume>, Error> {
+    sqlx::query_as::<_, Volume>(&series_queries::get_volumes_query())
+        .bind(series_id)
+        .fetch_all(tx)
+        .await
+}
+
+pub async fn get_volumes(pool: &Pool<Sqlite>, series_id: i64) -> Result<Vec<Volume>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure(&mut tx, &series_queries::get_series_id_query(), series_id).await?;
+
+    fetch_volumes(&mut tx, series_id).await
+}
+
+// Adds a book to a series, or moves it if it is already part of it.
+pub async fn set_volume(
+    pool: &Pool<Sqlite>,
+    series_id: i64,
+    book_id: i64,
+    volume: Volume,
+) -> Result<Change, Error> {
+    let mut tx = pool.begin().await?;
+    ensure(&mut tx, &series_queries::get_series_id_query(), series_id).await?;
+    ensure(&mut tx, &series_queries::get_book_id_query(), book_id).await?;
+    if !volume.alternate {
+        let holder: Option<i64> = sqlx::query_scalar(&series_queries::get_position_holder_query())
+            .bind(series_id)
+            .bind(volume.position)
+            .bind(book_id)
+            .fetch_optional(&mut tx)
+            .await?;
+        if let Some(id) = holder {
+            return Ok(Change::Duplicate(id));
+        }
+    }
+
+    sqlx::query(&series_queries::set_volume_query())
+        .bind(series_id)
+        .bind(book_id)
+        .bind(volume.position)
+        .bind(volume.alternate)
+        .execute(&mut tx)
+        .await?;
+    let volumes = fetch_volumes(&mut tx, series_id).await?;
+    tx.commit().await?;
+
+    Ok(Change::Done(volumes))
+}
+
+pub async fn delete_volume(pool: &Pool<Sqlite>, series_id: i64, book_id: i64) -> Result<(), Error> {
+    sqlx::query(&series_queries::delete_volume_query())
+        .bind(series_id)
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
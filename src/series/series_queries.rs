@@ -0,0 +1,42 @@
+use super::super::constants::{BOOKS_TABLE, SERIES_BOOKS_TABLE, SERIES_TABLE};
+
+pub fn get_series_id_query() -> String {
+    format!("Select id From {} where id=?", SERIES_TABLE)
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn get_volumes_query() -> String {
+    format!(
+        "Select sb.book_id, b.title, sb.position, sb.alternate From {} sb \
+         JOIN {} b ON b.id = sb.book_id where sb.series_id=? \
+         order by sb.position, sb.alternate, sb.book_id",
+        SERIES_BOOKS_TABLE, BOOKS_TABLE
+    )
+}
+
+// The book already holding a position, not counting alternate editions.
+pub fn get_position_holder_query() -> String {
+    format!(
+        "Select book_id From {} where series_id=? and position=? and alternate=0 and book_id<>?",
+        SERIES_BOOKS_TABLE
+    )
+}
+
+pub fn set_volume_query() -> String {
+    format!(
+        "INSERT INTO {} (series_id, book_id, position, alternate) values (?, ?, ?, ?) \
+         ON CONFLICT (series_id, book_id) DO UPDATE SET \
+         position=excluded.position, alternate=excluded.alternate",
+        SERIES_BOOKS_TABLE
+    )
+}
+
+pub fn delete_volume_query() -> String {
+    format!(
+        "DELETE From {} where series_id=? and book_id=?",
+        SERIES_BOOKS_TABLE
+    )
+}
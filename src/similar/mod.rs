@@ -0,0 +1,5 @@
+pub mod scoring;
+#[allow(clippy::module_inception)]
+pub mod similar;
+mod similar_db;
+mod similar_queries;
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::super::similarity::normalize;
+
+// Similar books kept per book.
+pub const TOP: usize = 20;
+// Words in more books than this add nothing but noise to the candidates.
+const COMMON_TERM_BOOKS: usize = 1000;
+const STOP_WORDS: &[&str] = &[
+    "and", "are", "for", "from", "her", "his", "into", "its", "not", "that", "the", "their",
+    "this", "was", "with",
+];
+
+// What each kind of shared metadata adds to the score; TF-IDF similarity of
+// the text, between 0 and 1, is scaled by `text`.
+#[derive(Debug, Clone, Copy)]
+pub struct Weights {
+    pub contributor: f32,
+    pub tag: f32,
+    pub subject: f32,
+    pub series: f32,
+    pub text: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonKind {
+    Contributor,
+    Tag,
+    Subject,
+    Series,
+    Text,
+}
+
+// Why a book was found similar: a shared contributor, tag, subject or series
+// by name, or the words the texts have most in common.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Reason {
+    pub kind: ReasonKind,
+    pub value: String,
+    pub score: f64,
+}
+
+// A book's metadata, each item by id and name. `text` is its title and
+// description.
+#[derive(Debug, Default, Clone)]
+pub struct Features {
+    pub contributors: Vec<(i64, String)>,
+    pub tags: Vec<(i64, String)>,
+    pub subjects: Vec<(i64, String)>,
+    pub series: Vec<(i64, String)>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Contributor(i64),
+    Tag(i64),
+    Subject(i64),
+    Series(i64),
+}
+
+struct Entry {
+    features: Features,
+    // TF-IDF weights of the words of `text`, of unit length.
+    vector: HashMap<String, f64>,
+}
+
+fn terms(text: &str) -> Vec<String> {
+    normalize(text)
+        .split(' ')
+        .filter(|t| t.chars().count() >= 3 && !STOP_WORDS.contains(t))
+        .map(str::to_string)
+        .collect()
+}
+
+// Every book with what it can be compared on.
+pub struct Corpus {
+    books: HashMap<i64, Entry>,
+    postings: HashMap<Key, Vec<i64>>,
+    terms: HashMap<String, Vec<i64>>,
+}
+
+impl Corpus {
+    pub fn new(books: HashMap<i64, Features>) -> Self {
+        let mut postings: HashMap<Key, Vec<i64>> = HashMap::new();
+        let mut counts: HashMap<i64, HashMap<String, f64>> = HashMap::new();
+        let mut df: HashMap<String, Vec<i64>> = HashMap::new();
+        for (id, features) in &books {
+            for key in keys(features) {
+                postings.entry(key).or_default().push(*id);
+            }
+            let tf = counts.entry(*id).or_default();
+            for term in terms(&features.text) {
+                *tf.entry(term).or_default() += 1.0;
+            }
+            for term in tf.keys() {
+                df.entry(term.clone()).or_default().push(*id);
+            }
+        }
+
+        let n = books.len() as f64;
+        let books = books
+            .into_iter()
+            .map(|(id, features)| {
+                let mut vector: HashMap<String, f64> = counts
+                    .remove(&id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(term, tf)| {
+                        let idf = ((n + 1.0) / df[&term].len() as f64).ln();
+                        let weight = (1.0 + tf.ln()) * idf;
+                        (term, weight)
+                    })
+                    .collect();
+                let norm = vector.values().map(|w| w * w).sum::<f64>().sqrt();
+                if norm > 0.0 {
+                    vector.values_mut().for_each(|w| *w /= norm);
+                }
+                (id, Entry { features, vector })
+            })
+            .collect();
+
+        Corpus {
+            books,
+            postings,
+            terms: df,
+        }
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.books.contains_key(&id)
+    }
+
+    pub fn ids(&self) -> Vec<i64> {
+        self.books.keys().copied().collect()
+    }
+
+    // The books `id` shares something with, and so could be similar to.
+    pub fn neighbours(&self, id: i64) -> HashSet<i64> {
+        let entry = match self.books.get(&id) {
+            Some(e) => e,
+            None => return HashSet::new(),
+        };
+        let mut neighbours: HashSet<i64> = keys(&entry.features)
+            .flat_map(|key| self.postings[&key].iter().copied())
+            .collect();
+        for term in entry.vector.keys() {
+            let books = &self.terms[term];
+            if books.len() <= COMMON_TERM_BOOKS {
+                neighbours.extend(books.iter().copied());
+            }
+        }
+        neighbours.remove(&id);
+        neighbours
+    }
+
+    // The books most similar to `id`, best first, with why.
+    pub fn similar(&self, id: i64, weights: &Weights) -> Vec<(i64, f64, Vec<Reason>)> {
+        let entry = match self.books.get(&id) {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+        let mut similar: Vec<(i64, f64, Vec<Reason>)> = self
+            .neighbours(id)
+            .into_iter()
+            .filter_map(|other| {
+                let reasons = reasons(entry, &self.books[&other], weights);
+                let score: f64 = reasons.iter().map(|r| r.score).sum();
+                if score > 0.0 {
+                    Some((other, score, reasons))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        similar.truncate(TOP);
+        similar
+    }
+}
+
+fn keys(features: &Features) -> impl Iterator<Item = Key> + '_ {
+    features
+        .contributors
+        .iter()
+        .map(|(id, _)| Key::Contributor(*id))
+        .chain(features.tags.iter().map(|(id, _)| Key::Tag(*id)))
+        .chain(features.subjects.iter().map(|(id, _)| Key::Subject(*id)))
+        .chain(features.series.iter().map(|(id, _)| Key::Series(*id)))
+}
+
+fn shared(
+    kind: ReasonKind,
+    weight: f32,
+    a: &[(i64, String)],
+    b: &[(i64, String)],
+) -> impl Iterator<Item = Reason> {
+    let b: HashSet<i64> = b.iter().map(|(id, _)| *id).collect();
+    a.iter()
+        .filter(|(id, _)| b.contains(id))
+        .map(|(_, name)| Reason {
+            kind,
+            value: name.clone(),
+            score: weight as f64,
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+fn reasons(a: &Entry, b: &Entry, weights: &Weights) -> Vec<Reason> {
+    let (fa, fb) = (&a.features, &b.features);
+    let mut reasons: Vec<Reason> =
+        shared(ReasonKind::Series, weights.series, &fa.series, &fb.series)
+            .chain(shared(
+                ReasonKind::Contributor,
+                weights.contributor,
+                &fa.contributors,
+                &fb.contributors,
+            ))
+            .chain(shared(
+                ReasonKind::Subject,
+                weights.subject,
+                &fa.subjects,
+                &fb.subjects,
+            ))
+            .chain(shared(ReasonKind::Tag, weights.tag, &fa.tags, &fb.tags))
+            .collect();
+
+    let mut common: Vec<(&String, f64)> = a
+        .vector
+        .iter()
+        .filter_map(|(term, w)| b.vector.get(term).map(|v| (term, w * v)))
+        .collect();
+    let cosine: f64 = common.iter().map(|(_, p)| p).sum();
+    if cosine > 0.0 && weights.text > 0.0 {
+        common.sort_by(|x, y| y.1.total_cmp(&x.1).then(x.0.cmp(y.0)));
+        let words: Vec<&str> = common.iter().take(3).map(|(t, _)| t.as_str()).collect();
+        reasons.push(Reason {
+            kind: ReasonKind::Text,
+            value: words.join(", "),
+            score: weights.text as f64 * cosine,
+        });
+    }
+    reasons.retain(|r| r.score > 0.0);
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEIGHTS: Weights = Weights {
+        contributor: 3.0,
+        tag: 1.0,
+        subject: 1.5,
+        series: 4.0,
+        text: 2.0,
+    };
+
+    fn named(items: &[(i64, &str)]) -> Vec<(i64, String)> {
+        items.iter().map(|(id, n)| (*id, n.to_string())).collect()
+    }
+
+    fn corpus() -> Corpus {
+        let mut books = HashMap::new();
+        books.insert(
+            1,
+            Features {
+                contributors: named(&[(1, "Frank Herbert")]),
+                tags: named(&[(1, "scifi")]),
+                series: named(&[(1, "Dune")]),
+                text: "Dune. Spice and sandworms on the desert planet Arrakis.".to_string(),
+                ..Features::default()
+            },
+        );
+        books.insert(
+            2,
+            Features {
+                contributors: named(&[(1, "Frank Herbert")]),
+                tags: named(&[(1, "scifi")]),
+                series: named(&[(1, "Dune")]),
+                text: "Dune Messiah. Paul rules Arrakis.".to_string(),
+                ..Features::default()
+            },
+        );
+        books.insert(
+            3,
+            Features {
+                tags: named(&[(1, "scifi")]),
+                text: "Hyperion. Pilgrims on a desert world.".to_string(),
+                ..Features::default()
+            },
+        );
+        books.insert(
+            4,
+            Features {
+                text: "Emma. A novel of manners.".to_string(),
+                ..Features::default()
+            },
+        );
+        Corpus::new(books)
+    }
+
+    #[test]
+    fn test_similar() {
+        let corpus = corpus();
+        let similar = corpus.similar(1, &WEIGHTS);
+
+        let ids: Vec<i64> = similar.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        let kinds: Vec<ReasonKind> = similar[0].2.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ReasonKind::Series,
+                ReasonKind::Contributor,
+                ReasonKind::Tag,
+                ReasonKind::Text
+            ]
+        );
+        let text = &similar[0].2[3];
+        assert_eq!(text.value, "arrakis, dune");
+        assert!(text.score > 0.0 && text.score < WEIGHTS.text as f64);
+        assert!((similar[0].1 - 8.0 - text.score).abs() < 1e-9);
+
+        let reasons: Vec<&str> = similar[1].2.iter().map(|r| r.value.as_str()).collect();
+        assert_eq!(reasons, vec!["scifi", "desert"]);
+        assert!(corpus.similar(4, &WEIGHTS).is_empty());
+        assert_eq!(corpus.neighbours(3), HashSet::from([1, 2]));
+    }
+}
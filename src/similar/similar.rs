@@ -0,0 +1,241 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Error, Pool, Sqlite};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::super::responses::CustomError;
+use super::scoring::{Weights, TOP};
+use super::similar_db;
+
+pub const DEFAULT_LIMIT: usize = 10;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// Failed refreshes in a row before the recommender gives up.
+const MAX_FAILURES: u32 = 5;
+
+// Keeps the precomputed similar books in step with the changes the triggers
+// record. The first refresh of a process scores every book, since the
+// weights may have changed since the last one.
+pub struct Recommender {
+    weights: Weights,
+    // The last change applied, once everything has been scored.
+    last_change: Mutex<Option<i64>>,
+}
+
+impl Recommender {
+    pub fn new(weights: Weights) -> Self {
+        Recommender {
+            weights,
+            last_change: Mutex::new(None),
+        }
+    }
+
+    pub async fn refresh(&self, pool: &Pool<Sqlite>) -> Result<(), Error> {
+        let mut last_change = self.last_change.lock().await;
+        let after = match *last_change {
+            Some(last) => last,
+            None => {
+                let last = similar_db::get_last_change(pool).await?;
+                let corpus = similar_db::get_corpus(pool).await?;
+                similar_db::rebuild(pool, &corpus, &self.weights).await?;
+                similar_db::delete_changes(pool, last).await?;
+                *last_change = Some(last);
+                return Ok(());
+            }
+        };
+
+        let (last, changed) = similar_db::get_changes(pool, after).await?;
+        if changed.is_empty() {
+            return Ok(());
+        }
+        // A change moves the book towards some books and away from those
+        // that listed it.
+        let corpus = similar_db::get_corpus(pool).await?;
+        let mut affected: BTreeSet<i64> = changed.clone();
+        for book_id in changed {
+            affected.extend(similar_db::get_listing(pool, book_id).await?);
+            affected.extend(corpus.neighbours(book_id));
+        }
+        similar_db::update(pool, &corpus, &self.weights, &affected).await?;
+        *last_change = Some(last);
+        similar_db::delete_changes(pool, last).await
+    }
+
+    // Refreshes every few seconds. A failed refresh is reported and tried
+    // again on the next tick; after `MAX_FAILURES` in a row, gives up with
+    // the last error.
+    pub async fn run(self, pool: Pool<Sqlite>) -> Result<(), Error> {
+        let mut interval = actix_web::rt::time::interval(REFRESH_INTERVAL);
+        let mut failures = 0;
+        loop {
+            interval.tick().await;
+            match self.refresh(&pool).await {
+                Ok(()) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    if failures == MAX_FAILURES {
+                        return Err(e);
+                    }
+                    eprintln!("could not refresh similar books, retrying: {}", e);
+                }
+            }
+        }
+    }
+}
+
+pub fn config_similar(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_similar_books);
+}
+
+#[derive(Deserialize)]
+pub struct SimilarParams {
+    pub limit: Option<usize>,
+}
+
+// The books most like this one by shared contributors, tags, subjects,
+// series and wording, each with the reasons for its score. They are only
+// as fresh as the last refresh.
+#[get("/books/{id}/similar")]
+async fn get_similar_books(
+    id: web::Path<i64>,
+    params: web::Query<SimilarParams>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=TOP).contains(&limit) {
+        return HttpResponse::BadRequest().json(CustomError::message(format!(
+            "limit must be between 1 and {} (got {})",
+            TOP, limit
+        )));
+    }
+
+    match similar_db::get_similar(pool.get_ref(), id.into_inner(), limit).await {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::scoring::Weights;
+    use super::Recommender;
+    use crate::test_utils::{self, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    const WEIGHTS: Weights = Weights {
+        contributor: 3.0,
+        tag: 1.0,
+        subject: 1.5,
+        series: 4.0,
+        text: 2.0,
+    };
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(tags::tags::config_tags)
+                    .configure(books::books::config_books)
+                    .configure(super::config_similar),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! tag_book {
+        ($app:expr, $book:expr, $tags:expr) => {{
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}/tags", $book))
+                .set_json(json!($tags))
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! similar {
+        ($app:expr, $book:expr) => {{
+            let req = test::TestRequest::get()
+                .uri(&format!("/books/{}/similar", $book))
+                .to_request();
+            let body: Value = test::call_and_read_body_json(&$app, req).await;
+            body
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_similar_books() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await.id.unwrap();
+        let hyperion = book()
+            .title("Hyperion")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let emma = book().title("Emma").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        tag_book!(app, dune, ["scifi", "desert"]);
+        tag_book!(app, hyperion, ["scifi"]);
+        tag_book!(app, emma, ["romance"]);
+        let recommender = Recommender::new(WEIGHTS);
+        recommender.refresh(&conn_pool).await.unwrap();
+
+        let body = similar!(app, dune);
+        assert_eq!(
+            body,
+            json!([{
+                "book_id": hyperion,
+                "title": "Hyperion",
+                "author": "author",
+                "score": 1.0,
+                "reasons": [{"kind": "tag", "value": "scifi", "score": 1.0}]
+            }])
+        );
+        assert_eq!(similar!(app, emma), json!([]));
+
+        // Changes are picked up on the next refresh, on both sides.
+        tag_book!(app, emma, ["romance", "desert"]);
+        tag_book!(app, hyperion, ["space"]);
+        recommender.refresh(&conn_pool).await.unwrap();
+        let body = similar!(app, dune);
+        assert_eq!(body[0]["book_id"], emma);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(similar!(app, hyperion), json!([]));
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/books/{}", emma))
+            .to_request();
+        test::call_service(&app, req).await;
+        recommender.refresh(&conn_pool).await.unwrap();
+        assert_eq!(similar!(app, dune), json!([]));
+    }
+
+    #[actix_web::test]
+    async fn test_similar_books_bad_request() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}/similar?limit=21", b))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "limit must be between 1 and 20 (got 21)");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}/similar", b + 1))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
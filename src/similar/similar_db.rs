@@ -0,0 +1,168 @@
+use super::scoring::{Corpus, Features, Reason, Weights};
+use super::similar_queries;
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::{Error, FromRow, Pool, Sqlite, Transaction};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Serialize, Debug, FromRow, Clone)]
+pub struct SimilarBook {
+    pub book_id: i64,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub score: f64,
+    pub reasons: Json<Vec<Reason>>,
+}
+
+async fn named(pool: &Pool<Sqlite>, query: &str) -> Result<Vec<(i64, i64, String)>, Error> {
+    sqlx::query_as(query).fetch_all(pool).await
+}
+
+pub async fn get_corpus(pool: &Pool<Sqlite>) -> Result<Corpus, Error> {
+    let texts: Vec<(i64, String)> = sqlx::query_as(&similar_queries::get_texts_query())
+        .fetch_all(pool)
+        .await?;
+    let mut books: HashMap<i64, Features> = texts
+        .into_iter()
+        .map(|(id, text)| {
+            (
+                id,
+                Features {
+                    text,
+                    ..Features::default()
+                },
+            )
+        })
+        .collect();
+
+    for (book_id, id, name) in named(pool, &similar_queries::get_contributors_query()).await? {
+        if let Some(f) = books.get_mut(&book_id) {
+            f.contributors.push((id, name));
+        }
+    }
+    for (book_id, id, name) in named(pool, &similar_queries::get_tags_query()).await? {
+        if let Some(f) = books.get_mut(&book_id) {
+            f.tags.push((id, name));
+        }
+    }
+    for (book_id, id, name) in named(pool, &similar_queries::get_subjects_query()).await? {
+        if let Some(f) = books.get_mut(&book_id) {
+            f.subjects.push((id, name));
+        }
+    }
+    for (book_id, id, name) in named(pool, &similar_queries::get_series_query()).await? {
+        if let Some(f) = books.get_mut(&book_id) {
+            f.series.push((id, name));
+        }
+    }
+
+    Ok(Corpus::new(books))
+}
+
+pub async fn get_last_change(pool: &Pool<Sqlite>) -> Result<i64, Error> {
+    sqlx::query_scalar(&similar_queries::get_last_change_query())
+        .fetch_one(pool)
+        .await
+}
+
+// The last change after `after` and the books changed since.
+pub async fn get_changes(pool: &Pool<Sqlite>, after: i64) -> Result<(i64, BTreeSet<i64>), Error> {
+    let rows: Vec<(i64, i64)> = sqlx::query_as(&similar_queries::get_changes_query())
+        .bind(after)
+        .fetch_all(pool)
+        .await?;
+    let last = rows.last().map_or(after, |(id, _)| *id);
+
+    Ok((last, rows.into_iter().map(|(_, book_id)| book_id).collect()))
+}
+
+pub async fn delete_changes(pool: &Pool<Sqlite>, upto: i64) -> Result<(), Error> {
+    sqlx::query(&similar_queries::delete_changes_query())
+        .bind(upto)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_listing(pool: &Pool<Sqlite>, similar_id: i64) -> Result<Vec<i64>, Error> {
+    sqlx::query_scalar(&similar_queries::get_listing_query())
+        .bind(similar_id)
+        .fetch_all(pool)
+        .await
+}
+
+async fn insert(
+    tx: &mut Transaction<'_, Sqlite>,
+    corpus: &Corpus,
+    weights: &Weights,
+    book_id: i64,
+) -> Result<(), Error> {
+    for (similar_id, score, reasons) in corpus.similar(book_id, weights) {
+        sqlx::query(&similar_queries::create_similar_query())
+            .bind(book_id)
+            .bind(similar_id)
+            .bind(score)
+            .bind(Json(reasons))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Replaces the similar books of every book in `book_ids`, in one
+// transaction. Books no longer in `corpus` are forgotten altogether.
+pub async fn update(
+    pool: &Pool<Sqlite>,
+    corpus: &Corpus,
+    weights: &Weights,
+    book_ids: &BTreeSet<i64>,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    for book_id in book_ids {
+        if corpus.contains(*book_id) {
+            sqlx::query(&similar_queries::delete_similar_query())
+                .bind(book_id)
+                .execute(&mut tx)
+                .await?;
+            insert(&mut tx, corpus, weights, *book_id).await?;
+        } else {
+            sqlx::query(&similar_queries::delete_similar_to_query())
+                .bind(book_id)
+                .bind(book_id)
+                .execute(&mut tx)
+                .await?;
+        }
+    }
+    tx.commit().await
+}
+
+pub async fn rebuild(pool: &Pool<Sqlite>, corpus: &Corpus, weights: &Weights) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&similar_queries::clear_similar_query())
+        .execute(&mut tx)
+        .await?;
+    for book_id in corpus.ids() {
+        insert(&mut tx, corpus, weights, book_id).await?;
+    }
+    tx.commit().await
+}
+
+pub async fn get_similar(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    limit: usize,
+) -> Result<Vec<SimilarBook>, Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&similar_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(&mut tx)
+        .await?;
+
+    sqlx::query_as::<_, SimilarBook>(&similar_queries::get_similar_query())
+        .bind(book_id)
+        .bind(limit as i64)
+        .fetch_all(&mut tx)
+        .await
+}
@@ -0,0 +1,102 @@
+use super::super::constants::{
+    AUTHORS_TABLE, BOOKS_TABLE, BOOK_CONTRIBUTORS_TABLE, BOOK_SUBJECTS_TABLE, BOOK_TAGS_TABLE,
+    SERIES_BOOKS_TABLE, SERIES_TABLE, SIMILAR_BOOKS_TABLE, SIMILAR_CHANGES_TABLE, SUBJECTS_TABLE,
+    TAGS_TABLE,
+};
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn get_texts_query() -> String {
+    format!(
+        "Select id, COALESCE(title, '') || ' ' || COALESCE(description, '') From {}",
+        BOOKS_TABLE
+    )
+}
+
+// Each book's contributors, tags, subjects and series, by id and name.
+pub fn get_contributors_query() -> String {
+    format!(
+        "Select DISTINCT c.book_id, a.id, COALESCE(a.name, '') From {} c \
+         JOIN {} a ON a.id = c.author_id",
+        BOOK_CONTRIBUTORS_TABLE, AUTHORS_TABLE
+    )
+}
+
+pub fn get_tags_query() -> String {
+    format!(
+        "Select bt.book_id, t.id, t.name From {} bt JOIN {} t ON t.id = bt.tag_id",
+        BOOK_TAGS_TABLE, TAGS_TABLE
+    )
+}
+
+pub fn get_subjects_query() -> String {
+    format!(
+        "Select bs.book_id, s.id, COALESCE(s.name, s.code, '') From {} bs \
+         JOIN {} s ON s.id = bs.subject_id",
+        BOOK_SUBJECTS_TABLE, SUBJECTS_TABLE
+    )
+}
+
+pub fn get_series_query() -> String {
+    format!(
+        "Select sb.book_id, s.id, COALESCE(s.name, '') From {} sb \
+         JOIN {} s ON s.id = sb.series_id",
+        SERIES_BOOKS_TABLE, SERIES_TABLE
+    )
+}
+
+pub fn get_last_change_query() -> String {
+    format!("Select COALESCE(MAX(id), 0) From {}", SIMILAR_CHANGES_TABLE)
+}
+
+pub fn get_changes_query() -> String {
+    format!(
+        "Select id, book_id From {} where id > ? order by id",
+        SIMILAR_CHANGES_TABLE
+    )
+}
+
+pub fn delete_changes_query() -> String {
+    format!("DELETE From {} where id <= ?", SIMILAR_CHANGES_TABLE)
+}
+
+// The books that list `similar_id` among their similar books.
+pub fn get_listing_query() -> String {
+    format!(
+        "Select book_id From {} where similar_id=?",
+        SIMILAR_BOOKS_TABLE
+    )
+}
+
+pub fn clear_similar_query() -> String {
+    format!("DELETE From {}", SIMILAR_BOOKS_TABLE)
+}
+
+pub fn delete_similar_query() -> String {
+    format!("DELETE From {} where book_id=?", SIMILAR_BOOKS_TABLE)
+}
+
+pub fn delete_similar_to_query() -> String {
+    format!(
+        "DELETE From {} where book_id=? or similar_id=?",
+        SIMILAR_BOOKS_TABLE
+    )
+}
+
+pub fn create_similar_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, similar_id, score, reasons) values (?, ?, ?, ?)",
+        SIMILAR_BOOKS_TABLE
+    )
+}
+
+pub fn get_similar_query() -> String {
+    format!(
+        "Select s.similar_id AS book_id, b.title, b.author, s.score, s.reasons \
+         From {} s JOIN {} b ON b.id = s.similar_id where s.book_id=? \
+         order by s.score desc, s.similar_id limit ?",
+        SIMILAR_BOOKS_TABLE, BOOKS_TABLE
+    )
+}
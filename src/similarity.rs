@@ -0,0 +1,168 @@
+// String normalization and similarity measures used to compare free text
+// such as titles and names.
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+// Letters that carry no combining mark in Unicode and so survive NFD.
+fn fold_letter(c: char) -> Option<&'static str> {
+    match c {
+        'ß' => Some("ss"),
+        'æ' => Some("ae"),
+        'œ' => Some("oe"),
+        'ø' => Some("o"),
+        'ł' => Some("l"),
+        'đ' | 'ð' => Some("d"),
+        'þ' => Some("th"),
+        'ı' => Some("i"),
+        _ => None,
+    }
+}
+
+// Case folds, strips diacritics and turns punctuation into spaces, so that
+// "Tolkien, J.R.R." becomes "tolkien j r r".
+pub fn normalize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.nfd().filter(|c| !is_combining_mark(*c)) {
+        for c in c.to_lowercase() {
+            match fold_letter(c) {
+                Some(s) => out.push_str(s),
+                None if c.is_alphanumeric() => out.push(c),
+                None => out.push(' '),
+            }
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// `normalize`, with the words sorted so that word order does not matter.
+pub fn token_sort(text: &str) -> String {
+    let normalized = normalize(text);
+    let mut tokens: Vec<&str> = normalized.split(' ').collect();
+    tokens.sort_unstable();
+    tokens.join(" ")
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+// Levenshtein distance scaled to a similarity between 0 and 1.
+pub fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+pub fn jaro(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+    let a_order = a
+        .iter()
+        .zip(&a_matched)
+        .filter(|(_, m)| **m)
+        .map(|(c, _)| c);
+    let b_order = b
+        .iter()
+        .zip(&b_matched)
+        .filter(|(_, m)| **m)
+        .map(|(c, _)| c);
+    let transpositions = a_order.zip(b_order).filter(|(x, y)| x != y).count() / 2;
+    let m = matches as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
+
+// Jaro similarity boosted for strings that share a prefix of up to four
+// characters.
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let j = jaro(a, b);
+    let prefix = a
+        .chars()
+        .zip(b.chars())
+        .take(4)
+        .take_while(|(x, y)| x == y)
+        .count();
+    j + prefix as f64 * 0.1 * (1.0 - j)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Tolkien, J.R.R. "), "tolkien j r r");
+        assert_eq!(normalize("Émile Zola"), "emile zola");
+        assert_eq!(normalize("Straße — Ærø"), "strasse aero");
+        assert_eq!(
+            token_sort("Tolkien, J.R.R."),
+            token_sort("J. R. R. Tolkien")
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("flaw", "flaw"), 0);
+        assert!(close(
+            levenshtein_similarity("kitten", "sitting"),
+            1.0 - 3.0 / 7.0
+        ));
+        assert!(close(levenshtein_similarity("", ""), 1.0));
+    }
+
+    #[test]
+    fn test_jaro_winkler() {
+        assert!(close(jaro("martha", "marhta"), 0.944));
+        assert!(close(jaro_winkler("martha", "marhta"), 0.961));
+        assert!(close(jaro_winkler("dixon", "dicksonx"), 0.813));
+        assert!(close(jaro_winkler("abc", "xyz"), 0.0));
+        assert!(close(jaro_winkler("same", "same"), 1.0));
+    }
+}
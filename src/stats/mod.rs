@@ -0,0 +1,5 @@
+pub mod report;
+#[allow(clippy::module_inception)]
+pub mod stats;
+mod stats_db;
+mod stats_queries;
@@ -0,0 +1,142 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Result<Format, String> {
+        match format {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("format must be one of json, csv (got {})", format)),
+        }
+    }
+}
+
+// A table of figures, kept as rows of columns so that it can be written out
+// as JSON objects or as CSV in the same column order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub name: &'static str,
+    pub columns: &'static [&'static str],
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .columns
+                        .iter()
+                        .map(|c| c.to_string())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    // Nulls are written as empty fields, strings without their quotes.
+    pub fn to_csv(&self) -> Result<String, csv::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(self.columns)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|v| match v {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            }))?;
+        }
+        let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+        Ok(String::from_utf8(bytes).unwrap_or_default())
+    }
+}
+
+// Reports by request, each served for `ttl` after it was computed. Shared by
+// every worker.
+pub struct Cache {
+    ttl: Duration,
+    reports: Mutex<HashMap<String, (Instant, Report)>>,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Self {
+        Cache {
+            ttl,
+            reports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Report> {
+        let reports = self.reports.lock().unwrap();
+        match reports.get(key) {
+            Some((at, report)) if at.elapsed() < self.ttl => Some(report.clone()),
+            _ => None,
+        }
+    }
+
+    // Expired reports are dropped on the way.
+    pub fn insert(&self, key: String, report: Report) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut reports = self.reports.lock().unwrap();
+        reports.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        reports.insert(key, (Instant::now(), report));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn report() -> Report {
+        Report {
+            name: "books-per-decade",
+            columns: &["decade", "books"],
+            rows: vec![
+                vec![json!("1960s"), json!(2)],
+                vec![json!("Fiction, \"new\""), Value::Null],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        assert_eq!(
+            report().to_json(),
+            json!([
+                {"decade": "1960s", "books": 2},
+                {"decade": "Fiction, \"new\"", "books": null}
+            ])
+        );
+        assert_eq!(
+            report().to_csv().unwrap(),
+            "decade,books\n1960s,2\n\"Fiction, \"\"new\"\"\",\n"
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = Cache::new(Duration::from_secs(60));
+        assert_eq!(cache.get("/stats"), None);
+        cache.insert("/stats".to_string(), report());
+        assert_eq!(cache.get("/stats"), Some(report()));
+        assert_eq!(cache.get("/stats?format=csv"), None);
+
+        let cache = Cache::new(Duration::ZERO);
+        cache.insert("/stats".to_string(), report());
+        assert_eq!(cache.get("/stats"), None);
+    }
+}
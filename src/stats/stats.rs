@@ -0,0 +1,495 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Error, Pool, Sqlite};
+use std::future::Future;
+
+use super::super::books::publication_date;
+use super::super::contributors::contributor::Role;
+use super::super::responses::CustomError;
+use super::report::{Cache, Format, Report};
+use super::stats_db;
+
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 100;
+
+pub fn config_stats(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_books_per_author)
+        .service(get_top_authors)
+        .service(get_books_per_decade)
+        .service(get_most_borrowed)
+        .service(get_growth);
+}
+
+// Every report takes `format=json|csv`; the others only where they apply.
+#[derive(Deserialize)]
+pub struct StatsParams {
+    pub format: Option<String>,
+    pub role: Option<String>,
+    pub limit: Option<u32>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub interval: Option<String>,
+}
+
+impl StatsParams {
+    fn format(&self) -> Result<Format, String> {
+        self.format
+            .as_deref()
+            .map_or(Ok(Format::Json), Format::parse)
+    }
+
+    fn role(&self) -> Result<Option<&'static str>, String> {
+        self.role
+            .as_deref()
+            .map(|r| Role::parse(r).map(|r| r.as_str()))
+            .transpose()
+    }
+
+    fn limit(&self) -> Result<u32, String> {
+        match self.limit {
+            None => Ok(DEFAULT_LIMIT),
+            Some(l) if (1..=MAX_LIMIT).contains(&l) => Ok(l),
+            Some(l) => Err(format!(
+                "limit must be between 1 and {} (got {})",
+                MAX_LIMIT, l
+            )),
+        }
+    }
+}
+
+fn bad_request(e: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(CustomError::message(e))
+}
+
+// The same report in either format is cached once, under the request's
+// path and its other parameters.
+fn cache_key(req: &HttpRequest) -> String {
+    let params: Vec<(String, String)> =
+        serde_urlencoded::from_str(req.query_string()).unwrap_or_default();
+    let params: Vec<(String, String)> = params.into_iter().filter(|(k, _)| k != "format").collect();
+    format!(
+        "{}?{}",
+        req.path(),
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    )
+}
+
+// Serves the cached report, or computes and caches it when there is none or
+// it is older than the cache's TTL.
+async fn respond(
+    req: &HttpRequest,
+    cache: &Cache,
+    format: Format,
+    report: impl Future<Output = Result<Report, Error>>,
+) -> HttpResponse {
+    let key = cache_key(req);
+    let report = match cache.get(&key) {
+        Some(r) => r,
+        None => match report.await {
+            Ok(r) => {
+                cache.insert(key, r.clone());
+                r
+            }
+            Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+        },
+    };
+
+    match format {
+        Format::Json => HttpResponse::Ok().json(report.to_json()),
+        Format::Csv => match report.to_csv() {
+            Ok(csv) => HttpResponse::Ok()
+                .content_type("text/csv; charset=utf-8")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}.csv\"", report.name),
+                ))
+                .body(csv),
+            Err(e) => HttpResponse::InternalServerError().json(CustomError::message(e.to_string())),
+        },
+    }
+}
+
+// Every author with how many books they contributed to, optionally in a
+// given `role`.
+#[get("/stats/books-per-author")]
+async fn get_books_per_author(
+    req: HttpRequest,
+    params: web::Query<StatsParams>,
+    cache: web::Data<Cache>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (format, role) = match (params.format(), params.role()) {
+        (Ok(f), Ok(r)) => (f, r),
+        (Err(e), _) | (_, Err(e)) => return bad_request(e),
+    };
+    let report = stats_db::get_books_per_author(pool.get_ref(), role);
+    respond(&req, &cache, format, report).await
+}
+
+// The `limit` authors with the most titles.
+#[get("/stats/top-authors")]
+async fn get_top_authors(
+    req: HttpRequest,
+    params: web::Query<StatsParams>,
+    cache: web::Data<Cache>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (format, role, limit) = match (params.format(), params.role(), params.limit()) {
+        (Ok(f), Ok(r), Ok(l)) => (f, r, l),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return bad_request(e),
+    };
+    let report = stats_db::get_top_authors(pool.get_ref(), role, limit);
+    respond(&req, &cache, format, report).await
+}
+
+#[get("/stats/books-per-decade")]
+async fn get_books_per_decade(
+    req: HttpRequest,
+    params: web::Query<StatsParams>,
+    cache: web::Data<Cache>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let format = match params.format() {
+        Ok(f) => f,
+        Err(e) => return bad_request(e),
+    };
+    let report = stats_db::get_books_per_decade(pool.get_ref());
+    respond(&req, &cache, format, report).await
+}
+
+// The titles lent most often, between `since` and `until` when given. Both
+// may be a year, a month or a date. Empty until the first loan.
+#[get("/stats/most-borrowed")]
+async fn get_most_borrowed(
+    req: HttpRequest,
+    params: web::Query<StatsParams>,
+    cache: web::Data<Cache>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let (format, limit) = match (params.format(), params.limit()) {
+        (Ok(f), Ok(l)) => (f, l),
+        (Err(e), _) | (_, Err(e)) => return bad_request(e),
+    };
+    for (field, date) in [("since", &params.since), ("until", &params.until)] {
+        if let Some(Err(e)) = date
+            .as_deref()
+            .map(|d| publication_date::validate(field, d))
+        {
+            return bad_request(e);
+        }
+    }
+    let report = stats_db::get_most_borrowed(
+        pool.get_ref(),
+        params.since.as_deref(),
+        params.until.as_deref(),
+        limit,
+    );
+    respond(&req, &cache, format, report).await
+}
+
+// Books and authors added per `interval`, month or year, with running
+// totals.
+#[get("/stats/growth")]
+async fn get_growth(
+    req: HttpRequest,
+    params: web::Query<StatsParams>,
+    cache: web::Data<Cache>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let format = match params.format() {
+        Ok(f) => f,
+        Err(e) => return bad_request(e),
+    };
+    let length = match params.interval.as_deref() {
+        None | Some("month") => 7,
+        Some("year") => 4,
+        Some(i) => return bad_request(format!("interval must be one of month, year (got {})", i)),
+    };
+    let report = stats_db::get_growth(pool.get_ref(), length);
+    respond(&req, &cache, format, report).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::report::Cache;
+    use crate::test_utils::{self, author, book, member, test_pool};
+    use actix_web::{
+        http::{self},
+        test, web,
+    };
+    use serde_json::{json, Value};
+    use sqlx::{Pool, Sqlite};
+    use std::time::Duration;
+
+    macro_rules! app {
+        ($pool:expr, $ttl:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .app_data(web::Data::new(Cache::new($ttl)))
+                    .configure(contributors::contributors::config_contributors)
+                    .configure(super::config_stats),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    macro_rules! set_contributors {
+        ($app:expr, $book_id:expr, $body:expr) => {{
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}/contributors", $book_id))
+                .set_json($body)
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    async fn lend(pool: &Pool<Sqlite>, book_id: i64, member_id: i64, at: &str) {
+        sqlx::query(
+            "INSERT INTO loans (book_id, member_id, loaned_at, due_at, returned_at) \
+             values (?, ?, ?, ?, ?)",
+        )
+        .bind(book_id)
+        .bind(member_id)
+        .bind(at)
+        .bind(at)
+        .bind(at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_author_stats() {
+        let conn_pool = test_pool().await;
+        let herbert = author().name("Frank Herbert").create(&conn_pool).await;
+        let austen = author().name("Jane Austen").create(&conn_pool).await;
+        let nobody = author().name("Nobody").create(&conn_pool).await;
+        let (herbert, austen, nobody) =
+            (herbert.id.unwrap(), austen.id.unwrap(), nobody.id.unwrap());
+        let dune = book().title("Dune").create(&conn_pool).await.id.unwrap();
+        let messiah = book().title("Messiah").create(&conn_pool).await.id.unwrap();
+        let emma = book().title("Emma").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool, Duration::ZERO);
+        set_contributors!(app, dune, json!([{"author_id": herbert, "role": "author"}]));
+        set_contributors!(
+            app,
+            messiah,
+            json!([
+                {"author_id": herbert, "role": "author"},
+                {"author_id": austen, "role": "editor"}
+            ])
+        );
+        set_contributors!(app, emma, json!([{"author_id": austen, "role": "author"}]));
+
+        assert_eq!(
+            get_json!(app, "/stats/books-per-author"),
+            json!([
+                {"author_id": herbert, "author": "Frank Herbert", "books": 2},
+                {"author_id": austen, "author": "Jane Austen", "books": 2},
+                {"author_id": nobody, "author": "Nobody", "books": 0}
+            ])
+        );
+        assert_eq!(
+            get_json!(app, "/stats/top-authors?role=author&limit=1"),
+            json!([{"author_id": herbert, "author": "Frank Herbert", "books": 2}])
+        );
+        let body = get_json!(app, "/stats/books-per-author?role=editor");
+        assert_eq!(
+            body[1],
+            json!({"author_id": austen, "author": "Jane Austen", "books": 1})
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/stats/top-authors?format=csv")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"top-authors.csv\""
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(
+            body,
+            format!(
+                "author_id,author,books\n{},Frank Herbert,2\n{},Jane Austen,2\n",
+                herbert, austen
+            )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_catalogue_stats() {
+        let conn_pool = test_pool().await;
+        let dune = book()
+            .title("Dune")
+            .published("1965")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let emma = book()
+            .title("Emma")
+            .published("1815-12-23")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        book()
+            .title("Messiah")
+            .published("1969")
+            .create(&conn_pool)
+            .await;
+        book().title("Undated").create(&conn_pool).await;
+        let m = member().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool, Duration::ZERO);
+
+        assert_eq!(
+            get_json!(app, "/stats/books-per-decade"),
+            json!([
+                {"decade": "1810s", "books": 1},
+                {"decade": "1960s", "books": 2}
+            ])
+        );
+
+        assert_eq!(get_json!(app, "/stats/most-borrowed"), json!([]));
+        lend(&conn_pool, dune, m, "2019-12-31T10:00:00Z").await;
+        lend(&conn_pool, dune, m, "2020-03-01T10:00:00Z").await;
+        lend(&conn_pool, emma, m, "2020-12-31T10:00:00Z").await;
+        let body = get_json!(app, "/stats/most-borrowed");
+        assert_eq!(body[0]["book_id"], dune);
+        assert_eq!(body[0]["loans"], 2);
+        assert_eq!(body[1]["book_id"], emma);
+        let body = get_json!(app, "/stats/most-borrowed?since=2020&until=2020-12");
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(body[0]["loans"], 1);
+        let body = get_json!(app, "/stats/most-borrowed?until=2020-03-01");
+        assert_eq!(
+            body,
+            json!([{"book_id": dune, "title": "Dune", "author": "author", "loans": 2}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_growth() {
+        let conn_pool = test_pool().await;
+        for (title, at) in [
+            ("Dune", Some("2020-01-05T10:00:00Z")),
+            ("Emma", Some("2020-01-20T10:00:00Z")),
+            ("Messiah", Some("2021-03-01T10:00:00Z")),
+            ("Old", None),
+        ] {
+            let id = book().title(title).create(&conn_pool).await.id.unwrap();
+            sqlx::query("UPDATE books SET created_at=? where id=?")
+                .bind(at)
+                .bind(id)
+                .execute(&conn_pool)
+                .await
+                .unwrap();
+        }
+        let a = author().create(&conn_pool).await.id.unwrap();
+        sqlx::query("UPDATE authors SET created_at='2020-02-01T00:00:00Z' where id=?")
+            .bind(a)
+            .execute(&conn_pool)
+            .await
+            .unwrap();
+        let app = app!(conn_pool, Duration::ZERO);
+
+        let growth = |interval: &'static str| {
+            let req = test::TestRequest::get()
+                .uri(&format!("/stats/growth?interval={}&format=csv", interval))
+                .to_request();
+            test::call_and_read_body(&app, req)
+        };
+        assert_eq!(
+            growth("month").await,
+            "period,books,authors,total_books,total_authors\n\
+             2020-01,2,0,3,0\n\
+             2020-02,0,1,3,1\n\
+             2021-03,1,0,4,1\n"
+        );
+        assert_eq!(
+            growth("year").await,
+            "period,books,authors,total_books,total_authors\n\
+             2020,2,1,3,1\n\
+             2021,1,0,4,1\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_stats_are_cached() {
+        let conn_pool = test_pool().await;
+        book().published("1965").create(&conn_pool).await;
+        let app = app!(conn_pool, Duration::from_secs(60));
+
+        let body = get_json!(app, "/stats/books-per-decade");
+        assert_eq!(body, json!([{"decade": "1960s", "books": 1}]));
+        book().published("1965").create(&conn_pool).await;
+        // Both formats are served from the same entry until it expires.
+        assert_eq!(get_json!(app, "/stats/books-per-decade"), body);
+        let req = test::TestRequest::get()
+            .uri("/stats/books-per-decade?format=csv")
+            .to_request();
+        let csv = test::call_and_read_body(&app, req).await;
+        assert_eq!(csv, "decade,books\n1960s,1\n");
+
+        let app = app!(conn_pool, Duration::ZERO);
+        assert_eq!(
+            get_json!(app, "/stats/books-per-decade"),
+            json!([{"decade": "1960s", "books": 2}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_stats_bad_request() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool, Duration::ZERO);
+
+        for (uri, message) in [
+            (
+                "/stats/books-per-decade?format=xml",
+                "format must be one of json, csv (got xml)",
+            ),
+            (
+                "/stats/top-authors?limit=101",
+                "limit must be between 1 and 100 (got 101)",
+            ),
+            (
+                "/stats/books-per-author?role=narrator",
+                "role must be one of author, editor, translator, illustrator (got narrator)",
+            ),
+            (
+                "/stats/most-borrowed?since=2020-13",
+                "since must be a year, year-month or date like \
+                 1990, 1990-05 or 1990-05-17 (got 2020-13)",
+            ),
+            (
+                "/stats/growth?interval=week",
+                "interval must be one of month, year (got week)",
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(body["message"], message);
+        }
+    }
+}
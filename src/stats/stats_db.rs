@@ -0,0 +1,119 @@
+use serde_json::json;
+use sqlx::{Error, Pool, Sqlite};
+
+use super::report::Report;
+use super::stats_queries;
+
+pub async fn get_books_per_author(
+    pool: &Pool<Sqlite>,
+    role: Option<&str>,
+) -> Result<Report, Error> {
+    let rows: Vec<(i64, Option<String>, i64)> =
+        sqlx::query_as(&stats_queries::get_books_per_author_query())
+            .bind(role)
+            .bind(role)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(Report {
+        name: "books-per-author",
+        columns: &["author_id", "author", "books"],
+        rows: rows
+            .into_iter()
+            .map(|(id, name, books)| vec![json!(id), json!(name), json!(books)])
+            .collect(),
+    })
+}
+
+pub async fn get_top_authors(
+    pool: &Pool<Sqlite>,
+    role: Option<&str>,
+    limit: u32,
+) -> Result<Report, Error> {
+    let rows: Vec<(i64, Option<String>, i64)> =
+        sqlx::query_as(&stats_queries::get_top_authors_query())
+            .bind(role)
+            .bind(role)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(Report {
+        name: "top-authors",
+        columns: &["author_id", "author", "books"],
+        rows: rows
+            .into_iter()
+            .map(|(id, name, books)| vec![json!(id), json!(name), json!(books)])
+            .collect(),
+    })
+}
+
+pub async fn get_books_per_decade(pool: &Pool<Sqlite>) -> Result<Report, Error> {
+    let rows: Vec<(String, i64)> = sqlx::query_as(&stats_queries::get_books_per_decade_query())
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Report {
+        name: "books-per-decade",
+        columns: &["decade", "books"],
+        rows: rows
+            .into_iter()
+            .map(|(decade, books)| vec![json!(decade), json!(books)])
+            .collect(),
+    })
+}
+
+pub async fn get_most_borrowed(
+    pool: &Pool<Sqlite>,
+    since: Option<&str>,
+    until: Option<&str>,
+    limit: u32,
+) -> Result<Report, Error> {
+    let rows: Vec<(i64, Option<String>, Option<String>, i64)> =
+        sqlx::query_as(&stats_queries::get_most_borrowed_query())
+            .bind(since)
+            .bind(since)
+            .bind(since)
+            .bind(until)
+            .bind(until)
+            .bind(until)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(Report {
+        name: "most-borrowed",
+        columns: &["book_id", "title", "author", "loans"],
+        rows: rows
+            .into_iter()
+            .map(|(id, title, author, loans)| {
+                vec![json!(id), json!(title), json!(author), json!(loans)]
+            })
+            .collect(),
+    })
+}
+
+// `length` is 4 for years, 7 for months.
+pub async fn get_growth(pool: &Pool<Sqlite>, length: usize) -> Result<Report, Error> {
+    let rows: Vec<(String, i64, i64, i64, i64)> =
+        sqlx::query_as(&stats_queries::get_growth_query(length))
+            .fetch_all(pool)
+            .await?;
+
+    Ok(Report {
+        name: "growth",
+        columns: &["period", "books", "authors", "total_books", "total_authors"],
+        rows: rows
+            .into_iter()
+            .map(|(period, books, authors, total_books, total_authors)| {
+                vec![
+                    json!(period),
+                    json!(books),
+                    json!(authors),
+                    json!(total_books),
+                    json!(total_authors),
+                ]
+            })
+            .collect(),
+    })
+}
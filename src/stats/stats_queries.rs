@@ -0,0 +1,65 @@
+use super::super::constants::{AUTHORS_TABLE, BOOKS_TABLE, BOOK_CONTRIBUTORS_TABLE, LOANS_TABLE};
+
+// Every author with the number of distinct books they contributed to, in
+// any role or the one bound, alphabetically. Authors without books count 0.
+pub fn get_books_per_author_query() -> String {
+    format!(
+        "Select a.id, a.name, COUNT(DISTINCT c.book_id) AS books From {} a \
+         LEFT JOIN {} c ON c.author_id = a.id and (? IS NULL or c.role = ?) \
+         group by a.id order by a.name, a.id",
+        AUTHORS_TABLE, BOOK_CONTRIBUTORS_TABLE
+    )
+}
+
+pub fn get_top_authors_query() -> String {
+    format!(
+        "Select a.id, a.name, COUNT(DISTINCT c.book_id) AS books From {} a \
+         JOIN {} c ON c.author_id = a.id where (? IS NULL or c.role = ?) \
+         group by a.id order by books desc, a.name, a.id limit ?",
+        AUTHORS_TABLE, BOOK_CONTRIBUTORS_TABLE
+    )
+}
+
+// Books without a publication date are left out.
+pub fn get_books_per_decade_query() -> String {
+    format!(
+        "Select substr(publication_date, 1, 3) || '0s' AS decade, COUNT(*) AS books \
+         From {} where publication_date IS NOT NULL group by decade order by decade",
+        BOOKS_TABLE
+    )
+}
+
+// Loans are compared on as much of `loaned_at` as each bound gives, so that
+// `until=2020` takes in the whole year.
+pub fn get_most_borrowed_query() -> String {
+    format!(
+        "Select b.id, b.title, b.author, COUNT(*) AS loans From {} l \
+         JOIN {} b ON b.id = l.book_id \
+         where (? IS NULL or substr(l.loaned_at, 1, length(?)) >= ?) \
+         and (? IS NULL or substr(l.loaned_at, 1, length(?)) <= ?) \
+         group by b.id order by loans desc, b.title, b.id limit ?",
+        LOANS_TABLE, BOOKS_TABLE
+    )
+}
+
+// Books and authors catalogued in each period, the first `length` characters
+// of `created_at`, with running totals. Undated rows predate every period
+// and count towards the totals only.
+pub fn get_growth_query(length: usize) -> String {
+    format!(
+        "WITH added AS ( \
+         Select substr(created_at, 1, {length}) AS period, 1 AS books, 0 AS authors \
+         From {books} where created_at IS NOT NULL \
+         UNION ALL \
+         Select substr(created_at, 1, {length}), 0, 1 From {authors} where created_at IS NOT NULL) \
+         Select period, SUM(books) AS books, SUM(authors) AS authors, \
+         (Select COUNT(*) From {books} where created_at IS NULL) \
+         + SUM(SUM(books)) OVER (order by period) AS total_books, \
+         (Select COUNT(*) From {authors} where created_at IS NULL) \
+         + SUM(SUM(authors)) OVER (order by period) AS total_authors \
+         From added group by period order by period",
+        length = length,
+        books = BOOKS_TABLE,
+        authors = AUTHORS_TABLE
+    )
+}
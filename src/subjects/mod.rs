@@ -0,0 +1,5 @@
+pub mod subject;
+#[allow(clippy::module_inception)]
+pub mod subjects;
+mod subjects_db;
+pub mod subjects_queries;
@@ -0,0 +1,127 @@
+use super::super::constants::SUBJECTS_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// A node of the subject classification, e.g. `500 Science`. `path` lists the
+// ids from the root down to the subject, as in `/1/4/12/`, and is kept up to
+// date by triggers whenever a subject is created or moved.
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Subject {
+    pub id: Option<i64>,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub parent_id: Option<i64>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub path: Option<String>,
+}
+
+impl Resource for Subject {
+    const PATH: &'static str = "/subjects";
+    const TABLE: &'static str = SUBJECTS_TABLE;
+    const FIELDS: &'static [&'static str] = &["code", "name", "parent_id"];
+    const FILTERABLE: &'static [&'static str] = &["code", "name", "parent_id"];
+    const SORTABLE: &'static [&'static str] = &["id", "code", "name", "path"];
+    const REQUIRED: &'static [&'static str] = &["code", "name"];
+    const UNIQUE: &'static [&'static str] = &["code"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "code" => Value::from(self.code.clone()),
+            "name" => Value::from(self.name.clone()),
+            "parent_id" => Value::from(self.parent_id),
+            "path" => Value::from(self.path.clone()),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        match field {
+            "code" => self.code = value.into(),
+            "name" => self.name = value.into(),
+            "parent_id" => self.parent_id = value.into(),
+            _ => {}
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.code = self.code.as_deref().map(|c| c.trim().to_string());
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("code", &self.code)?;
+        validate_not_blank("name", &self.name)
+    }
+}
+
+// A subject with its book counts, as read for `/subjects/{id}/tree`.
+#[derive(Debug, FromRow, Clone)]
+pub struct SubjectCount {
+    pub id: i64,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub parent_id: Option<i64>,
+    pub book_count: i64,
+    pub total_book_count: i64,
+}
+
+// `book_count` only counts the books filed directly under the subject,
+// `total_book_count` the distinct books of the whole subtree.
+#[derive(Serialize, Debug, Clone)]
+pub struct SubjectTree {
+    pub id: i64,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub book_count: i64,
+    pub total_book_count: i64,
+    pub children: Vec<SubjectTree>,
+}
+
+impl SubjectTree {
+    // Builds the tree below `root` out of its subtree, listed parents first.
+    pub fn build(root: i64, subjects: Vec<SubjectCount>) -> Option<SubjectTree> {
+        let mut nodes: Vec<(Option<i64>, SubjectTree)> = subjects
+            .into_iter()
+            .map(|s| {
+                (
+                    s.parent_id,
+                    SubjectTree {
+                        id: s.id,
+                        code: s.code,
+                        name: s.name,
+                        book_count: s.book_count,
+                        total_book_count: s.total_book_count,
+                        children: Vec::new(),
+                    },
+                )
+            })
+            .collect();
+
+        // Children come after their parent, so attaching from the end moves
+        // every subtree into place before its own parent is attached.
+        while let Some((parent_id, node)) = nodes.pop() {
+            if node.id == root {
+                return Some(node);
+            }
+            let parent = nodes
+                .iter_mut()
+                .rev()
+                .find(|(_, p)| Some(p.id) == parent_id)?;
+            parent.1.children.insert(0, node);
+        }
+
+        None
+    }
+}
@@ -0,0 +1,407 @@
+use actix_web::{get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+
+use super::super::resources::resources;
+use super::super::resources::resources_repository::Repository;
+use super::super::responses::CustomError;
+use super::subject::Subject;
+use super::subjects_db::{self, Move};
+
+pub fn config_subjects(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_subject_tree)
+        .service(move_subject)
+        .service(get_book_subjects)
+        .service(set_book_subjects)
+        .service(resources::collection::<Subject>())
+        .service(
+            resources::item::<Subject>()
+                .route(web::get().to(resources::get_one::<Subject>))
+                .route(web::put().to(update_subject))
+                .route(web::delete().to(delete_subject)),
+        );
+}
+
+#[derive(Deserialize)]
+pub struct MoveBody {
+    pub parent_id: Option<i64>,
+}
+
+#[get("/subjects/{id}/tree")]
+async fn get_subject_tree(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = subjects_db::get_tree(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// `{"parent_id": null}` moves the subject to the top level.
+#[post("/subjects/{id}/move")]
+async fn move_subject(
+    id: web::Path<i64>,
+    json: web::Json<MoveBody>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let r = subjects_db::move_subject(pool.get_ref(), id.into_inner(), json.parent_id).await;
+
+    match r {
+        Ok(Move::Done) => HttpResponse::Ok().json("Moved"),
+        Ok(Move::Refused(message)) => HttpResponse::Conflict().json(CustomError::message(message)),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Same as the generic update, except that a new `parent_id` may not put the
+// subject below itself.
+async fn update_subject(
+    id: web::Path<i64>,
+    json: web::Json<Subject>,
+    repo: web::Data<dyn Repository<Subject>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let subject = match resources::check(json.into_inner(), Some(id), repo.get_ref()).await {
+        Ok(s) => s,
+        Err(resp) => return resp,
+    };
+    let r = subjects_db::update_subject(pool.get_ref(), id, subject).await;
+
+    match r {
+        Ok(Move::Done) => HttpResponse::Ok().json("Updated"),
+        Ok(Move::Refused(message)) => HttpResponse::Conflict().json(CustomError::message(message)),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// Subjects are only removed once they have no children left; their books
+// simply lose the subject.
+async fn delete_subject(
+    id: web::Path<i64>,
+    repo: web::Data<dyn Repository<Subject>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    match subjects_db::count_children(pool.get_ref(), id).await {
+        Ok(0) => {}
+        Ok(n) => {
+            return HttpResponse::Conflict().json(CustomError::message(format!(
+                "subject {} still has {} subjects below it",
+                id, n
+            )))
+        }
+        Err(e) => return HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+    let r = repo.delete(id).await;
+
+    match r {
+        Ok(_) => HttpResponse::Ok().json("Deleted"),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[get("/books/{id}/subjects")]
+async fn get_book_subjects(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = subjects_db::get_book_subjects(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+// The body lists the subject ids the book is filed under.
+#[put("/books/{id}/subjects")]
+async fn set_book_subjects(
+    id: web::Path<i64>,
+    json: web::Json<Vec<i64>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let mut ids = json.into_inner();
+    ids.sort_unstable();
+    ids.dedup();
+    let r = subjects_db::set_book_subjects(pool.get_ref(), id.into_inner(), &ids).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_subjects),
+            )
+            .await
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $req:expr, $uri:expr, $body:expr) => {{
+            let req = $req.uri(&$uri).set_json($body).to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    macro_rules! get_json {
+        ($app:expr, $uri:expr) => {{
+            let req = test::TestRequest::get().uri(&$uri).to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body: Value = test::read_body_json(resp).await;
+            body
+        }};
+    }
+
+    macro_rules! subject {
+        ($app:expr, $code:expr, $parent:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/subjects")
+                .set_json(json!({"code": $code, "name": $code, "parent_id": $parent}))
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::CREATED);
+            let body: Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap()
+        }};
+    }
+
+    macro_rules! file {
+        ($app:expr, $book_id:expr, $subjects:expr) => {{
+            let resp = send!(
+                $app,
+                test::TestRequest::put(),
+                format!("/books/{}/subjects", $book_id),
+                json!($subjects)
+            );
+            assert_eq!(resp.status(), http::StatusCode::OK);
+        }};
+    }
+
+    fn titles(body: &Value) -> Vec<&str> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["title"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_subject_paths() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+        let algebra = subject!(app, "512", maths);
+
+        let s = get_json!(app, format!("/subjects/{}", algebra));
+        assert_eq!(s["path"], format!("/{}/{}/{}/", science, maths, algebra));
+
+        let req = test::TestRequest::post()
+            .uri("/subjects")
+            .set_json(json!({"code": " 512 ", "name": "Algebra"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_move_subject() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+        let algebra = subject!(app, "512", maths);
+        let technology = subject!(app, "600", Value::Null);
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/subjects/{}", maths),
+            json!({"parent_id": technology})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let s = get_json!(app, format!("/subjects/{}", algebra));
+        assert_eq!(s["path"], format!("/{}/{}/{}/", technology, maths, algebra));
+
+        let resp = send!(
+            app,
+            test::TestRequest::post(),
+            format!("/subjects/{}/move", maths),
+            json!({"parent_id": null})
+        );
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let s = get_json!(app, format!("/subjects/{}", algebra));
+        assert_eq!(s["path"], format!("/{}/{}/", maths, algebra));
+        let s = get_json!(app, format!("/subjects/{}", maths));
+        assert_eq!(s["parent_id"], Value::Null);
+    }
+
+    #[actix_web::test]
+    async fn test_move_subject_below_itself() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+
+        for parent in [science, maths] {
+            let resp = send!(
+                app,
+                test::TestRequest::post(),
+                format!("/subjects/{}/move", science),
+                json!({ "parent_id": parent })
+            );
+            assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        }
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/subjects/{}", science),
+            json!({ "parent_id": maths })
+        );
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let s = get_json!(app, format!("/subjects/{}", science));
+        assert_eq!(s["path"], format!("/{}/", science));
+    }
+
+    #[actix_web::test]
+    async fn test_update_subject_is_atomic() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+        let technology = subject!(app, "600", Value::Null);
+        sqlx::query(
+            "CREATE TRIGGER keep_names BEFORE UPDATE OF name ON subjects \
+             BEGIN SELECT RAISE(ABORT, 'kept'); END",
+        )
+        .execute(&conn_pool)
+        .await
+        .unwrap();
+
+        let resp = send!(
+            app,
+            test::TestRequest::put(),
+            format!("/subjects/{}", maths),
+            json!({"name": "Mathematics", "parent_id": technology})
+        );
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let s = get_json!(app, format!("/subjects/{}", maths));
+        assert_eq!(s["parent_id"], science);
+        assert_eq!(s["path"], format!("/{}/{}/", science, maths));
+    }
+
+    #[actix_web::test]
+    async fn test_delete_subject_with_children() {
+        let conn_pool = test_pool().await;
+        let b = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+        file!(app, b, [maths]);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/subjects/{}", science))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/subjects/{}", maths))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(get_json!(app, format!("/books/{}/subjects", b)), json!([]));
+    }
+
+    #[actix_web::test]
+    async fn test_books_by_subject() {
+        let conn_pool = test_pool().await;
+        let general = book()
+            .title("Science Today")
+            .create(&conn_pool)
+            .await
+            .id
+            .unwrap();
+        let algebra_book = book().title("Algebra").create(&conn_pool).await.id.unwrap();
+        let cooking = book().title("Cooking").create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let maths = subject!(app, "510", science);
+        let algebra = subject!(app, "512", maths);
+        let technology = subject!(app, "600", Value::Null);
+        file!(app, general, [science]);
+        file!(app, algebra_book, [algebra, algebra]);
+        file!(app, cooking, [technology]);
+
+        let subjects = get_json!(app, format!("/books/{}/subjects", algebra_book));
+        assert_eq!(subjects.as_array().unwrap().len(), 1);
+
+        let books = get_json!(app, "/books?subject=500");
+        assert_eq!(titles(&books), vec!["Science Today"]);
+        let books = get_json!(app, "/books?subject=500&include_descendants=true");
+        assert_eq!(titles(&books), vec!["Science Today", "Algebra"]);
+        let books = get_json!(app, "/books?subject=999");
+        assert_eq!(titles(&books), Vec::<&str>::new());
+
+        let req = test::TestRequest::get()
+            .uri("/books?subject=500&include_descendants=maybe")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_subject_tree() {
+        let conn_pool = test_pool().await;
+        let general = book().create(&conn_pool).await.id.unwrap();
+        let algebra_book = book().create(&conn_pool).await.id.unwrap();
+        let app = app!(conn_pool);
+        let science = subject!(app, "500", Value::Null);
+        let physics = subject!(app, "530", science);
+        let maths = subject!(app, "510", science);
+        let algebra = subject!(app, "512", maths);
+        file!(app, general, [science, maths]);
+        file!(app, algebra_book, [algebra]);
+
+        let tree = get_json!(app, format!("/subjects/{}/tree", science));
+        assert_eq!(
+            tree,
+            json!({"id": science, "code": "500", "name": "500", "book_count": 1, "total_book_count": 2,
+            "children": [
+                {"id": maths, "code": "510", "name": "510", "book_count": 1, "total_book_count": 2,
+                    "children": [
+                        {"id": algebra, "code": "512", "name": "512", "book_count": 1,
+                            "total_book_count": 1, "children": []}
+                    ]},
+                {"id": physics, "code": "530", "name": "530", "book_count": 0, "total_book_count": 0,
+                    "children": []}
+            ]})
+        );
+
+        let tree = get_json!(app, format!("/subjects/{}/tree", algebra));
+        assert_eq!(tree["children"], json!([]));
+
+        let req = test::TestRequest::get()
+            .uri("/subjects/0/tree")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
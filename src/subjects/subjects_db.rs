@@ -0,0 +1,141 @@
+use super::subject::{Subject, SubjectCount, SubjectTree};
+use super::subjects_queries;
+use sqlx::{Error, Pool, Sqlite, Transaction};
+
+pub enum Move {
+    Done,
+    // The subject would end up below itself, with the reason.
+    Refused(String),
+}
+
+async fn ensure(tx: &mut Transaction<'_, Sqlite>, query: &str, id: i64) -> Result<(), Error> {
+    sqlx::query(query).bind(id).fetch_one(tx).await?;
+
+    Ok(())
+}
+
+// Whether the subject may go under `parent_id`, or to the top level when
+// there is none, with the reason when it may not.
+async fn check_move(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+    parent_id: Option<i64>,
+) -> Result<Move, Error> {
+    ensure(tx, &subjects_queries::get_subject_id_query(), id).await?;
+    if let Some(parent_id) = parent_id {
+        ensure(tx, &subjects_queries::get_subject_id_query(), parent_id).await?;
+        let below: Option<i64> = sqlx::query_scalar(&subjects_queries::get_descendant_query())
+            .bind(id)
+            .bind(parent_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if below.is_some() {
+            return Ok(Move::Refused(format!(
+                "subject {} cannot be moved below itself",
+                id
+            )));
+        }
+    }
+
+    Ok(Move::Done)
+}
+
+// Moves a subject, and with it its subtree, under `parent_id`, or to the top
+// level when there is none.
+pub async fn move_subject(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    parent_id: Option<i64>,
+) -> Result<Move, Error> {
+    let mut tx = pool.begin().await?;
+    if let Move::Refused(message) = check_move(&mut tx, id, parent_id).await? {
+        return Ok(Move::Refused(message));
+    }
+
+    sqlx::query(&subjects_queries::move_subject_query())
+        .bind(parent_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Move::Done)
+}
+
+// Like the generic update, fields left out are kept. A new `parent_id` is
+// checked and applied along with the rest, all or nothing.
+pub async fn update_subject(pool: &Pool<Sqlite>, id: i64, subject: Subject) -> Result<Move, Error> {
+    let mut tx = pool.begin().await?;
+    if subject.parent_id.is_some() {
+        if let Move::Refused(message) = check_move(&mut tx, id, subject.parent_id).await? {
+            return Ok(Move::Refused(message));
+        }
+    }
+
+    sqlx::query(&subjects_queries::update_subject_query())
+        .bind(subject.code)
+        .bind(subject.name)
+        .bind(subject.parent_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Move::Done)
+}
+
+pub async fn count_children(pool: &Pool<Sqlite>, id: i64) -> Result<i64, Error> {
+    sqlx::query_scalar(&subjects_queries::get_children_count_query())
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_tree(pool: &Pool<Sqlite>, id: i64) -> Result<SubjectTree, Error> {
+    let subjects = sqlx::query_as::<_, SubjectCount>(&subjects_queries::get_subtree_query())
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+    SubjectTree::build(id, subjects).ok_or(Error::RowNotFound)
+}
+
+pub async fn get_book_subjects(pool: &Pool<Sqlite>, book_id: i64) -> Result<Vec<Subject>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure(&mut tx, &subjects_queries::get_book_id_query(), book_id).await?;
+
+    sqlx::query_as::<_, Subject>(&subjects_queries::get_book_subjects_query())
+        .bind(book_id)
+        .fetch_all(&mut tx)
+        .await
+}
+
+// Replaces all subjects of a book in one transaction.
+pub async fn set_book_subjects(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    subject_ids: &[i64],
+) -> Result<Vec<Subject>, Error> {
+    let mut tx = pool.begin().await?;
+    ensure(&mut tx, &subjects_queries::get_book_id_query(), book_id).await?;
+    sqlx::query(&subjects_queries::delete_book_subjects_query())
+        .bind(book_id)
+        .execute(&mut tx)
+        .await?;
+    for subject_id in subject_ids {
+        ensure(
+            &mut tx,
+            &subjects_queries::get_subject_id_query(),
+            *subject_id,
+        )
+        .await?;
+        sqlx::query(&subjects_queries::create_book_subject_query())
+            .bind(book_id)
+            .bind(subject_id)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    get_book_subjects(pool, book_id).await
+}
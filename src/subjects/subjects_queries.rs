@@ -0,0 +1,92 @@
+use super::super::constants::{BOOKS_TABLE, BOOK_SUBJECTS_TABLE, SUBJECTS_TABLE};
+use super::super::resources::filter::Clause;
+use super::super::resources::value::Value;
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn get_subject_id_query() -> String {
+    format!("Select id From {} where id=?", SUBJECTS_TABLE)
+}
+
+pub fn get_book_subjects_query() -> String {
+    format!(
+        "Select s.* From {} s JOIN {} bs ON bs.subject_id = s.id where bs.book_id=? \
+         order by s.code",
+        SUBJECTS_TABLE, BOOK_SUBJECTS_TABLE
+    )
+}
+
+pub fn delete_book_subjects_query() -> String {
+    format!("DELETE From {} where book_id=?", BOOK_SUBJECTS_TABLE)
+}
+
+pub fn create_book_subject_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, subject_id) values (?, ?)",
+        BOOK_SUBJECTS_TABLE
+    )
+}
+
+// Finds `parent_id` within the subtree of the subject `id`, the subject itself
+// included.
+pub fn get_descendant_query() -> String {
+    format!(
+        "Select d.id From {s} d JOIN {s} s ON s.id=? \
+         where d.id=? and d.path LIKE s.path || '%'",
+        s = SUBJECTS_TABLE
+    )
+}
+
+pub fn get_children_count_query() -> String {
+    format!("Select COUNT(*) From {} where parent_id=?", SUBJECTS_TABLE)
+}
+
+pub fn move_subject_query() -> String {
+    format!("UPDATE {} SET parent_id=? where id=?", SUBJECTS_TABLE)
+}
+
+pub fn update_subject_query() -> String {
+    format!(
+        "UPDATE {} SET code=coalesce(?, code), name=coalesce(?, name), \
+         parent_id=coalesce(?, parent_id) where id=?",
+        SUBJECTS_TABLE
+    )
+}
+
+// The subtree of a subject with its book counts, each level ordered by code
+// and every parent listed before its children.
+pub fn get_subtree_query() -> String {
+    format!(
+        "Select d.id, d.code, d.name, d.parent_id, \
+         (SELECT COUNT(*) FROM {bs} bs WHERE bs.subject_id = d.id) AS book_count, \
+         (SELECT COUNT(DISTINCT bs.book_id) FROM {bs} bs JOIN {s} t ON t.id = bs.subject_id \
+           WHERE t.path LIKE d.path || '%') AS total_book_count \
+         From {s} d JOIN {s} r ON r.id=? where d.path LIKE r.path || '%' \
+         order by length(d.path) - length(replace(d.path, '/', '')), d.code, d.id",
+        s = SUBJECTS_TABLE,
+        bs = BOOK_SUBJECTS_TABLE
+    )
+}
+
+// Restricts `books` to those filed under the subject with the given code, or
+// anywhere below it when `descendants` is set.
+pub fn books_with_subject_clause(code: &str, descendants: bool) -> Clause {
+    let condition = if descendants {
+        format!(
+            "s.path LIKE (SELECT path FROM {} WHERE code = ?) || '%'",
+            SUBJECTS_TABLE
+        )
+    } else {
+        "s.code = ?".to_string()
+    };
+
+    Clause {
+        sql: format!(
+            "id IN (SELECT bs.book_id FROM {} bs JOIN {} s ON s.id = bs.subject_id WHERE {})",
+            BOOK_SUBJECTS_TABLE, SUBJECTS_TABLE, condition
+        ),
+        binds: vec![Value::Text(code.to_string())],
+    }
+}
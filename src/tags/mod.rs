@@ -0,0 +1,5 @@
+pub mod tag;
+#[allow(clippy::module_inception)]
+pub mod tags;
+mod tags_db;
+pub mod tags_queries;
@@ -0,0 +1,63 @@
+use super::super::constants::TAGS_TABLE;
+use super::super::resources::resource::{validate_not_blank, Resource};
+use super::super::resources::value::Value;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow, Clone)]
+pub struct Tag {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    #[serde(skip_deserializing)]
+    #[sqlx(default)]
+    pub usage_count: Option<i64>,
+}
+
+impl Resource for Tag {
+    const PATH: &'static str = "/tags";
+    const TABLE: &'static str = TAGS_TABLE;
+    const FIELDS: &'static [&'static str] = &["name"];
+    const FILTERABLE: &'static [&'static str] = &["name"];
+    const SORTABLE: &'static [&'static str] = &["id", "name", "usage_count"];
+    const SELECT: &'static str =
+        "*, (SELECT COUNT(*) FROM book_tags WHERE book_tags.tag_id = tags.id) AS usage_count";
+    const REQUIRED: &'static [&'static str] = &["name"];
+    const UNIQUE: &'static [&'static str] = &["name"];
+
+    fn id(&self) -> Option<i64> {
+        self.id
+    }
+
+    #[cfg(test)]
+    fn set_id(&mut self, id: i64) {
+        self.id = Some(id);
+    }
+
+    fn get(&self, field: &str) -> Value {
+        match field {
+            "name" => Value::from(self.name.clone()),
+            "usage_count" => Value::from(self.usage_count),
+            _ => Value::Null,
+        }
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, field: &str, value: Value) {
+        if field == "name" {
+            self.name = value.into();
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.name = self.name.as_deref().map(normalize_name);
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_not_blank("name", &self.name)
+    }
+}
+
+// Tag names are compared case-insensitively, so they are stored folded.
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
@@ -0,0 +1,242 @@
+use actix_web::{get, put, web, HttpResponse, Responder};
+use sqlx::{Pool, Sqlite};
+
+use super::super::resources::resources;
+use super::super::responses::CustomError;
+use super::tag::{normalize_name, Tag};
+use super::tags_db;
+
+pub fn config_tags(cfg: &mut web::ServiceConfig) {
+    resources::config::<Tag>(cfg);
+    cfg.service(get_book_tags).service(set_book_tags);
+}
+
+#[get("/books/{id}/tags")]
+async fn get_book_tags(id: web::Path<i64>, pool: web::Data<Pool<Sqlite>>) -> impl Responder {
+    let r = tags_db::get_book_tags(pool.get_ref(), id.into_inner()).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[put("/books/{id}/tags")]
+async fn set_book_tags(
+    id: web::Path<i64>,
+    json: web::Json<Vec<String>>,
+    pool: web::Data<Pool<Sqlite>>,
+) -> impl Responder {
+    let mut names: Vec<String> = json.iter().map(|n| normalize_name(n)).collect();
+    if names.iter().any(|n| n.is_empty()) {
+        return HttpResponse::BadRequest()
+            .json(CustomError::message("tag names must not be blank"));
+    }
+    names.sort();
+    names.dedup();
+    let r = tags_db::set_book_tags(pool.get_ref(), id.into_inner(), &names).await;
+
+    match r {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => HttpResponse::InternalServerError().json(CustomError::new(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use crate::test_utils::{self, book, test_pool};
+    use actix_web::{
+        http::{self},
+        test,
+    };
+    use serde_json::{json, Value};
+
+    macro_rules! app {
+        ($pool:expr) => {{
+            let repositories = test_utils::repositories(&$pool);
+            test::init_service(
+                App::new()
+                    .configure(move |cfg| repositories.config(cfg))
+                    .configure(books::books::config_books)
+                    .configure(super::config_tags),
+            )
+            .await
+        }};
+    }
+
+    fn values<'a>(body: &'a Value, key: &str) -> Vec<&'a Value> {
+        body.as_array().unwrap().iter().map(|v| &v[key]).collect()
+    }
+
+    macro_rules! tag_book {
+        ($app:expr, $book:expr, $tags:expr) => {{
+            let req = test::TestRequest::put()
+                .uri(&format!("/books/{}/tags", $book.id.unwrap()))
+                .set_json(json!($tags))
+                .to_request();
+            test::call_service(&$app, req).await
+        }};
+    }
+
+    #[actix_web::test]
+    async fn test_create_tag_is_case_insensitive_and_unique() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(json!({"name": " Fantasy "}))
+            .uri("/tags")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::post()
+            .set_json(json!({"name": "FANTASY"}))
+            .uri("/tags")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], created["id"]);
+        assert_eq!(body["location"], format!("/tags/{}", created["id"]));
+
+        let req = test::TestRequest::get().uri("/tags").to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(values(&body, "name"), vec!["fantasy"]);
+    }
+
+    #[actix_web::test]
+    async fn test_create_tag_requires_name() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::post()
+            .set_json(json!({}))
+            .uri("/tags")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "name is required");
+    }
+
+    #[actix_web::test]
+    async fn test_set_book_tags_replaces_the_set() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await;
+        let app = app!(conn_pool);
+
+        let resp = tag_book!(app, dune, ["SciFi", "classic"]);
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let resp = tag_book!(app, dune, ["classic", "Desert", "desert"]);
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(values(&body, "name"), vec!["classic", "desert"]);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/books/{}/tags", dune.id.unwrap()))
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(values(&body, "name"), vec!["classic", "desert"]);
+    }
+
+    #[actix_web::test]
+    async fn test_set_book_tags_unknown_book() {
+        let conn_pool = test_pool().await;
+        let app = app!(conn_pool);
+
+        let req = test::TestRequest::put()
+            .uri("/books/42/tags")
+            .set_json(json!(["classic"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = test::TestRequest::get().uri("/tags").to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert!(body.as_array().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_get_tags_usage_counts() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await;
+        let emma = book().title("Emma").create(&conn_pool).await;
+        let app = app!(conn_pool);
+        tag_book!(app, dune, ["classic", "scifi"]);
+        tag_book!(app, emma, ["classic"]);
+
+        let req = test::TestRequest::get()
+            .uri("/tags?sort=-usage_count")
+            .to_request();
+        let body: Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(values(&body, "name"), vec!["classic", "scifi"]);
+        assert_eq!(values(&body, "usage_count"), vec![2, 1]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_by_tags() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").create(&conn_pool).await;
+        let emma = book().title("Emma").create(&conn_pool).await;
+        book().title("Untagged").create(&conn_pool).await;
+        let app = app!(conn_pool);
+        tag_book!(app, dune, ["classic", "scifi"]);
+        tag_book!(app, emma, ["classic", "romance"]);
+
+        for (uri, expected) in [
+            ("/books?tags=Classic,scifi", vec!["Dune"]),
+            ("/books?tags=classic", vec!["Dune", "Emma"]),
+            (
+                "/books?tags=scifi,romance&tag_mode=any",
+                vec!["Dune", "Emma"],
+            ),
+            ("/books?tags=scifi,romance&tag_mode=all", vec![]),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK, "{}", uri);
+            let body: Value = test::read_body_json(resp).await;
+            assert_eq!(values(&body, "title"), expected, "{}", uri);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/books?tags=scifi&tag_mode=some")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_get_books_tag_facet() {
+        let conn_pool = test_pool().await;
+        let dune = book().title("Dune").language("en").create(&conn_pool).await;
+        let emma = book().title("Emma").language("en").create(&conn_pool).await;
+        let nana = book().title("Nana").language("fr").create(&conn_pool).await;
+        let app = app!(conn_pool);
+        tag_book!(app, dune, ["classic", "scifi"]);
+        tag_book!(app, emma, ["classic", "romance"]);
+        tag_book!(app, nana, ["classic"]);
+
+        // The tag counts leave out `tags` but keep `language`.
+        let req = test::TestRequest::get()
+            .uri("/books?tags=scifi&language=en&facets=tag,language&envelope=true")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(values(&body["hits"], "title"), vec!["Dune"]);
+        assert_eq!(
+            body["facets"],
+            json!({
+                "tag": [
+                    {"value": "classic", "count": 2},
+                    {"value": "romance", "count": 1},
+                    {"value": "scifi", "count": 1}
+                ],
+                "language": [{"value": "en", "count": 1}]
+            })
+        );
+    }
+}
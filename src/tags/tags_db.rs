@@ -0,0 +1,46 @@
+use super::tag::Tag;
+use super::tags_queries;
+use sqlx::{Error, Pool, Sqlite};
+
+pub async fn get_book_tags(pool: &Pool<Sqlite>, book_id: i64) -> Result<Vec<Tag>, Error> {
+    let query = tags_queries::get_book_tags_query();
+
+    sqlx::query_as::<_, Tag>(&query)
+        .bind(book_id)
+        .fetch_all(pool)
+        .await
+}
+
+// Replaces the whole tag set of a book in one transaction, creating any tag
+// that does not exist yet.
+pub async fn set_book_tags(
+    pool: &Pool<Sqlite>,
+    book_id: i64,
+    names: &[String],
+) -> Result<Vec<Tag>, Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&tags_queries::get_book_id_query())
+        .bind(book_id)
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query(&tags_queries::delete_book_tags_query())
+        .bind(book_id)
+        .execute(&mut tx)
+        .await?;
+    for name in names {
+        sqlx::query(&tags_queries::create_tag_if_missing_query())
+            .bind(name)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(&tags_queries::create_book_tag_query())
+            .bind(book_id)
+            .bind(name)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    get_book_tags(pool, book_id).await
+}
@@ -0,0 +1,49 @@
+use super::super::constants::{BOOKS_TABLE, BOOK_TAGS_TABLE, TAGS_TABLE};
+use super::super::resources::filter::Clause;
+use super::super::resources::value::Value;
+
+pub fn get_book_tags_query() -> String {
+    format!(
+        "Select t.*, (SELECT COUNT(*) FROM {bt} WHERE {bt}.tag_id = t.id) AS usage_count \
+         From {t} t JOIN {bt} bt ON bt.tag_id = t.id where bt.book_id=? order by t.name",
+        t = TAGS_TABLE,
+        bt = BOOK_TAGS_TABLE
+    )
+}
+
+pub fn get_book_id_query() -> String {
+    format!("Select id From {} where id=?", BOOKS_TABLE)
+}
+
+pub fn create_tag_if_missing_query() -> String {
+    format!("INSERT OR IGNORE INTO {} (name) values (?)", TAGS_TABLE)
+}
+
+pub fn delete_book_tags_query() -> String {
+    format!("DELETE From {} where book_id=?", BOOK_TAGS_TABLE)
+}
+
+pub fn create_book_tag_query() -> String {
+    format!(
+        "INSERT INTO {} (book_id, tag_id) SELECT ?, id From {} where name=?",
+        BOOK_TAGS_TABLE, TAGS_TABLE
+    )
+}
+
+// Restricts `books` to rows tagged with any (or all) of `names`.
+pub fn books_with_tags_clause(names: Vec<String>, all: bool) -> Clause {
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let mut sql = format!(
+        "id IN (SELECT bt.book_id From {} bt JOIN {} t ON t.id = bt.tag_id \
+         where t.name IN ({}) GROUP BY bt.book_id",
+        BOOK_TAGS_TABLE, TAGS_TABLE, placeholders
+    );
+    let mut binds: Vec<Value> = names.into_iter().map(Value::Text).collect();
+    if all {
+        sql.push_str(" HAVING COUNT(DISTINCT t.id) = ?");
+        binds.push(Value::Integer(binds.len() as i64));
+    }
+    sql.push(')');
+
+    Clause { sql, binds }
+}
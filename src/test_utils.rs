@@ -0,0 +1,224 @@
+use super::authors::author::Author;
+use super::books::book::Book;
+use super::db;
+use super::members::member::Member;
+use super::publishers::publisher::Publisher;
+use super::repositories::Repositories;
+use super::resources::resource::Resource;
+use super::resources::resources_db::SqliteRepository;
+use super::resources::resources_repository::Repository;
+use actix_web::web;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+
+// Every call returns a private in-memory database with all migrations
+// applied.
+pub async fn test_pool() -> Pool<Sqlite> {
+    db::establish_memory_connection().await.unwrap()
+}
+
+pub fn repository<R: Resource>(pool: &Pool<Sqlite>) -> web::Data<dyn Repository<R>> {
+    let repo: Arc<dyn Repository<R>> = Arc::new(SqliteRepository::<R>::new(pool.clone()));
+    web::Data::from(repo)
+}
+
+pub fn repositories(pool: &Pool<Sqlite>) -> Repositories {
+    Repositories::sqlite(pool.clone())
+}
+
+async fn insert<R: Resource>(pool: &Pool<Sqlite>, mut resource: R) -> R {
+    let id = SqliteRepository::<R>::new(pool.clone())
+        .create(resource.clone())
+        .await
+        .unwrap();
+    resource.set_id(id);
+    resource
+}
+
+pub struct BookBuilder {
+    book: Book,
+}
+
+pub fn book() -> BookBuilder {
+    BookBuilder {
+        book: Book {
+            id: None,
+            title: Some("title".to_string()),
+            author: Some("author".to_string()),
+            publisher_id: None,
+            isbn: None,
+            publication_date: None,
+            language: None,
+            page_count: None,
+            edition: None,
+            description: None,
+            average_rating: None,
+            review_count: None,
+            copy_count: None,
+            available_copies: None,
+            contributors: None,
+            series: None,
+            metadata: None,
+        },
+    }
+}
+
+impl BookBuilder {
+    pub fn title(mut self, title: &str) -> Self {
+        self.book.title = Some(title.to_string());
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.book.author = Some(author.to_string());
+        self
+    }
+
+    pub fn publisher(mut self, publisher: &Publisher) -> Self {
+        self.book.publisher_id = publisher.id;
+        self
+    }
+
+    pub fn isbn(mut self, isbn: &str) -> Self {
+        self.book.isbn = Some(isbn.to_string());
+        self
+    }
+
+    pub fn published(mut self, date: &str) -> Self {
+        self.book.publication_date = Some(date.to_string());
+        self
+    }
+
+    pub fn language(mut self, language: &str) -> Self {
+        self.book.language = Some(language.to_string());
+        self
+    }
+
+    pub fn page_count(mut self, page_count: i64) -> Self {
+        self.book.page_count = Some(page_count);
+        self
+    }
+
+    pub fn build(self) -> Book {
+        self.book
+    }
+
+    pub async fn create(self, pool: &Pool<Sqlite>) -> Book {
+        insert(pool, self.book).await
+    }
+}
+
+pub struct AuthorBuilder {
+    author: Author,
+}
+
+pub fn author() -> AuthorBuilder {
+    AuthorBuilder {
+        author: Author {
+            id: None,
+            name: Some("name".to_string()),
+            birth_date: None,
+            death_date: None,
+            nationality: None,
+            biography: None,
+            isni: None,
+            viaf: None,
+            orcid: None,
+            wikidata: None,
+            metadata: None,
+        },
+    }
+}
+
+impl AuthorBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.author.name = Some(name.to_string());
+        self
+    }
+
+    pub fn build(self) -> Author {
+        self.author
+    }
+
+    pub async fn create(self, pool: &Pool<Sqlite>) -> Author {
+        insert(pool, self.author).await
+    }
+}
+
+pub struct PublisherBuilder {
+    publisher: Publisher,
+}
+
+pub fn publisher() -> PublisherBuilder {
+    PublisherBuilder {
+        publisher: Publisher {
+            id: None,
+            name: Some("name".to_string()),
+            country: Some("country".to_string()),
+            website: None,
+        },
+    }
+}
+
+impl PublisherBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.publisher.name = Some(name.to_string());
+        self
+    }
+
+    pub async fn create(self, pool: &Pool<Sqlite>) -> Publisher {
+        insert(pool, self.publisher).await
+    }
+}
+
+pub struct MemberBuilder {
+    member: Member,
+}
+
+pub fn member() -> MemberBuilder {
+    MemberBuilder {
+        member: Member {
+            id: None,
+            name: Some("name".to_string()),
+            email: None,
+            member_type: None,
+        },
+    }
+}
+
+impl MemberBuilder {
+    pub fn email(mut self, email: &str) -> Self {
+        self.member.email = Some(email.to_string());
+        self
+    }
+
+    pub fn member_type(mut self, member_type: &str) -> Self {
+        self.member.member_type = Some(member_type.to_string());
+        self
+    }
+
+    pub async fn create(self, pool: &Pool<Sqlite>) -> Member {
+        insert(pool, self.member).await
+    }
+}
+
+pub struct CopyBuilder {
+    book_id: i64,
+}
+
+pub fn copy(book_id: i64) -> CopyBuilder {
+    CopyBuilder { book_id }
+}
+
+impl CopyBuilder {
+    pub async fn create(self, pool: &Pool<Sqlite>) -> i64 {
+        sqlx::query_scalar(
+            "INSERT INTO copies (book_id, barcode) values (?, lower(hex(randomblob(8)))) \
+             RETURNING id",
+        )
+        .bind(self.book_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+}